@@ -2,15 +2,56 @@ use std::collections::VecDeque;
 use std::fmt::{self, Display, Formatter};
 use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::{Path, PathBuf};
-
-use super::summary::{parse_summary, Link, SectionNumber, Summary, SummaryItem};
-use crate::config::BuildConfig;
+use std::path::{Component, Path, PathBuf};
+
+use super::front_matter::{split_front_matter, Metadata};
+use super::summary::{
+    expand_nested_summaries, parse_summary, ExternalLink, Link, SectionNumber, Summary, SummaryItem,
+};
+use crate::config::{BuildConfig, Numbering};
+use crate::diagnostic::Diagnostic;
 use crate::errors::*;
 
 /// Load a book into memory from its `src/` directory.
 pub fn load_book<P: AsRef<Path>>(src_dir: P, cfg: &BuildConfig) -> Result<Book> {
     let src_dir = src_dir.as_ref();
+    let summary = read_summary(src_dir)?;
+
+    if cfg.create_missing {
+        create_missing(src_dir, &summary).with_context(|| "Unable to create missing chapters")?;
+    }
+
+    load_book_from_disk(&summary, src_dir)
+}
+
+/// Load a translation of a multilingual book from its `src/<language>`
+/// directory.
+///
+/// Chapters which haven't been translated yet are loaded from
+/// `fallback_dir`, the source directory of the book's default language. If
+/// the translation has no `SUMMARY.md` of its own, the whole book is loaded
+/// from `fallback_dir`.
+pub fn load_translation<P, Q>(src_dir: P, fallback_dir: Q, cfg: &BuildConfig) -> Result<Book>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let src_dir = src_dir.as_ref();
+    let fallback_dir = fallback_dir.as_ref();
+
+    if !src_dir.join("SUMMARY.md").exists() {
+        debug!(
+            "No SUMMARY.md in {}, using the default language",
+            src_dir.display()
+        );
+        return load_book(fallback_dir, cfg);
+    }
+
+    let summary = read_summary(src_dir)?;
+    load_book_from_disk_with_fallback(&summary, src_dir, Some(fallback_dir))
+}
+
+fn read_summary(src_dir: &Path) -> Result<Summary> {
     let summary_md = src_dir.join("SUMMARY.md");
 
     let mut summary_content = String::new();
@@ -19,13 +60,12 @@ pub fn load_book<P: AsRef<Path>>(src_dir: P, cfg: &BuildConfig) -> Result<Book>
         .read_to_string(&mut summary_content)?;
 
     let summary = parse_summary(&summary_content)
+        .map_err(|mut e| {
+            Diagnostic::set_file_in(&mut e, &summary_md);
+            e
+        })
         .with_context(|| format!("Summary parsing failed for file={:?}", summary_md))?;
-
-    if cfg.create_missing {
-        create_missing(&src_dir, &summary).with_context(|| "Unable to create missing chapters")?;
-    }
-
-    load_book_from_disk(&summary, src_dir)
+    expand_nested_summaries(summary, src_dir)
 }
 
 fn create_missing(src_dir: &Path, summary: &Summary) -> Result<()> {
@@ -113,6 +153,70 @@ impl Book {
         self.sections.push(item.into());
         self
     }
+
+    /// The label of each item's section number, written in the numbering
+    /// style of the part it's in, in the same order as [`iter()`]. Items
+    /// without a section number have no label.
+    ///
+    /// [`iter()`]: #method.iter
+    pub fn section_labels(&self, numbering: &Numbering) -> Vec<Option<String>> {
+        // Part titles are only ever at the top level, before their chapters.
+        let mut part = None;
+        self.iter()
+            .map(|item| match *item {
+                BookItem::PartTitle(ref title) => {
+                    part = Some(title.as_str());
+                    None
+                }
+                BookItem::Chapter(ref ch) => ch
+                    .number
+                    .as_ref()
+                    .map(|number| number.format_with(numbering.style(part))),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Number the chapters of the parts configured to restart their numbering
+/// from the start again, instead of continuing from the previous part.
+pub(crate) fn restart_part_numbering(book: &mut Book, numbering: &Numbering) {
+    fn set_first(items: &mut [BookItem], first: u32) {
+        for item in items {
+            if let BookItem::Chapter(ref mut ch) = *item {
+                if let Some(ref mut number) = ch.number {
+                    number[0] = first;
+                }
+                set_first(&mut ch.sub_items, first);
+            }
+        }
+    }
+
+    if numbering.parts.is_empty() {
+        return;
+    }
+
+    // How many of the top-level chapters so far are numbered, and how many
+    // of those were before the current part started again.
+    let mut numbered = 0;
+    let mut offset = 0;
+    for item in &mut book.sections {
+        match *item {
+            BookItem::PartTitle(ref title) if numbering.restarts(title) => offset = numbered,
+            BookItem::Chapter(ref mut ch) => {
+                let first = match ch.number {
+                    Some(ref mut number) if !number.is_empty() => {
+                        numbered += 1;
+                        number[0] = numbered - offset;
+                        number[0]
+                    }
+                    _ => continue,
+                };
+                set_first(&mut ch.sub_items, first);
+            }
+            _ => {}
+        }
+    }
 }
 
 pub fn for_each_mut<'a, F, I>(func: &mut F, items: I)
@@ -138,6 +242,9 @@ pub enum BookItem {
     Separator,
     /// A part title.
     PartTitle(String),
+    /// A link to a page outside the book, which is listed in the table of
+    /// contents but doesn't have a page of its own.
+    ExternalLink(ExternalLink),
 }
 
 impl From<Chapter> for BookItem {
@@ -164,6 +271,14 @@ pub struct Chapter {
     pub source_path: Option<PathBuf>,
     /// An ordered list of the names of each chapter above this one in the hierarchy.
     pub parent_names: Vec<String>,
+    /// The metadata from the chapter's front matter, if it has any.
+    #[serde(default)]
+    pub metadata: Metadata,
+    /// Whether the chapter is left out of the table of contents, the
+    /// navigation between chapters and the print page, while still being
+    /// rendered.
+    #[serde(default)]
+    pub hidden: bool,
 }
 
 impl Chapter {
@@ -209,6 +324,14 @@ impl Chapter {
 /// You need to pass in the book's source directory because all the links in
 /// `SUMMARY.md` give the chapter locations relative to it.
 pub(crate) fn load_book_from_disk<P: AsRef<Path>>(summary: &Summary, src_dir: P) -> Result<Book> {
+    load_book_from_disk_with_fallback(summary, src_dir, None)
+}
+
+fn load_book_from_disk_with_fallback<P: AsRef<Path>>(
+    summary: &Summary,
+    src_dir: P,
+    fallback_dir: Option<&Path>,
+) -> Result<Book> {
     debug!("Loading the book from disk");
     let src_dir = src_dir.as_ref();
 
@@ -221,7 +344,7 @@ pub(crate) fn load_book_from_disk<P: AsRef<Path>>(summary: &Summary, src_dir: P)
     let mut chapters = Vec::new();
 
     for summary_item in summary_items {
-        let chapter = load_summary_item(summary_item, src_dir, Vec::new())?;
+        let chapter = load_summary_item(summary_item, src_dir, fallback_dir, Vec::new())?;
         chapters.push(chapter);
     }
 
@@ -234,20 +357,23 @@ pub(crate) fn load_book_from_disk<P: AsRef<Path>>(summary: &Summary, src_dir: P)
 fn load_summary_item<P: AsRef<Path> + Clone>(
     item: &SummaryItem,
     src_dir: P,
+    fallback_dir: Option<&Path>,
     parent_names: Vec<String>,
 ) -> Result<BookItem> {
     match item {
         SummaryItem::Separator => Ok(BookItem::Separator),
         SummaryItem::Link(ref link) => {
-            load_chapter(link, src_dir, parent_names).map(BookItem::Chapter)
+            load_chapter(link, src_dir, fallback_dir, parent_names).map(BookItem::Chapter)
         }
         SummaryItem::PartTitle(title) => Ok(BookItem::PartTitle(title.clone())),
+        SummaryItem::ExternalLink(link) => Ok(BookItem::ExternalLink(link.clone())),
     }
 }
 
 fn load_chapter<P: AsRef<Path>>(
     link: &Link,
     src_dir: P,
+    fallback_dir: Option<&Path>,
     parent_names: Vec<String>,
 ) -> Result<Chapter> {
     let src_dir = src_dir.as_ref();
@@ -255,14 +381,34 @@ fn load_chapter<P: AsRef<Path>>(
     let mut ch = if let Some(ref link_location) = link.location {
         debug!("Loading {} ({})", link.name, link_location.display());
 
-        let location = if link_location.is_absolute() {
+        let mut location = if link_location.is_absolute() {
             link_location.clone()
         } else {
             src_dir.join(link_location)
         };
+        // Where the chapter's source file is, relative to `src_dir`
+        let mut source_path = None;
+
+        if let Some(fallback_dir) = fallback_dir {
+            if !location.exists() && link_location.is_relative() {
+                debug!(
+                    "{} hasn't been translated, falling back to {}",
+                    link_location.display(),
+                    fallback_dir.display()
+                );
+                location = fallback_dir.join(link_location);
+                source_path = Some(relative_path(fallback_dir, src_dir).join(link_location));
+            }
+        }
 
-        let mut f = File::open(&location)
-            .with_context(|| format!("Chapter file not found, {}", link_location.display()))?;
+        let mut f = File::open(&location).with_context(|| {
+            Diagnostic::error(format!(
+                "Chapter file not found, {}",
+                link_location.display()
+            ))
+            .with_file(&location)
+            .with_note(format!("referenced by \"{}\" in SUMMARY.md", link.name))
+        })?;
 
         let mut content = String::new();
         f.read_to_string(&mut content).with_context(|| {
@@ -273,11 +419,29 @@ fn load_chapter<P: AsRef<Path>>(
             content.replace_range(..3, "");
         }
 
-        let stripped = location
-            .strip_prefix(&src_dir)
-            .expect("Chapters are always inside a book");
+        let (metadata, content, diagnostic) = split_front_matter(&content);
+        if let Some(diagnostic) = diagnostic {
+            diagnostic.with_file(&location).emit();
+        }
 
-        Chapter::new(&link.name, content, stripped, parent_names.clone())
+        let stripped = match source_path {
+            Some(_) => link_location.as_path(),
+            None => location
+                .strip_prefix(src_dir)
+                .expect("Chapters are always inside a book"),
+        };
+
+        let mut ch = Chapter::new(
+            &link.name,
+            content.to_string(),
+            stripped,
+            parent_names.clone(),
+        );
+        if let Some(source_path) = source_path {
+            ch.source_path = Some(source_path);
+        }
+        ch.metadata = metadata;
+        ch
     } else {
         Chapter::new_draft(&link.name, parent_names.clone())
     };
@@ -285,12 +449,13 @@ fn load_chapter<P: AsRef<Path>>(
     let mut sub_item_parents = parent_names;
 
     ch.number = link.number.clone();
+    ch.hidden = link.hidden;
 
     sub_item_parents.push(link.name.clone());
     let sub_items = link
         .nested_items
         .iter()
-        .map(|i| load_summary_item(i, src_dir, sub_item_parents.clone()))
+        .map(|i| load_summary_item(i, src_dir, fallback_dir, sub_item_parents.clone()))
         .collect::<Result<Vec<_>>>()?;
 
     ch.sub_items = sub_items;
@@ -298,6 +463,22 @@ fn load_chapter<P: AsRef<Path>>(
     Ok(ch)
 }
 
+/// The relative path from the directory `base` to `path`, like `../en` from
+/// `src/ja` to `src/en`.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let mut path_components = path.components().peekable();
+    let mut base_components = base.components().peekable();
+    while path_components.peek().is_some() && path_components.peek() == base_components.peek() {
+        path_components.next();
+        base_components.next();
+    }
+
+    base_components
+        .map(|_| Component::ParentDir)
+        .chain(path_components)
+        .collect()
+}
+
 /// A depth-first iterator over the items in a book.
 ///
 /// # Note
@@ -396,7 +577,7 @@ And here is some \
             Vec::new(),
         );
 
-        let got = load_chapter(&link, temp_dir.path(), Vec::new()).unwrap();
+        let got = load_chapter(&link, temp_dir.path(), None, Vec::new()).unwrap();
         assert_eq!(got, should_be);
     }
 
@@ -419,7 +600,33 @@ And here is some \
             Vec::new(),
         );
 
-        let got = load_chapter(&link, temp_dir.path(), Vec::new()).unwrap();
+        let got = load_chapter(&link, temp_dir.path(), None, Vec::new()).unwrap();
+        assert_eq!(got, should_be);
+    }
+
+    #[test]
+    fn load_a_single_chapter_with_front_matter_from_disk() {
+        let temp_dir = TempFileBuilder::new().prefix("book").tempdir().unwrap();
+
+        let chapter_path = temp_dir.path().join("chapter_1.md");
+        File::create(&chapter_path)
+            .unwrap()
+            .write_all(("---\nreviewed: 2021-06-01\n---\n".to_owned() + DUMMY_SRC).as_bytes())
+            .unwrap();
+
+        let link = Link::new("Chapter 1", chapter_path);
+
+        let mut should_be = Chapter::new(
+            "Chapter 1",
+            DUMMY_SRC.to_string(),
+            "chapter_1.md",
+            Vec::new(),
+        );
+        should_be
+            .metadata
+            .insert(String::from("reviewed"), "2021-06-01".into());
+
+        let got = load_chapter(&link, temp_dir.path(), None, Vec::new()).unwrap();
         assert_eq!(got, should_be);
     }
 
@@ -427,8 +634,23 @@ And here is some \
     fn cant_load_a_nonexistent_chapter() {
         let link = Link::new("Chapter 1", "/foo/bar/baz.md");
 
-        let got = load_chapter(&link, "", Vec::new());
+        let got = load_chapter(&link, "", None, Vec::new());
         assert!(got.is_err());
+
+        let diag = Diagnostic::from_error(&got.unwrap_err());
+        assert_eq!(diag.file, Some(PathBuf::from("/foo/bar/baz.md")));
+        assert_eq!(diag.notes, ["referenced by \"Chapter 1\" in SUMMARY.md"]);
+    }
+
+    #[test]
+    fn summary_errors_point_at_the_summary_file() {
+        let temp = TempFileBuilder::new().prefix("book").tempdir().unwrap();
+        let src = "# Summary\n\n- [First](first.md)\n\n[Suffix](suffix.md)\n\n- [Late](late.md)\n";
+        fs::write(temp.path().join("SUMMARY.md"), src).unwrap();
+
+        let diag = Diagnostic::from_error(&read_summary(temp.path()).unwrap_err());
+        assert_eq!(diag.file, Some(temp.path().join("SUMMARY.md")));
+        assert_eq!(diag.span.map(|span| span.line), Some(7));
     }
 
     #[test]
@@ -443,6 +665,8 @@ And here is some \
             source_path: Some(PathBuf::from("second.md")),
             parent_names: vec![String::from("Chapter 1")],
             sub_items: Vec::new(),
+            metadata: Metadata::new(),
+            hidden: false,
         };
         let should_be = BookItem::Chapter(Chapter {
             name: String::from("Chapter 1"),
@@ -456,9 +680,12 @@ And here is some \
                 BookItem::Separator,
                 BookItem::Chapter(nested.clone()),
             ],
+            metadata: Metadata::new(),
+            hidden: false,
         });
 
-        let got = load_summary_item(&SummaryItem::Link(root), temp.path(), Vec::new()).unwrap();
+        let got =
+            load_summary_item(&SummaryItem::Link(root), temp.path(), None, Vec::new()).unwrap();
         assert_eq!(got, should_be);
     }
 
@@ -485,6 +712,59 @@ And here is some \
         assert_eq!(got, should_be);
     }
 
+    #[test]
+    fn relative_paths_between_directories() {
+        let relative = |path: &str, base: &str| relative_path(Path::new(path), Path::new(base));
+
+        assert_eq!(
+            relative("book/src/en", "book/src/ja"),
+            PathBuf::from("../en")
+        );
+        assert_eq!(relative("book/src/en", "book/src"), PathBuf::from("en"));
+        assert_eq!(
+            relative("book/en", "book/src/ja"),
+            PathBuf::from("../../en")
+        );
+    }
+
+    #[test]
+    fn appendices_are_lettered_from_the_start() {
+        let numbered = |name: &str, number: Vec<u32>| Chapter {
+            name: name.to_string(),
+            number: Some(SectionNumber(number)),
+            ..Default::default()
+        };
+        let mut first = numbered("First", vec![1]);
+        first.sub_items.push(numbered("Nested", vec![1, 1]).into());
+        let mut appendix = numbered("Glossary", vec![3]);
+        appendix
+            .sub_items
+            .push(numbered("Terms", vec![3, 1]).into());
+        let mut book = Book::new();
+        book.push_item(first)
+            .push_item(numbered("Second", vec![2]))
+            .push_item(BookItem::PartTitle("Appendices".to_string()))
+            .push_item(appendix)
+            .push_item(numbered("Index", vec![4]));
+
+        let mut numbering = Numbering::default();
+        numbering.parts.insert(
+            "Appendices".to_string(),
+            crate::config::PartNumbering {
+                appendix: true,
+                ..Default::default()
+            },
+        );
+        restart_part_numbering(&mut book, &numbering);
+
+        let labels: Vec<_> = book
+            .section_labels(&numbering)
+            .into_iter()
+            .flatten()
+            .collect();
+        assert_eq!(labels, ["1.", "1.1.", "2.", "A.", "A.1.", "B."]);
+    }
+
     #[test]
     fn book_iter_iterates_over_sequential_items() {
         let book = Book {
@@ -532,6 +812,8 @@ And here is some \
                             Vec::new(),
                         )),
                     ],
+                    metadata: Metadata::new(),
+                    hidden: false,
                 }),
                 BookItem::Separator,
             ],
@@ -585,6 +867,8 @@ And here is some \
                             Vec::new(),
                         )),
                     ],
+                    metadata: Metadata::new(),
+                    hidden: false,
                 }),
                 BookItem::Separator,
             ],
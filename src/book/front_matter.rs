@@ -0,0 +1,259 @@
+//! Parsing of the metadata block which may start a chapter's source file.
+//!
+//! Front matter is either YAML, fenced by `---` lines, or TOML, fenced by
+//! `+++` lines:
+//!
+//! ```markdown
+//! ---
+//! description: An introduction to the book
+//! authors: [Jane Doe]
+//! ---
+//!
+//! # Introduction
+//! ```
+
+use crate::diagnostic::Diagnostic;
+use std::collections::BTreeMap;
+
+/// The metadata attached to a chapter, keyed by name.
+pub type Metadata = BTreeMap<String, serde_json::Value>;
+
+/// Split the front matter (if any) off the start of a chapter, returning the
+/// parsed metadata and the rest of the chapter's content.
+///
+/// A `---` line is also a horizontal rule in markdown, so the block is only
+/// front matter if it's closed and parses as a table of metadata. Otherwise
+/// the content is returned unchanged. If the block is malformed, a warning
+/// pointing at the problem is returned as well, for the caller to emit once
+/// it knows which file the content came from.
+pub(crate) fn split_front_matter(content: &str) -> (Metadata, &str, Option<Diagnostic>) {
+    let (fence, is_yaml) = if starts_with_fence(content, "---") {
+        ("---", true)
+    } else if starts_with_fence(content, "+++") {
+        ("+++", false)
+    } else {
+        return (Metadata::new(), content, None);
+    };
+
+    let body_start = content.find('\n').map(|i| i + 1).unwrap_or(content.len());
+    let (front_matter, rest) = match find_closing_fence(&content[body_start..], fence) {
+        Some((end, rest)) => (
+            &content[body_start..body_start + end],
+            &content[body_start + rest..],
+        ),
+        None => return (Metadata::new(), content, None),
+    };
+
+    match parse_metadata(front_matter, is_yaml) {
+        Ok(Some(metadata)) => (metadata, rest, None),
+        Ok(None) => {
+            debug!("Not treating the `{}` block as front matter", fence);
+            (Metadata::new(), content, None)
+        }
+        Err(e) => {
+            let start = match e.position {
+                Some((line, column)) => body_start + offset_of(front_matter, line, column),
+                None => 0,
+            };
+            let end = content[start..]
+                .find('\n')
+                .map(|i| start + i)
+                .unwrap_or_else(|| content.len());
+            let diagnostic = Diagnostic::warning(
+                "Unable to parse the front matter, so it's rendered as part of the chapter",
+            )
+            .with_span(content, start..end, Some(e.message));
+            (Metadata::new(), content, Some(diagnostic))
+        }
+    }
+}
+
+/// Why the front matter couldn't be parsed, and the 0-based line and column
+/// of the front matter the problem is at, if it's known.
+struct ParseError {
+    message: String,
+    position: Option<(usize, usize)>,
+}
+
+/// Parse the text of the front matter, if it's a table of metadata.
+fn parse_metadata(
+    front_matter: &str,
+    is_yaml: bool,
+) -> std::result::Result<Option<Metadata>, ParseError> {
+    if is_yaml {
+        // An empty block would just be two horizontal rules, and one with
+        // only text in between is a heading and a rule
+        if front_matter.trim().is_empty() {
+            return Ok(None);
+        }
+        let value: serde_yaml::Value =
+            serde_yaml::from_str(front_matter).map_err(|e| ParseError {
+                message: e.to_string(),
+                position: e
+                    .location()
+                    .map(|location| (location.line().saturating_sub(1), location.column())),
+            })?;
+        if !value.is_mapping() {
+            return Ok(None);
+        }
+        serde_yaml::from_value(value)
+            .map(Some)
+            .map_err(|e| ParseError {
+                message: e.to_string(),
+                position: None,
+            })
+    } else if front_matter.trim().is_empty() {
+        Ok(Some(Metadata::new()))
+    } else {
+        let table: toml::value::Table = toml::from_str(front_matter).map_err(|e| ParseError {
+            message: e.to_string(),
+            position: e.line_col(),
+        })?;
+        Ok(Some(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ))
+    }
+}
+
+/// The byte offset of a 0-based line and column (in characters) of `text`.
+fn offset_of(text: &str, line: usize, column: usize) -> usize {
+    let line_start = match line {
+        0 => 0,
+        _ => text
+            .match_indices('\n')
+            .nth(line - 1)
+            .map(|(i, _)| i + 1)
+            .unwrap_or_else(|| text.len()),
+    };
+    text[line_start..]
+        .char_indices()
+        .take_while(|&(_, c)| c != '\n')
+        .nth(column)
+        .map(|(i, _)| line_start + i)
+        .unwrap_or(line_start)
+}
+
+/// Convert a TOML value to JSON, representing dates and times as strings.
+fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    use serde_json::Value;
+
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Value::from(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(array) => array.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn starts_with_fence(content: &str, fence: &str) -> bool {
+    let first_line = content.lines().next().unwrap_or_default();
+    first_line.trim_end() == fence
+}
+
+/// Find the line closing the front matter, returning where that line starts
+/// and where the content after it starts.
+fn find_closing_fence(text: &str, fence: &str) -> Option<(usize, usize)> {
+    let mut start = 0;
+
+    while start < text.len() {
+        let end = match text[start..].find('\n') {
+            Some(newline) => start + newline + 1,
+            None => text.len(),
+        };
+        if text[start..end].trim_end() == fence {
+            return Some((start, end));
+        }
+        start = end;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Level;
+    use serde_json::json;
+
+    #[test]
+    fn content_without_front_matter_is_untouched() {
+        let content = "# Chapter\n\n---\n\nSome text\n";
+        let (metadata, rest, _) = split_front_matter(content);
+
+        assert!(metadata.is_empty());
+        assert_eq!(rest, content);
+    }
+
+    #[test]
+    fn yaml_front_matter_is_parsed_and_stripped() {
+        let content = "---\ndescription: The intro\nauthors: [Jane, John]\n---\n# Intro\n";
+        let (metadata, rest, _) = split_front_matter(content);
+
+        assert_eq!(metadata["description"], json!("The intro"));
+        assert_eq!(metadata["authors"], json!(["Jane", "John"]));
+        assert_eq!(rest, "# Intro\n");
+    }
+
+    #[test]
+    fn toml_front_matter_is_parsed_and_stripped() {
+        let content = "+++\r\ndraft = true\r\nreviewed = 2021-06-01\r\n+++\r\n# Intro";
+        let (metadata, rest, _) = split_front_matter(content);
+
+        assert_eq!(metadata["draft"], json!(true));
+        assert_eq!(metadata["reviewed"], json!("2021-06-01"));
+        assert_eq!(rest, "# Intro");
+    }
+
+    #[test]
+    fn empty_toml_front_matter_is_allowed() {
+        let (metadata, rest, _) = split_front_matter("+++\n+++\nText");
+
+        assert!(metadata.is_empty());
+        assert_eq!(rest, "Text");
+    }
+
+    #[test]
+    fn horizontal_rules_are_not_front_matter() {
+        for content in &[
+            "---\n---\nText",
+            "---\n\n# Chapter\n",
+            "---\n# Chapter\n\nSome text\n\n---\n\nMore text\n",
+            "---\n- just\n- a list\n---\n",
+        ] {
+            let (metadata, rest, diagnostic) = split_front_matter(content);
+
+            assert!(metadata.is_empty());
+            assert_eq!(rest, *content);
+            assert!(diagnostic.is_none());
+        }
+    }
+
+    #[test]
+    fn malformed_front_matter_is_reported() {
+        for (content, line) in &[
+            ("---\ntitle: Intro\nauthors: [Jane\n---\n# Intro\n", 4),
+            ("+++\ntitle = \"Intro\"\nnot = [toml\n+++\n# Intro\n", 3),
+        ] {
+            let (metadata, rest, diagnostic) = split_front_matter(content);
+
+            assert!(metadata.is_empty());
+            assert_eq!(rest, *content);
+            let diagnostic = diagnostic.unwrap();
+            assert_eq!(diagnostic.level, Level::Warning);
+            let span = diagnostic.span.unwrap();
+            assert_eq!(span.line, *line);
+            assert!(span.label.is_some());
+        }
+    }
+}
@@ -166,6 +166,8 @@ impl BookBuilder {
         let mut f = File::create(self.root.join(".gitignore"))?;
 
         writeln!(f, "{}", self.config.build.build_dir.display())?;
+        // Left behind by `mdbook serve` and `mdbook watch`
+        writeln!(f, ".mdbook-incremental")?;
 
         Ok(())
     }
@@ -7,15 +7,27 @@
 
 #[allow(clippy::module_inception)]
 mod book;
+mod front_matter;
 mod init;
+mod scan;
 mod summary;
+mod testing;
 
-pub use self::book::{load_book, Book, BookItem, BookItems, Chapter};
+pub use self::book::{load_book, load_translation, Book, BookItem, BookItems, Chapter};
+pub use self::front_matter::Metadata;
 pub use self::init::BookBuilder;
-pub use self::summary::{parse_summary, Link, SectionNumber, Summary, SummaryItem};
+pub use self::scan::{append_to_summary, find_unlinked_chapters, generate_summary};
+pub(crate) use self::summary::normalize;
+pub use self::summary::{
+    expand_nested_summaries, parse_summary, ExternalLink, Link, SectionNumber, Summary, SummaryItem,
+};
+pub use self::testing::{BlockResult, ChapterReport, Outcome, TestOptions, TestReport};
 
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::string::ToString;
 use tempfile::Builder as TempFileBuilder;
@@ -23,12 +35,15 @@ use toml::Value;
 
 use crate::errors::*;
 use crate::preprocess::{
-    CmdPreprocessor, IndexPreprocessor, LinkPreprocessor, Preprocessor, PreprocessorContext,
+    CmdPreprocessor, IndexPreprocessor, LinkPreprocessor, MathPreprocessor, Preprocessor,
+    PreprocessorContext,
+};
+use crate::renderer::{
+    CmdRenderer, EpubRenderer, HtmlHandlebars, MarkdownRenderer, RenderContext, Renderer,
 };
-use crate::renderer::{CmdRenderer, HtmlHandlebars, MarkdownRenderer, RenderContext, Renderer};
 use crate::utils;
 
-use crate::config::{Config, RustEdition};
+use crate::config::{Config, RustEdition, TestConfig};
 
 /// The object used to manage and build a book.
 pub struct MDBook {
@@ -37,7 +52,11 @@ pub struct MDBook {
     /// The configuration used to tweak now a book is built.
     pub config: Config,
     /// A representation of the book's contents in memory.
+    ///
+    /// For a multilingual book this is the book's default language.
     pub book: Book,
+    /// The other translations of a multilingual book, keyed by language code.
+    pub translations: BTreeMap<String, Book>,
     renderers: Vec<Box<dyn Renderer>>,
 
     /// List of pre-processors to be run on the book.
@@ -83,7 +102,15 @@ impl MDBook {
         let root = book_root.into();
 
         let src_dir = root.join(&config.book.src);
-        let book = book::load_book(&src_dir, &config.build)?;
+        let (mut book, mut translations) = if config.book.multilingual {
+            load_translations(&src_dir, &config)?
+        } else {
+            (book::load_book(&src_dir, &config.build)?, BTreeMap::new())
+        };
+        book::restart_part_numbering(&mut book, &config.book.numbering);
+        for translation in translations.values_mut() {
+            book::restart_part_numbering(translation, &config.book.numbering);
+        }
 
         let renderers = determine_renderers(&config);
         let preprocessors = determine_preprocessors(&config)?;
@@ -92,6 +119,7 @@ impl MDBook {
             root,
             config,
             book,
+            translations,
             renderers,
             preprocessors,
         })
@@ -106,7 +134,8 @@ impl MDBook {
         let root = book_root.into();
 
         let src_dir = root.join(&config.book.src);
-        let book = book::load_book_from_disk(&summary, &src_dir)?;
+        let mut book = book::load_book_from_disk(&summary, &src_dir)?;
+        book::restart_part_numbering(&mut book, &config.book.numbering);
 
         let renderers = determine_renderers(&config);
         let preprocessors = determine_preprocessors(&config)?;
@@ -115,6 +144,7 @@ impl MDBook {
             root,
             config,
             book,
+            translations: BTreeMap::new(),
             renderers,
             preprocessors,
         })
@@ -133,6 +163,7 @@ impl MDBook {
     ///         BookItem::Chapter(ref chapter) => {},
     ///         BookItem::Separator => {},
     ///         BookItem::PartTitle(ref title) => {}
+    ///         BookItem::ExternalLink(ref link) => {}
     ///     }
     /// }
     ///
@@ -181,34 +212,76 @@ impl MDBook {
     }
 
     /// Run the entire build process for a particular [`Renderer`].
+    ///
+    /// Each translation of a multilingual book is rendered into its own
+    /// `<language>` subdirectory of the renderer's build directory.
     pub fn execute_build_process(&self, renderer: &dyn Renderer) -> Result<()> {
-        let mut preprocessed_book = self.book.clone();
+        let build_dir = self.build_dir_for(renderer.name());
+
+        if !self.config.book.multilingual {
+            let render_context =
+                self.render_context(renderer, &self.book, self.config.clone(), build_dir)?;
+            return self.render(renderer, render_context);
+        }
+
+        let default_language = self.config.book.default_language();
+        let languages: Vec<_> = std::iter::once((default_language, &self.book))
+            .chain(
+                self.translations
+                    .iter()
+                    .map(|(language, book)| (language.as_str(), book)),
+            )
+            .collect();
+        let translations: BTreeMap<_, _> = languages
+            .iter()
+            .map(|&(language, book)| (language.to_string(), chapter_paths(book)))
+            .collect();
+
+        for (language, book) in languages {
+            info!("Building the \"{}\" translation", language);
+            let config = config_for_language(&self.config, language);
+            let mut render_context =
+                self.render_context(renderer, book, config, build_dir.join(language))?;
+            render_context.default_language = Some(default_language.to_string());
+            render_context.translations = translations.clone();
+            self.render(renderer, render_context)?;
+        }
+
+        Ok(())
+    }
+
+    /// Preprocess `book` for a renderer, getting the context it's rendered with.
+    fn render_context(
+        &self,
+        renderer: &dyn Renderer,
+        book: &Book,
+        config: Config,
+        build_dir: PathBuf,
+    ) -> Result<RenderContext> {
+        let mut preprocessed_book = book.clone();
         let preprocess_ctx = PreprocessorContext::new(
             self.root.clone(),
-            self.config.clone(),
+            config.clone(),
             renderer.name().to_string(),
         );
 
         for preprocessor in &self.preprocessors {
-            if preprocessor_should_run(&**preprocessor, renderer, &self.config) {
+            if preprocessor_should_run(&**preprocessor, renderer, &config) {
                 debug!("Running the {} preprocessor.", preprocessor.name());
                 preprocessed_book = preprocessor.run(&preprocess_ctx, preprocessed_book)?;
             }
         }
 
-        let name = renderer.name();
-        let build_dir = self.build_dir_for(name);
-
-        let mut render_context = RenderContext::new(
-            self.root.clone(),
-            preprocessed_book,
-            self.config.clone(),
-            build_dir,
-        );
+        let mut render_context =
+            RenderContext::new(self.root.clone(), preprocessed_book, config, build_dir);
         render_context
             .chapter_titles
             .extend(preprocess_ctx.chapter_titles.borrow_mut().drain());
 
+        Ok(render_context)
+    }
+
+    fn render(&self, renderer: &dyn Renderer, render_context: RenderContext) -> Result<()> {
         info!("Running the {} backend", renderer.name());
         renderer
             .render(&render_context)
@@ -231,73 +304,141 @@ impl MDBook {
 
     /// Run `rustdoc` tests on the book, linking against the provided libraries.
     pub fn test(&mut self, library_paths: Vec<&str>) -> Result<()> {
-        let library_args: Vec<&str> = (0..library_paths.len())
-            .map(|_| "-L")
-            .zip(library_paths.into_iter())
-            .flat_map(|x| vec![x.0, x.1])
+        let options = TestOptions {
+            library_paths,
+            ..Default::default()
+        };
+        let report = self.test_with_options(&options)?;
+        report.log();
+
+        if report.failed() {
+            bail!("One or more tests failed");
+        }
+        Ok(())
+    }
+
+    /// Run `rustdoc` tests on the book's chapters, along with any runners
+    /// configured in the `[test]` table for other languages, returning the
+    /// result of every code block which was tested. Failing tests are
+    /// reported rather than returned as an error.
+    pub fn test_with_options(&mut self, options: &TestOptions<'_>) -> Result<TestReport> {
+        let library_args: Vec<&str> = options
+            .library_paths
+            .iter()
+            .flat_map(|path| vec!["-L", *path])
             .collect();
 
         let temp_dir = TempFileBuilder::new().prefix("mdbook-").tempdir()?;
 
+        // Only the default language of a multilingual book is tested.
+        let config = if self.config.book.multilingual {
+            config_for_language(&self.config, self.config.book.default_language())
+        } else {
+            self.config.clone()
+        };
+        let source_dir = self.root.join(&config.book.src);
+        let title = config.book.title.clone();
+        let test_config: TestConfig = config
+            .get_deserialized_opt("test")
+            .with_context(|| "Parsing configuration [test]")?
+            .unwrap_or_default();
+
         // FIXME: Is "test" the proper renderer name to use here?
         let preprocess_context =
-            PreprocessorContext::new(self.root.clone(), self.config.clone(), "test".to_string());
+            PreprocessorContext::new(self.root.clone(), config, "test".to_string());
 
         let book = LinkPreprocessor::new().run(&preprocess_context, self.book.clone())?;
         // Index Preprocessor is disabled so that chapter paths continue to point to the
         // actual markdown files.
 
-        let mut failed = false;
+        // Write every chapter to be tested to the temporary directory first,
+        // so the tests themselves can run in parallel.
+        let mut chapters = Vec::new();
         for item in book.iter() {
             if let BookItem::Chapter(ref ch) = *item {
                 let chapter_path = match ch.path {
                     Some(ref path) if !path.as_os_str().is_empty() => path,
                     _ => continue,
                 };
-
-                let path = self.source_dir().join(&chapter_path);
-                info!("Testing file: {:?}", path);
+                if let Some(chapter) = options.chapter {
+                    if ch.name != chapter && chapter_path != Path::new(chapter) {
+                        continue;
+                    }
+                }
 
                 // write preprocessed file to tempdir
                 let path = temp_dir.path().join(&chapter_path);
                 let mut tmpf = utils::fs::create_file(&path)?;
                 tmpf.write_all(ch.content.as_bytes())?;
 
-                let mut cmd = Command::new("rustdoc");
-                cmd.arg(&path).arg("--test").args(&library_args);
-
-                if let Some(edition) = self.config.rust.edition {
-                    match edition {
-                        RustEdition::E2015 => {
-                            cmd.args(&["--edition", "2015"]);
-                        }
-                        RustEdition::E2018 => {
-                            cmd.args(&["--edition", "2018"]);
-                        }
-                        RustEdition::E2021 => {
-                            cmd.args(&["--edition", "2021"])
-                                .args(&["-Z", "unstable-options"]);
-                        }
+                chapters.push((ch, chapter_path, path));
+            }
+        }
+
+        let edition = self.config.rust.edition;
+        let root = &self.root;
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(options.jobs.unwrap_or(0))
+            .build()?;
+        let run_test = |(ch, chapter_path, path): &(&Chapter, &PathBuf, PathBuf)| {
+            info!("Testing file: {:?}", source_dir.join(chapter_path));
+
+            let mut cmd = Command::new("rustdoc");
+            cmd.arg(path).arg("--test").args(&library_args);
+
+            if let Some(edition) = edition {
+                match edition {
+                    RustEdition::E2015 => {
+                        cmd.args(["--edition", "2015"]);
+                    }
+                    RustEdition::E2018 => {
+                        cmd.args(["--edition", "2018"]);
+                    }
+                    RustEdition::E2021 => {
+                        cmd.args(["--edition", "2021"])
+                            .args(["-Z", "unstable-options"]);
                     }
                 }
+            }
 
-                let output = cmd.output()?;
+            // Failing to run rustdoc only fails this chapter, not the whole run
+            let mut report = match cmd.output() {
+                Ok(output) => ChapterReport::from_rustdoc_output(
+                    &ch.name,
+                    chapter_path,
+                    path,
+                    output.status.success(),
+                    &String::from_utf8_lossy(&output.stdout),
+                    &String::from_utf8_lossy(&output.stderr),
+                ),
+                Err(e) => ChapterReport {
+                    name: ch.name.clone(),
+                    path: chapter_path.to_path_buf(),
+                    blocks: Vec::new(),
+                    error: Some(format!("Unable to run rustdoc: {}", e)),
+                },
+            };
+            let other_blocks = testing::run_code_blocks(&ch.content, &test_config.runners, root);
+            if !other_blocks.is_empty() {
+                report.blocks.extend(other_blocks);
+                report.blocks.sort_by_key(|block| block.line);
+            }
+            let source_path = ch.source_path.as_ref().unwrap_or(chapter_path);
+            let source = fs::read_to_string(source_dir.join(source_path)).unwrap_or_default();
+            testing::map_lines_to_source(&mut report.blocks, &ch.content, &source);
+            report
+        };
+        let report = TestReport {
+            title,
+            chapters: pool.install(|| chapters.par_iter().map(run_test).collect()),
+        };
 
-                if !output.status.success() {
-                    failed = true;
-                    error!(
-                        "rustdoc returned an error:\n\
-                        \n--- stdout\n{}\n--- stderr\n{}",
-                        String::from_utf8_lossy(&output.stdout),
-                        String::from_utf8_lossy(&output.stderr)
-                    );
-                }
+        if let Some(chapter) = options.chapter {
+            if report.chapters.is_empty() {
+                bail!("Chapter not found: {}", chapter);
             }
         }
-        if failed {
-            bail!("One or more tests failed");
-        }
-        Ok(())
+        Ok(report)
     }
 
     /// The logic for determining where a backend should put its build
@@ -348,6 +489,74 @@ impl MDBook {
     }
 }
 
+/// Load every translation of a multilingual book, returning the book for the
+/// default language along with the other translations.
+fn load_translations(src_dir: &Path, config: &Config) -> Result<(Book, BTreeMap<String, Book>)> {
+    let default_language = config.book.default_language();
+
+    if config.language.is_empty() {
+        bail!("A multilingual book must declare its languages in `[language.<code>]` tables");
+    }
+    if !config.language.contains_key(default_language) {
+        bail!(
+            "The default language \"{}\" (`book.language`) must have a `[language.{}]` table",
+            default_language,
+            default_language
+        );
+    }
+
+    let default_dir = src_dir.join(default_language);
+    let book = book::load_book(&default_dir, &config.build)?;
+
+    let mut translations = BTreeMap::new();
+    for language in config.language.keys() {
+        if language == default_language {
+            continue;
+        }
+
+        debug!("Loading the \"{}\" translation", language);
+        let translation =
+            book::load_translation(src_dir.join(language), &default_dir, &config.build)
+                .with_context(|| format!("Unable to load the \"{}\" translation", language))?;
+        translations.insert(language.clone(), translation);
+    }
+
+    Ok((book, translations))
+}
+
+/// The paths of every chapter in a translation of a multilingual book.
+fn chapter_paths(book: &Book) -> BTreeSet<PathBuf> {
+    book.iter()
+        .filter_map(|item| match item {
+            BookItem::Chapter(ch) => ch.path.clone(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Get the `Config` used when building one translation of a multilingual book.
+///
+/// The source directory points at the translation's own directory, and the
+/// book's language, title and description are replaced with the ones for
+/// that translation.
+fn config_for_language(config: &Config, language: &str) -> Config {
+    let mut config = config.clone();
+    let src = config.book.src.join(language);
+    config.book.src = src;
+
+    if let Some(translation) = config.language.get(language).cloned() {
+        if translation.title.is_some() {
+            config.book.title = translation.title;
+        }
+        if translation.description.is_some() {
+            config.book.description = translation.description;
+        }
+    }
+    config.book.language = Some(language.to_string());
+
+    config
+}
+
 /// Look at the `Config` and try to figure out what renderers to use.
 fn determine_renderers(config: &Config) -> Vec<Box<dyn Renderer>> {
     let mut renderers = Vec::new();
@@ -358,6 +567,8 @@ fn determine_renderers(config: &Config) -> Vec<Box<dyn Renderer>> {
                 Box::new(HtmlHandlebars::new()) as Box<dyn Renderer>
             } else if key == "markdown" {
                 Box::new(MarkdownRenderer::new()) as Box<dyn Renderer>
+            } else if key == "epub" {
+                Box::new(EpubRenderer::new()) as Box<dyn Renderer>
             } else {
                 interpret_custom_renderer(key, table)
             }
@@ -397,6 +608,7 @@ fn determine_preprocessors(config: &Config) -> Result<Vec<Box<dyn Preprocessor>>
             match key.as_ref() {
                 "links" => preprocessors.push(Box::new(LinkPreprocessor::new())),
                 "index" => preprocessors.push(Box::new(IndexPreprocessor::new())),
+                "math" => preprocessors.push(Box::new(MathPreprocessor::new())),
                 name => preprocessors.push(interpret_custom_preprocessor(
                     name,
                     &preprocessor_table[name],
@@ -0,0 +1,323 @@
+//! Scanning the source directory for chapters, to generate a `SUMMARY.md`
+//! from its layout or to find the chapters `SUMMARY.md` doesn't link to.
+
+use pulldown_cmark::{Event, Tag};
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use super::front_matter::split_front_matter;
+use super::summary::{parse_summary, Summary, SummaryItem};
+use crate::errors::*;
+use crate::utils;
+
+/// The markdown files in a directory of the source, and its subdirectories.
+#[derive(Debug, Default)]
+struct Dir {
+    /// The `README.md` or `index.md` introducing the directory's chapters.
+    index: Option<PathBuf>,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug)]
+enum Entry {
+    File(PathBuf),
+    Dir(PathBuf, Dir),
+}
+
+/// Generate a `SUMMARY.md` listing every markdown file in `src_dir`, nested
+/// the same way as the directories they're in.
+///
+/// The link text of each chapter is the file's first heading. A directory's
+/// `README.md` (or else `index.md`) becomes the parent of the other chapters in
+/// it, and directories without one get a draft chapter instead. Paths in
+/// `ignore`, relative to `src_dir`, are skipped along with everything in them.
+pub fn generate_summary(src_dir: &Path, ignore: &[PathBuf]) -> Result<String> {
+    let root = scan_dir(src_dir, Path::new(""), ignore)?;
+    let mut summary = String::from("# Summary\n\n");
+
+    if let Some(ref index) = root.index {
+        push_link(
+            &mut summary,
+            0,
+            &chapter_title(src_dir, index)?,
+            Some(index),
+        );
+    }
+    push_entries(&mut summary, src_dir, &root.entries, 0)?;
+
+    Ok(summary)
+}
+
+/// Find the markdown files in `src_dir` which `summary` doesn't link to, so
+/// they'd never be part of the book. Paths in `ignore`, relative to
+/// `src_dir`, are skipped along with everything in them.
+pub fn find_unlinked_chapters(
+    src_dir: &Path,
+    summary: &Summary,
+    ignore: &[PathBuf],
+) -> Result<Vec<PathBuf>> {
+    let mut linked = HashSet::new();
+    for items in &[
+        &summary.prefix_chapters,
+        &summary.numbered_chapters,
+        &summary.suffix_chapters,
+    ] {
+        collect_links(items, &mut linked);
+    }
+
+    let mut files = Vec::new();
+    collect_files(&scan_dir(src_dir, Path::new(""), ignore)?, &mut files);
+    files.retain(|file| !linked.contains(file));
+    Ok(files)
+}
+
+/// Append a top-level link to each of `files` to the end of the text of a
+/// `SUMMARY.md`.
+pub fn append_to_summary(summary: &str, src_dir: &Path, files: &[PathBuf]) -> Result<String> {
+    let mut appended = summary.trim_end().to_string();
+    let last_line = appended.lines().last().unwrap_or_default().trim_start();
+    // Start a new list unless the summary already ends with one
+    if last_line.starts_with("- ") || last_line.starts_with("* ") {
+        appended.push('\n');
+    } else if !appended.is_empty() {
+        appended.push_str("\n\n");
+    }
+
+    for file in files {
+        push_link(&mut appended, 0, &chapter_title(src_dir, file)?, Some(file));
+    }
+
+    parse_summary(&appended)
+        .with_context(|| "The chapters can't be added to the end of SUMMARY.md")?;
+    Ok(appended)
+}
+
+fn scan_dir(src_dir: &Path, dir: &Path, ignore: &[PathBuf]) -> Result<Dir> {
+    let full_path = src_dir.join(dir);
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&full_path)
+        .with_context(|| format!("Unable to read the directory {}", full_path.display()))?
+    {
+        let entry = entry?;
+        names.push((entry.file_name(), entry.file_type()?.is_dir()));
+    }
+    names.sort();
+
+    let mut scanned = Dir::default();
+    for (name, is_dir) in names {
+        let path = dir.join(&name);
+        let hidden = name.to_string_lossy().starts_with('.');
+        if hidden || ignore.iter().any(|ignored| path.starts_with(ignored)) {
+            continue;
+        }
+
+        if is_dir {
+            let sub_dir = scan_dir(src_dir, &path, ignore)?;
+            if sub_dir.index.is_some() || !sub_dir.entries.is_empty() {
+                scanned.entries.push(Entry::Dir(path, sub_dir));
+            }
+        } else if path.extension() == Some(OsStr::new("md")) {
+            // The book's summary, or a nested summary it includes
+            if name == "SUMMARY.md" {
+                continue;
+            }
+            scanned.entries.push(Entry::File(path));
+        }
+    }
+
+    for index_name in &["README.md", "index.md"] {
+        let index = scanned
+            .entries
+            .iter()
+            .position(|entry| matches!(entry, Entry::File(path) if path.ends_with(index_name)));
+        if let Some(position) = index {
+            if let Entry::File(path) = scanned.entries.remove(position) {
+                scanned.index = Some(path);
+            }
+            break;
+        }
+    }
+
+    Ok(scanned)
+}
+
+fn push_entries(
+    summary: &mut String,
+    src_dir: &Path,
+    entries: &[Entry],
+    depth: usize,
+) -> Result<()> {
+    for entry in entries {
+        match entry {
+            Entry::File(path) => {
+                push_link(summary, depth, &chapter_title(src_dir, path)?, Some(path));
+            }
+            Entry::Dir(path, dir) => {
+                match dir.index {
+                    Some(ref index) => {
+                        push_link(summary, depth, &chapter_title(src_dir, index)?, Some(index))
+                    }
+                    None => push_link(summary, depth, &title_from_file_name(path), None),
+                }
+                push_entries(summary, src_dir, &dir.entries, depth + 1)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn push_link(summary: &mut String, depth: usize, title: &str, path: Option<&PathBuf>) {
+    let title = title.replace('[', "\\[").replace(']', "\\]");
+    let location = path
+        .map(|path| utils::fs::normalize_path(&path.to_string_lossy()).replace(' ', "%20"))
+        .unwrap_or_default();
+    summary.push_str(&format!(
+        "{}- [{}]({})\n",
+        "    ".repeat(depth),
+        title,
+        location
+    ));
+}
+
+/// The text of the chapter's first heading, or a title made from its file
+/// name if it doesn't have one.
+fn chapter_title(src_dir: &Path, path: &Path) -> Result<String> {
+    let full_path = src_dir.join(path);
+    let content = fs::read_to_string(&full_path)
+        .with_context(|| format!("Unable to read {}", full_path.display()))?;
+    let (_, content, _) = split_front_matter(&content);
+
+    let mut title = String::new();
+    let mut in_heading = false;
+    for event in utils::new_cmark_parser(content) {
+        match event {
+            Event::Start(Tag::Heading(_)) => in_heading = true,
+            Event::End(Tag::Heading(_)) if title.trim().is_empty() => in_heading = false,
+            Event::End(Tag::Heading(_)) => break,
+            Event::Text(text) | Event::Code(text) if in_heading => title.push_str(&text),
+            _ => {}
+        }
+    }
+
+    let title = title.trim();
+    if title.is_empty() {
+        Ok(title_from_file_name(path))
+    } else {
+        Ok(title.to_string())
+    }
+}
+
+/// Turn a file name like `getting-started.md` into `Getting started`.
+fn title_from_file_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().replace(&['-', '_'][..], " "))
+        .unwrap_or_default();
+    let mut chars = stem.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => stem,
+    }
+}
+
+fn collect_links(items: &[SummaryItem], linked: &mut HashSet<PathBuf>) {
+    for item in items {
+        if let SummaryItem::Link(ref link) = *item {
+            if let Some(ref location) = link.location {
+                // Links may start with `./`
+                let location: PathBuf = location
+                    .components()
+                    .filter(|component| *component != Component::CurDir)
+                    .collect();
+                linked.insert(location);
+            }
+            collect_links(&link.nested_items, linked);
+        }
+    }
+}
+
+fn collect_files(dir: &Dir, files: &mut Vec<PathBuf>) {
+    files.extend(dir.index.clone());
+    for entry in &dir.entries {
+        match entry {
+            Entry::File(path) => files.push(path.clone()),
+            Entry::Dir(_, dir) => collect_files(dir, files),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::Builder as TempFileBuilder;
+
+    fn create_book(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let temp = TempFileBuilder::new().prefix("book").tempdir().unwrap();
+        for (path, content) in files {
+            let path = temp.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        temp
+    }
+
+    #[test]
+    fn summary_follows_the_directory_layout() {
+        let temp = create_book(&[
+            ("README.md", "# Introduction\n"),
+            ("SUMMARY.md", ""),
+            ("a-first-chapter.md", "Some text without a heading"),
+            (
+                "guide/index.md",
+                "---\ntitle: x\n---\n\n## The `guide` [1]\n",
+            ),
+            ("guide/install.md", "# Installing\n"),
+            ("reference/api docs.md", "# API\n"),
+            ("reference/image.png", ""),
+            ("listings/skipped.md", "# Skipped\n"),
+            (".hidden/file.md", "# Hidden\n"),
+        ]);
+
+        let got = generate_summary(temp.path(), &[PathBuf::from("listings")]).unwrap();
+
+        assert_eq!(
+            got,
+            "# Summary\n\n\
+             - [Introduction](README.md)\n\
+             - [A first chapter](a-first-chapter.md)\n\
+             - [The guide \\[1\\]](guide/index.md)\n    \
+             - [Installing](guide/install.md)\n\
+             - [Reference]()\n    \
+             - [API](reference/api%20docs.md)\n"
+        );
+        assert_eq!(parse_summary(&got).unwrap().numbered_chapters.len(), 4);
+    }
+
+    #[test]
+    fn unlinked_chapters_are_found_and_appended() {
+        let summary = "# Summary\n\n- [Intro](./README.md)\n    - [Nested](dir/nested.md)\n";
+        let temp = create_book(&[
+            ("README.md", "# Intro\n"),
+            ("SUMMARY.md", summary),
+            ("dir/nested.md", ""),
+            ("dir/forgotten.md", "# Forgotten\n"),
+            ("lost.md", "# Lost\n"),
+        ]);
+
+        let parsed = parse_summary(summary).unwrap();
+        let unlinked = find_unlinked_chapters(temp.path(), &parsed, &[]).unwrap();
+        assert_eq!(
+            unlinked,
+            vec![PathBuf::from("dir/forgotten.md"), PathBuf::from("lost.md")]
+        );
+
+        let appended = append_to_summary(summary, temp.path(), &unlinked).unwrap();
+        assert_eq!(
+            appended,
+            "# Summary\n\n- [Intro](./README.md)\n    - [Nested](dir/nested.md)\n\
+             - [Forgotten](dir/forgotten.md)\n- [Lost](lost.md)\n"
+        );
+    }
+}
@@ -1,10 +1,12 @@
+use crate::config::NumberingStyle;
+use crate::diagnostic::Diagnostic;
 use crate::errors::*;
-use memchr::{self, Memchr};
 use pulldown_cmark::{self, Event, Tag};
 use std::fmt::{self, Display, Formatter};
+use std::fs;
 use std::iter::FromIterator;
-use std::ops::{Deref, DerefMut};
-use std::path::{Path, PathBuf};
+use std::ops::{Deref, DerefMut, Range};
+use std::path::{Component, Path, PathBuf};
 
 /// Parse the text from a `SUMMARY.md` file into a sort of "recipe" to be
 /// used when loading a book from disk.
@@ -53,6 +55,172 @@ pub fn parse_summary(summary: &str) -> Result<Summary> {
     parser.parse()
 }
 
+/// Replace the entries of a summary which include another `SUMMARY.md`,
+/// written either as a link to it (`- [API](api/SUMMARY.md)`) or as
+/// `{{#summary api/SUMMARY.md}}`, with the chapters listed in that file.
+///
+/// The included chapters are spliced in place of the entry, their paths are
+/// made relative to `src_dir` instead of the included file's directory, and
+/// the numbered chapters are renumbered. Included files may include others in
+/// turn.
+pub fn expand_nested_summaries(summary: Summary, src_dir: &Path) -> Result<Summary> {
+    let mut stack = Vec::new();
+    let mut included = false;
+    let mut expand = |items| expand_items(items, src_dir, Path::new(""), &mut stack, &mut included);
+
+    let prefix_chapters = expand(summary.prefix_chapters)?;
+    let numbered_chapters = expand(summary.numbered_chapters)?;
+    let suffix_chapters = expand(summary.suffix_chapters)?;
+
+    let mut expanded = Summary {
+        title: summary.title,
+        prefix_chapters,
+        numbered_chapters,
+        suffix_chapters,
+    };
+    if included {
+        clear_section_numbers(&mut expanded.prefix_chapters);
+        clear_section_numbers(&mut expanded.suffix_chapters);
+        renumber_sections(&mut expanded.numbered_chapters, &SectionNumber::default());
+    }
+    Ok(expanded)
+}
+
+/// Whether a link's location is another summary rather than a chapter.
+fn is_nested_summary(location: &Path) -> bool {
+    location.file_name() == Some("SUMMARY.md".as_ref())
+}
+
+/// Expand the nested summaries in `items`, which come from a summary in the
+/// `base` directory (relative to `src_dir`). `stack` holds the summaries
+/// currently being expanded, to catch summaries which include themselves,
+/// and `included` is set once any summary is included.
+fn expand_items(
+    items: Vec<SummaryItem>,
+    src_dir: &Path,
+    base: &Path,
+    stack: &mut Vec<PathBuf>,
+    included: &mut bool,
+) -> Result<Vec<SummaryItem>> {
+    let mut expanded = Vec::with_capacity(items.len());
+
+    for item in items {
+        let mut link = match item {
+            SummaryItem::Link(link) => link,
+            other => {
+                expanded.push(other);
+                continue;
+            }
+        };
+        let location = link.location.take().map(|location| rebase(base, &location));
+
+        let location = match location {
+            Some(location) if is_nested_summary(&location) => location,
+            location => {
+                link.location = location;
+                link.nested_items =
+                    expand_items(link.nested_items, src_dir, base, stack, included)?;
+                expanded.push(SummaryItem::Link(link));
+                continue;
+            }
+        };
+        if !link.nested_items.is_empty() {
+            bail!(
+                "The entry including {} can't have nested chapters",
+                location.display()
+            );
+        }
+        let location = normalize(&location);
+        if stack.contains(&location) {
+            bail!("{} includes itself", location.display());
+        }
+
+        let file = src_dir.join(&location);
+        let content = fs::read_to_string(&file)
+            .with_context(|| format!("Unable to read the summary {}", file.display()))?;
+        let nested = parse_summary(&content)
+            .map_err(|mut e| {
+                Diagnostic::set_file_in(&mut e, &file);
+                e
+            })
+            .with_context(|| format!("Summary parsing failed for file={:?}", file))?;
+
+        debug!("Including the chapters from {}", location.display());
+        let nested_base = location
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .to_path_buf();
+        stack.push(location);
+        for items in [
+            nested.prefix_chapters,
+            nested.numbered_chapters,
+            nested.suffix_chapters,
+        ] {
+            expanded.extend(expand_items(items, src_dir, &nested_base, stack, included)?);
+        }
+        stack.pop();
+        *included = true;
+    }
+
+    Ok(expanded)
+}
+
+/// Make a path relative to a nested summary's directory relative to the
+/// source directory instead.
+fn rebase(base: &Path, location: &Path) -> PathBuf {
+    if base.as_os_str().is_empty() {
+        return location.to_path_buf();
+    }
+    normalize(&base.join(location))
+}
+
+/// Remove the `.` and `..` components from a relative path, where possible.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn clear_section_numbers(items: &mut [SummaryItem]) {
+    for item in items {
+        if let SummaryItem::Link(ref mut link) = *item {
+            link.number = None;
+            clear_section_numbers(&mut link.nested_items);
+        }
+    }
+}
+
+/// Number the chapters from 1 at every level, continuing across parts and
+/// separators like the parser does.
+fn renumber_sections(items: &mut [SummaryItem], parent: &SectionNumber) {
+    let mut count = 0;
+    for item in items {
+        if let SummaryItem::Link(ref mut link) = *item {
+            if link.hidden {
+                continue;
+            }
+            count += 1;
+            let mut number = parent.clone();
+            number.push(count);
+            renumber_sections(&mut link.nested_items, &number);
+            link.number = Some(number);
+        }
+    }
+}
+
 /// The parsed `SUMMARY.md`, specifying how the book should be laid out.
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Summary {
@@ -79,6 +247,10 @@ pub struct Link {
     pub location: Option<PathBuf>,
     /// The section number, if this chapter is in the numbered section.
     pub number: Option<SectionNumber>,
+    /// Whether the chapter is left out of the table of contents and the
+    /// navigation between chapters, written as `[Name](path.md "hidden")`.
+    #[serde(default)]
+    pub hidden: bool,
     /// Any nested items this chapter may contain.
     pub nested_items: Vec<SummaryItem>,
 }
@@ -90,6 +262,7 @@ impl Link {
             name: name.into(),
             location: Some(location.as_ref().to_path_buf()),
             number: None,
+            hidden: false,
             nested_items: Vec::new(),
         }
     }
@@ -101,16 +274,29 @@ impl Default for Link {
             name: String::new(),
             location: Some(PathBuf::new()),
             number: None,
+            hidden: false,
             nested_items: Vec::new(),
         }
     }
 }
 
+/// A link to a page outside the book, such as an API reference. It's listed
+/// in the table of contents, but doesn't have a page of its own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalLink {
+    /// The text of the link.
+    pub name: String,
+    /// The URL the link points to.
+    pub url: String,
+}
+
 /// An item in `SUMMARY.md` which could be either a separator or a `Link`.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SummaryItem {
     /// A link to a chapter.
     Link(Link),
+    /// A link to a page outside the book.
+    ExternalLink(ExternalLink),
     /// A separator (`---`).
     Separator,
     /// A part title.
@@ -162,7 +348,8 @@ impl From<Link> for SummaryItem {
 struct SummaryParser<'a> {
     src: &'a str,
     stream: pulldown_cmark::OffsetIter<'a>,
-    offset: usize,
+    /// The source range of the most recently read event.
+    span: Range<usize>,
 
     /// We can't actually put an event back into the `OffsetIter` stream, so instead we store it
     /// here until somebody calls `next_event` again.
@@ -214,22 +401,11 @@ impl<'a> SummaryParser<'a> {
         SummaryParser {
             src: text,
             stream: pulldown_parser,
-            offset: 0,
+            span: 0..0,
             back: None,
         }
     }
 
-    /// Get the current line and column to give the user more useful error
-    /// messages.
-    fn current_location(&self) -> (usize, usize) {
-        let previous_text = self.src[..self.offset].as_bytes();
-        let line = Memchr::new(b'\n', previous_text).count() + 1;
-        let start_of_line = memchr::memrchr(b'\n', previous_text).unwrap_or(0);
-        let col = self.src[start_of_line..self.offset].chars().count();
-
-        (line, col)
-    }
-
     /// Parse the text the `SummaryParser` was created with.
     fn parse(mut self) -> Result<Summary> {
         let title = self.parse_title();
@@ -273,11 +449,20 @@ impl<'a> SummaryParser<'a> {
                         bail!(self.parse_error("Suffix chapters cannot be followed by a list"));
                     }
                 }
-                Some(Event::Start(Tag::Link(_type, href, _title))) => {
-                    let link = self.parse_link(href.to_string());
-                    items.push(SummaryItem::Link(link));
+                Some(Event::Start(Tag::Link(_type, href, title))) => {
+                    if is_external_url(&href) {
+                        items.push(self.parse_external_link(href.to_string()));
+                    } else {
+                        let mut link = self.parse_link(href.to_string());
+                        link.hidden = is_hidden(&title);
+                        items.push(SummaryItem::Link(link));
+                    }
                 }
                 Some(Event::Rule) => items.push(SummaryItem::Separator),
+                Some(Event::Text(text)) if text.trim_start().starts_with("{{#summary") => {
+                    let link = self.parse_summary_include(&text)?;
+                    items.push(SummaryItem::Link(link));
+                }
                 Some(_) => {}
                 None => break,
             }
@@ -331,6 +516,15 @@ impl<'a> SummaryParser<'a> {
         Ok(parts)
     }
 
+    /// Finishes parsing a link to a URL outside the book once the
+    /// `Event::Start(Tag::Link(..))` has been opened.
+    fn parse_external_link(&mut self, url: String) -> SummaryItem {
+        let link_content = collect_events!(self.stream, end Tag::Link(..));
+        let name = stringify_events(link_content);
+
+        SummaryItem::ExternalLink(ExternalLink { name, url })
+    }
+
     /// Finishes parsing a link once the `Event::Start(Tag::Link(..))` has been opened.
     fn parse_link(&mut self, href: String) -> Link {
         let href = href.replace("%20", " ");
@@ -347,10 +541,50 @@ impl<'a> SummaryParser<'a> {
             name,
             location: path,
             number: None,
+            hidden: false,
             nested_items: Vec::new(),
         }
     }
 
+    /// Finishes parsing a `{{#summary path/to/SUMMARY.md}}` include, starting
+    /// with `text`. It's represented as a link to the included summary, which
+    /// is replaced by its chapters when the book is loaded.
+    fn parse_summary_include(&mut self, text: &str) -> Result<Link> {
+        let mut text = text.to_string();
+        loop {
+            match self.next_event() {
+                Some(Event::Text(more)) => text.push_str(&more),
+                Some(other) => {
+                    self.back(other);
+                    break;
+                }
+                None => break,
+            }
+        }
+
+        let path = text
+            .trim()
+            .strip_prefix("{{#summary")
+            .and_then(|rest| rest.strip_suffix("}}"))
+            .map(str::trim)
+            .unwrap_or_default();
+        let location = PathBuf::from(path.replace("%20", " "));
+        if !is_nested_summary(&location) {
+            bail!(self.parse_error(format!(
+                "`{}` must include a file named SUMMARY.md",
+                text.trim()
+            )));
+        }
+
+        Ok(Link {
+            name: String::new(),
+            location: Some(location),
+            number: None,
+            hidden: false,
+            nested_items: Vec::new(),
+        })
+    }
+
     /// Parse the numbered chapters.
     fn parse_numbered(
         &mut self,
@@ -388,7 +622,7 @@ impl<'a> SummaryParser<'a> {
                     // will be numbered from 1. We need to manually go back and update
                     // them
                     update_section_numbers(&mut bunch_of_items, 0, *root_items);
-                    *root_items += bunch_of_items.len() as u32;
+                    *root_items += count_links(&bunch_of_items);
                     items.extend(bunch_of_items);
                 }
                 Some(Event::Start(other_tag)) => {
@@ -431,7 +665,7 @@ impl<'a> SummaryParser<'a> {
     fn next_event(&mut self) -> Option<Event<'a>> {
         let next = self.back.take().or_else(|| {
             self.stream.next().map(|(ev, range)| {
-                self.offset = range.start;
+                self.span = range;
                 ev
             })
         });
@@ -448,7 +682,7 @@ impl<'a> SummaryParser<'a> {
         loop {
             match self.next_event() {
                 Some(Event::Start(Tag::Item)) => {
-                    let item = self.parse_nested_item(parent, items.len())?;
+                    let item = self.parse_nested_item(parent, count_links(&items) as usize)?;
                     items.push(item);
                 }
                 Some(Event::Start(Tag::List(..))) => {
@@ -456,6 +690,15 @@ impl<'a> SummaryParser<'a> {
                     if items.is_empty() {
                         continue;
                     }
+                    match items.last() {
+                        Some(SummaryItem::ExternalLink(_)) => {
+                            bail!(self.parse_error("External links can't have nested chapters"));
+                        }
+                        Some(SummaryItem::Link(link)) if link.hidden => {
+                            bail!(self.parse_error("Hidden chapters can't have nested chapters"));
+                        }
+                        _ => {}
+                    }
                     // recurse to parse the nested list
                     let (_, last_item) = get_last_link(&mut items)?;
                     let last_item_number = last_item
@@ -484,6 +727,15 @@ impl<'a> SummaryParser<'a> {
         loop {
             match self.next_event() {
                 Some(Event::Start(Tag::Paragraph)) => continue,
+                Some(Event::Start(Tag::Link(_type, href, _title))) if is_external_url(&href) => {
+                    return Ok(self.parse_external_link(href.to_string()));
+                }
+                Some(Event::Start(Tag::Link(_type, href, title))) if is_hidden(&title) => {
+                    // Hidden chapters aren't listed, so they don't get a number
+                    let mut link = self.parse_link(href.to_string());
+                    link.hidden = true;
+                    return Ok(SummaryItem::Link(link));
+                }
                 Some(Event::Start(Tag::Link(_type, href, _title))) => {
                     let mut link = self.parse_link(href.to_string());
 
@@ -503,6 +755,10 @@ impl<'a> SummaryParser<'a> {
 
                     return Ok(SummaryItem::Link(link));
                 }
+                Some(Event::Text(text)) if text.trim_start().starts_with("{{#summary") => {
+                    let link = self.parse_summary_include(&text)?;
+                    return Ok(SummaryItem::Link(link));
+                }
                 other => {
                     warn!("Expected a start of a link, actually got {:?}", other);
                     bail!(self.parse_error(
@@ -514,13 +770,14 @@ impl<'a> SummaryParser<'a> {
     }
 
     fn parse_error<D: Display>(&self, msg: D) -> Error {
-        let (line, col) = self.current_location();
-        anyhow::anyhow!(
-            "failed to parse SUMMARY.md line {}, column {}: {}",
-            line,
-            col,
-            msg
-        )
+        let mut diagnostic = Diagnostic::error("").with_span(self.src, self.span.clone(), None);
+        if let Some(ref span) = diagnostic.span {
+            diagnostic.message = format!(
+                "failed to parse SUMMARY.md line {}, column {}: {}",
+                span.line, span.column, msg
+            );
+        }
+        diagnostic.into()
     }
 
     /// Try to parse the title line.
@@ -536,12 +793,46 @@ impl<'a> SummaryParser<'a> {
                 // Skip a HTML element such as a comment line.
                 Some(Event::Html(_)) => {}
                 // Otherwise, no title.
-                _ => return None,
+                Some(ev) => {
+                    self.back(ev);
+                    return None;
+                }
+                None => return None,
             }
         }
     }
 }
 
+/// Whether a link points outside the book, because it starts with a URL
+/// scheme like `https:` or `mailto:`.
+fn is_external_url(href: &str) -> bool {
+    match href.find(':') {
+        // A single letter would be a Windows drive
+        Some(end) if end > 1 => {
+            let scheme = &href[..end];
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+/// Whether a link's title marks it as a hidden chapter.
+fn is_hidden(title: &str) -> bool {
+    title.trim() == "hidden"
+}
+
+/// The number of chapters in a list of items which aren't hidden, which are
+/// the only items with section numbers.
+fn count_links(items: &[SummaryItem]) -> u32 {
+    items
+        .iter()
+        .filter(|item| matches!(item, SummaryItem::Link(link) if !link.hidden))
+        .count() as u32
+}
+
 fn update_section_numbers(sections: &mut [SummaryItem], level: usize, by: u32) {
     for section in sections {
         if let SummaryItem::Link(ref mut link) = *section {
@@ -599,6 +890,78 @@ impl Display for SectionNumber {
     }
 }
 
+impl SectionNumber {
+    /// Format the section number with its top-level number written in
+    /// `style`, like "B.2." for [`NumberingStyle::UpperAlpha`]. The `Display`
+    /// impl is the same as the [`NumberingStyle::Arabic`] style.
+    pub fn format_with(&self, style: NumberingStyle) -> String {
+        let (first, rest) = match self.0.split_first() {
+            Some(split) => split,
+            None => return self.to_string(),
+        };
+
+        let mut formatted = match style {
+            NumberingStyle::Arabic => first.to_string(),
+            NumberingStyle::UpperRoman => roman(*first),
+            NumberingStyle::LowerRoman => roman(*first).to_lowercase(),
+            NumberingStyle::UpperAlpha => alpha(*first),
+            NumberingStyle::LowerAlpha => alpha(*first).to_lowercase(),
+        };
+        formatted.push('.');
+        for item in rest {
+            formatted.push_str(&format!("{}.", item));
+        }
+        formatted
+    }
+}
+
+/// Write a number as upper case Roman numerals, falling back to Arabic
+/// numbers outside of the range they can represent.
+fn roman(mut number: u32) -> String {
+    const NUMERALS: [(u32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+
+    if number == 0 || number >= 4000 {
+        return number.to_string();
+    }
+    let mut numerals = String::new();
+    for &(value, numeral) in &NUMERALS {
+        while number >= value {
+            numerals.push_str(numeral);
+            number -= value;
+        }
+    }
+    numerals
+}
+
+/// Write a number as upper case letters, continuing with "AA", "AB" and so
+/// on after "Z".
+fn alpha(mut number: u32) -> String {
+    if number == 0 {
+        return number.to_string();
+    }
+    let mut letters = Vec::new();
+    while number > 0 {
+        number -= 1;
+        letters.push(char::from(b'A' + (number % 26) as u8));
+        number /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
 impl Deref for SectionNumber {
     type Target = Vec<u32>;
     fn deref(&self) -> &Self::Target {
@@ -636,6 +999,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn section_numbers_can_be_formatted_in_other_styles() {
+        let number = SectionNumber(vec![14, 2]);
+
+        assert_eq!(number.format_with(NumberingStyle::Arabic), "14.2.");
+        assert_eq!(number.format_with(NumberingStyle::UpperRoman), "XIV.2.");
+        assert_eq!(number.format_with(NumberingStyle::LowerRoman), "xiv.2.");
+        assert_eq!(number.format_with(NumberingStyle::UpperAlpha), "N.2.");
+        assert_eq!(number.format_with(NumberingStyle::LowerAlpha), "n.2.");
+        assert_eq!(
+            SectionNumber(vec![28]).format_with(NumberingStyle::UpperAlpha),
+            "AB."
+        );
+        assert_eq!(
+            SectionNumber(vec![1994]).format_with(NumberingStyle::UpperRoman),
+            "MCMXCIV."
+        );
+        assert_eq!(
+            SectionNumber::default().format_with(NumberingStyle::UpperAlpha),
+            "0"
+        );
+    }
+
     #[test]
     fn parse_initial_title() {
         let src = "# Summary";
@@ -658,6 +1044,40 @@ mod tests {
         assert_eq!(got, should_be);
     }
 
+    #[test]
+    fn the_first_item_is_kept_when_there_is_no_title() {
+        // The event after the comment isn't a title, so it's handed back to
+        // the parser rather than dropped
+        let src = "<!-- A comment -->\n- [First](./first.md)\n    - [Nested](./nested.md)\n";
+
+        let got = parse_summary(src).unwrap();
+
+        assert_eq!(got.title, None);
+        let should_be = vec![SummaryItem::Link(Link {
+            number: Some(SectionNumber(vec![1])),
+            nested_items: vec![SummaryItem::Link(Link {
+                number: Some(SectionNumber(vec![1, 1])),
+                ..Link::new("Nested", "./nested.md")
+            })],
+            ..Link::new("First", "./first.md")
+        })];
+        assert_eq!(got.numbered_chapters, should_be);
+    }
+
+    #[test]
+    fn parse_errors_include_the_location() {
+        let src = "- [Docs](https://docs.rs)\n    - [Nested](./nested.md)\n";
+
+        let err = parse_summary(src).unwrap_err();
+
+        let message = format!("{:#}", err);
+        assert!(
+            message.contains("failed to parse SUMMARY.md line 2, column 3"),
+            "{}",
+            message
+        );
+    }
+
     #[test]
     fn convert_markdown_events_to_a_string() {
         let src = "Hello *World*, `this` is some text [and a link](./path/to/link)";
@@ -762,10 +1182,12 @@ mod tests {
                 name: String::from("First"),
                 location: Some(PathBuf::from("./first.md")),
                 number: Some(SectionNumber(vec![1])),
+                hidden: false,
                 nested_items: vec![SummaryItem::Link(Link {
                     name: String::from("Nested"),
                     location: Some(PathBuf::from("./nested.md")),
                     number: Some(SectionNumber(vec![1, 1])),
+                    hidden: false,
                     nested_items: Vec::new(),
                 })],
             }),
@@ -773,6 +1195,7 @@ mod tests {
                 name: String::from("Second"),
                 location: Some(PathBuf::from("./second.md")),
                 number: Some(SectionNumber(vec![2])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
         ];
@@ -794,12 +1217,14 @@ mod tests {
                 name: String::from("First"),
                 location: Some(PathBuf::from("./first.md")),
                 number: Some(SectionNumber(vec![1])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
             SummaryItem::Link(Link {
                 name: String::from("Second"),
                 location: Some(PathBuf::from("./second.md")),
                 number: Some(SectionNumber(vec![2])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
         ];
@@ -822,12 +1247,14 @@ mod tests {
                 name: String::from("First"),
                 location: Some(PathBuf::from("./first.md")),
                 number: Some(SectionNumber(vec![1])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
             SummaryItem::Link(Link {
                 name: String::from("Second"),
                 location: Some(PathBuf::from("./second.md")),
                 number: Some(SectionNumber(vec![2])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
             SummaryItem::PartTitle(String::from("Title 2")),
@@ -835,10 +1262,12 @@ mod tests {
                 name: String::from("Third"),
                 location: Some(PathBuf::from("./third.md")),
                 number: Some(SectionNumber(vec![3])),
+                hidden: false,
                 nested_items: vec![SummaryItem::Link(Link {
                     name: String::from("Fourth"),
                     location: Some(PathBuf::from("./fourth.md")),
                     number: Some(SectionNumber(vec![3, 1])),
+                    hidden: false,
                     nested_items: Vec::new(),
                 })],
             }),
@@ -862,12 +1291,14 @@ mod tests {
                 name: String::from("First"),
                 location: Some(PathBuf::from("./first.md")),
                 number: Some(SectionNumber(vec![1])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
             SummaryItem::Link(Link {
                 name: String::from("Second"),
                 location: Some(PathBuf::from("./second.md")),
                 number: Some(SectionNumber(vec![2])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
         ];
@@ -890,6 +1321,7 @@ mod tests {
             name: String::from("Empty"),
             location: None,
             number: Some(SectionNumber(vec![1])),
+            hidden: false,
             nested_items: Vec::new(),
         })];
 
@@ -908,6 +1340,7 @@ mod tests {
                 name: String::from("First"),
                 location: Some(PathBuf::from("./first.md")),
                 number: Some(SectionNumber(vec![1])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
             SummaryItem::Separator,
@@ -915,6 +1348,7 @@ mod tests {
                 name: String::from("Second"),
                 location: Some(PathBuf::from("./second.md")),
                 number: Some(SectionNumber(vec![2])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
             SummaryItem::Separator,
@@ -922,6 +1356,7 @@ mod tests {
                 name: String::from("Third"),
                 location: Some(PathBuf::from("./third.md")),
                 number: Some(SectionNumber(vec![3])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
         ];
@@ -943,6 +1378,7 @@ mod tests {
             name: String::from("Chapter title"),
             location: Some(PathBuf::from("./chapter.md")),
             number: Some(SectionNumber(vec![1])),
+            hidden: false,
             nested_items: Vec::new(),
         })];
 
@@ -962,12 +1398,14 @@ mod tests {
                 name: String::from("test1"),
                 location: Some(PathBuf::from("./test link1.md")),
                 number: Some(SectionNumber(vec![1])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
             SummaryItem::Link(Link {
                 name: String::from("test2"),
                 location: Some(PathBuf::from("./test link2.md")),
                 number: Some(SectionNumber(vec![2])),
+                hidden: false,
                 nested_items: Vec::new(),
             }),
         ];
@@ -1051,6 +1489,7 @@ mod tests {
                 name: String::from(name),
                 location: Some(PathBuf::from(location)),
                 number: Some(SectionNumber(numbers.to_vec())),
+                hidden: false,
                 nested_items,
             })
         };
@@ -1077,4 +1516,190 @@ mod tests {
         let got = parser.parse_affix(false).unwrap();
         assert_eq!(got, should_be);
     }
+
+    #[test]
+    fn external_links_are_not_numbered() {
+        let src = "[Home](https://example.com)\n\n\
+                   - [First](./first.md)\n    - [Docs](https://docs.rs/mdbook)\n    \
+                   - [Nested](./nested.md)\n- [Mail](mailto:me@example.com)\n- [Second](./second.md)";
+
+        let got = parse_summary(src).unwrap();
+
+        assert_eq!(
+            got.prefix_chapters,
+            vec![SummaryItem::ExternalLink(ExternalLink {
+                name: String::from("Home"),
+                url: String::from("https://example.com"),
+            })]
+        );
+        let should_be = vec![
+            SummaryItem::Link(Link {
+                name: String::from("First"),
+                location: Some(PathBuf::from("./first.md")),
+                number: Some(SectionNumber(vec![1])),
+                hidden: false,
+                nested_items: vec![
+                    SummaryItem::ExternalLink(ExternalLink {
+                        name: String::from("Docs"),
+                        url: String::from("https://docs.rs/mdbook"),
+                    }),
+                    SummaryItem::Link(Link {
+                        name: String::from("Nested"),
+                        location: Some(PathBuf::from("./nested.md")),
+                        number: Some(SectionNumber(vec![1, 1])),
+                        hidden: false,
+                        nested_items: Vec::new(),
+                    }),
+                ],
+            }),
+            SummaryItem::ExternalLink(ExternalLink {
+                name: String::from("Mail"),
+                url: String::from("mailto:me@example.com"),
+            }),
+            SummaryItem::Link(Link {
+                name: String::from("Second"),
+                location: Some(PathBuf::from("./second.md")),
+                number: Some(SectionNumber(vec![2])),
+                hidden: false,
+                nested_items: Vec::new(),
+            }),
+        ];
+        assert_eq!(got.numbered_chapters, should_be);
+    }
+
+    #[test]
+    fn external_links_cant_have_nested_chapters() {
+        let src = "- [Docs](https://docs.rs)\n    - [Nested](./nested.md)\n";
+
+        assert!(parse_summary(src).is_err());
+    }
+
+    #[test]
+    fn hidden_chapters_are_not_numbered() {
+        let src = "[Landing](./landing.md \"hidden\")\n\n\
+                   - [First](./first.md)\n    - [Legal](./legal.md \"hidden\")\n\
+                   - [Second](./second.md \"Not hidden\")";
+
+        let got = parse_summary(src).unwrap();
+
+        assert_eq!(
+            got.prefix_chapters,
+            vec![SummaryItem::Link(Link {
+                hidden: true,
+                ..Link::new("Landing", "./landing.md")
+            })]
+        );
+        let should_be = vec![
+            SummaryItem::Link(Link {
+                number: Some(SectionNumber(vec![1])),
+                nested_items: vec![SummaryItem::Link(Link {
+                    hidden: true,
+                    ..Link::new("Legal", "./legal.md")
+                })],
+                ..Link::new("First", "./first.md")
+            }),
+            SummaryItem::Link(Link {
+                number: Some(SectionNumber(vec![2])),
+                ..Link::new("Second", "./second.md")
+            }),
+        ];
+        assert_eq!(got.numbered_chapters, should_be);
+    }
+
+    #[test]
+    fn hidden_chapters_cant_have_nested_chapters() {
+        let src = "- [Legal](./legal.md \"hidden\")\n    - [Nested](./nested.md)\n";
+
+        assert!(parse_summary(src).is_err());
+    }
+
+    #[test]
+    fn only_urls_with_a_scheme_are_external() {
+        assert!(is_external_url("https://example.com"));
+        assert!(is_external_url("mailto:me@example.com"));
+        assert!(!is_external_url("./chapter.md"));
+        assert!(!is_external_url("C:/book/chapter.md"));
+        assert!(!is_external_url("dir/a:b.md"));
+    }
+
+    #[test]
+    fn summary_includes_are_links_to_the_included_file() {
+        let src =
+            "- [Intro](intro.md)\n- {{#summary api/SUMMARY.md}}\n- [Teams](teams/SUMMARY.md)\n";
+        let got = parse_summary(src).unwrap();
+
+        let locations: Vec<_> = got
+            .numbered_chapters
+            .iter()
+            .filter_map(|item| match item {
+                SummaryItem::Link(link) => link.location.clone(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            locations,
+            vec![
+                PathBuf::from("intro.md"),
+                PathBuf::from("api/SUMMARY.md"),
+                PathBuf::from("teams/SUMMARY.md"),
+            ]
+        );
+
+        assert!(parse_summary("- {{#summary api/toc.md}}\n").is_err());
+    }
+
+    #[test]
+    fn nested_summaries_are_spliced_in_and_renumbered() {
+        let temp = tempfile::Builder::new().prefix("book").tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("api/v2")).unwrap();
+        fs::write(
+            temp.path().join("api/SUMMARY.md"),
+            "# API\n\n[Overview](./overview.md)\n\n- [Calls](calls.md)\n    - {{#summary v2/SUMMARY.md}}\n",
+        )
+        .unwrap();
+        fs::write(temp.path().join("api/v2/SUMMARY.md"), "- [V2](index.md)\n").unwrap();
+
+        let summary = parse_summary(
+            "[Preface](preface.md)\n\n- [Intro](intro.md)\n- [API](api/SUMMARY.md)\n- [End](end.md)\n",
+        )
+        .unwrap();
+        let got = expand_nested_summaries(summary, temp.path()).unwrap();
+
+        let link = |name: &str, location: &str, number: &[u32], nested_items| {
+            SummaryItem::Link(Link {
+                name: String::from(name),
+                location: Some(PathBuf::from(location)),
+                number: Some(SectionNumber(number.to_vec())),
+                hidden: false,
+                nested_items,
+            })
+        };
+        let v2 = vec![link("V2", "api/v2/index.md", &[3, 1], vec![])];
+        assert_eq!(
+            got.numbered_chapters,
+            vec![
+                link("Intro", "intro.md", &[1], vec![]),
+                link("Overview", "api/overview.md", &[2], vec![]),
+                link("Calls", "api/calls.md", &[3], v2),
+                link("End", "end.md", &[4], vec![]),
+            ]
+        );
+        assert_eq!(got.prefix_chapters.len(), 1);
+    }
+
+    #[test]
+    fn summaries_cant_include_themselves() {
+        let temp = tempfile::Builder::new().prefix("book").tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("a")).unwrap();
+        fs::write(
+            temp.path().join("a/SUMMARY.md"),
+            "- {{#summary ../a/SUMMARY.md}}\n",
+        )
+        .unwrap();
+
+        let summary = parse_summary("- [A](a/SUMMARY.md)\n").unwrap();
+        let err = expand_nested_summaries(summary, temp.path()).unwrap_err();
+
+        assert!(format!("{:?}", err).contains("includes itself"));
+    }
 }
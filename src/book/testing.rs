@@ -0,0 +1,732 @@
+//! The results of testing a book's code samples with `mdbook test`.
+
+use crate::config::TestRunner;
+use crate::errors::*;
+use crate::utils;
+
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
+use regex::Regex;
+use shlex::Shlex;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::{self, Display, Formatter};
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Options controlling which chapters [`MDBook::test_with_options`] tests
+/// and how.
+///
+/// [`MDBook::test_with_options`]: super::MDBook::test_with_options
+#[derive(Debug, Default, Clone)]
+pub struct TestOptions<'a> {
+    /// Directories to add to the crate search path when building tests.
+    pub library_paths: Vec<&'a str>,
+    /// Only test the chapter with this name or source path.
+    pub chapter: Option<&'a str>,
+    /// How many chapters to test at the same time. Defaults to the number of
+    /// CPUs.
+    pub jobs: Option<usize>,
+}
+
+/// What happened to a single code block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// The code block behaved as expected.
+    Passed,
+    /// The code block didn't compile or run as expected, along with the
+    /// test's output.
+    Failed(String),
+    /// The code block was marked as not to be tested.
+    Ignored,
+}
+
+/// The result of testing a single code block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockResult {
+    /// The name `rustdoc` gave the test, which includes the headings the
+    /// code block appears under.
+    pub name: String,
+    /// The line of the chapter's source file the code block starts on.
+    pub line: usize,
+    /// Whether the code block was found in the chapter's source file. If it
+    /// wasn't, for example because it was included from another file,
+    /// `line` is its line in the preprocessed chapter instead.
+    pub in_source: bool,
+    /// What happened.
+    pub outcome: Outcome,
+}
+
+/// The results of testing every code block in a chapter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChapterReport {
+    /// The chapter's name.
+    pub name: String,
+    /// The chapter's source file, relative to the book's `src` directory.
+    pub path: PathBuf,
+    /// Every code block which was tested, in the order they appear in the
+    /// chapter.
+    pub blocks: Vec<BlockResult>,
+    /// Set when testing failed without reporting on individual code blocks,
+    /// for example because `rustdoc` couldn't be run.
+    pub error: Option<String>,
+}
+
+impl ChapterReport {
+    /// Build a chapter's report from the output of `rustdoc --test`, which
+    /// was run on the file at `tested_path`.
+    pub(crate) fn from_rustdoc_output(
+        name: &str,
+        path: &Path,
+        tested_path: &Path,
+        success: bool,
+        stdout: &str,
+        stderr: &str,
+    ) -> ChapterReport {
+        lazy_static! {
+            static ref RESULT: Regex =
+                Regex::new(r"^test (.*) \(line (\d+)\)(?: - [^.]*)? \.\.\. (ok|FAILED|ignored)$")
+                    .unwrap();
+            static ref FAILURE_HEADER: Regex = Regex::new(r"^---- (.*) stdout ----$").unwrap();
+        }
+
+        let prefix = format!("{} - ", tested_path.display());
+        let failures = failure_messages(stdout, &FAILURE_HEADER);
+
+        let mut blocks: Vec<BlockResult> = stdout
+            .lines()
+            .filter_map(|line| RESULT.captures(line))
+            .map(|caps| {
+                let full_name = format!("{} (line {})", &caps[1], &caps[2]);
+                let outcome = match &caps[3] {
+                    "ok" => Outcome::Passed,
+                    "ignored" => Outcome::Ignored,
+                    _ => Outcome::Failed(
+                        failures
+                            .iter()
+                            .find(|(name, _)| *name == full_name)
+                            .map(|(_, message)| message.clone())
+                            .unwrap_or_default(),
+                    ),
+                };
+
+                BlockResult {
+                    name: caps[1].trim_start_matches(&prefix).to_string(),
+                    line: caps[2].parse().unwrap_or_default(),
+                    in_source: true,
+                    outcome,
+                }
+            })
+            .collect();
+        blocks.sort_by_key(|block| block.line);
+
+        let failed_blocks = blocks.iter().any(|block| block.is_failure());
+        let error = if success || failed_blocks {
+            None
+        } else {
+            Some(format!("--- stdout\n{}\n--- stderr\n{}", stdout, stderr))
+        };
+
+        ChapterReport {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            blocks,
+            error,
+        }
+    }
+
+    /// Did any of this chapter's tests fail?
+    pub fn failed(&self) -> bool {
+        self.error.is_some() || self.blocks.iter().any(|block| block.is_failure())
+    }
+
+    fn count(&self, f: fn(&BlockResult) -> bool) -> usize {
+        self.blocks.iter().filter(|block| f(block)).count()
+    }
+
+    /// The number of failed code blocks, plus one if rustdoc itself failed.
+    fn failures(&self) -> usize {
+        self.count(BlockResult::is_failure) + self.error.iter().count()
+    }
+}
+
+impl BlockResult {
+    fn is_failure(&self) -> bool {
+        matches!(self.outcome, Outcome::Failed(_))
+    }
+
+    fn is_ignored(&self) -> bool {
+        self.outcome == Outcome::Ignored
+    }
+
+    fn is_pass(&self) -> bool {
+        self.outcome == Outcome::Passed
+    }
+
+    /// Where the code block is, as shown to the user.
+    fn location(&self) -> String {
+        if self.in_source {
+            format!("line {}", self.line)
+        } else {
+            format!("line {} of the preprocessed chapter", self.line)
+        }
+    }
+}
+
+/// Point the line numbers of `blocks`, which are lines of a chapter's
+/// preprocessed `content`, at the same code blocks in the chapter's `source`
+/// file. Front matter is stripped and `{{#include}}`s are expanded by the
+/// time a chapter is tested, so the two rarely line up.
+pub(crate) fn map_lines_to_source(blocks: &mut [BlockResult], content: &str, source: &str) {
+    let mut source_lines: HashMap<&str, VecDeque<usize>> = HashMap::new();
+    for (lines, text) in code_blocks(source) {
+        source_lines.entry(text).or_default().push_back(lines.start);
+    }
+    let content_blocks: Vec<_> = code_blocks(content)
+        .into_iter()
+        .map(|(lines, text)| {
+            let source_line = source_lines.get_mut(text).and_then(VecDeque::pop_front);
+            (lines, source_line)
+        })
+        .collect();
+
+    for block in blocks {
+        let found = content_blocks
+            .iter()
+            .find(|(lines, _)| lines.contains(&block.line));
+        match found {
+            Some((lines, Some(source_line))) => {
+                block.line = source_line + (block.line - lines.start);
+                block.in_source = true;
+            }
+            _ => block.in_source = false,
+        }
+    }
+}
+
+/// The lines spanned by each code block in `text`, along with its markdown.
+fn code_blocks(text: &str) -> Vec<(Range<usize>, &str)> {
+    utils::new_cmark_parser(text)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                let start = text[..range.start].matches('\n').count() + 1;
+                let end = start + text[range.clone()].matches('\n').count() + 1;
+                Some((start..end, &text[range]))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Test every code block in `content` whose language has a runner, running
+/// the commands from `root`.
+pub(crate) fn run_code_blocks(
+    content: &str,
+    runners: &BTreeMap<String, TestRunner>,
+    root: &Path,
+) -> Vec<BlockResult> {
+    let mut results = Vec::new();
+    let mut current: Option<(String, Vec<String>, usize, String)> = None;
+
+    for (event, range) in utils::new_cmark_parser(content).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                let mut words = info
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|word| !word.is_empty())
+                    .map(String::from);
+                if let Some(language) = words.next() {
+                    if language != "rust" && runners.contains_key(&language) {
+                        let line = content[..range.start].matches('\n').count() + 1;
+                        current = Some((language, words.collect(), line, String::new()));
+                    }
+                }
+            }
+            Event::Text(text) => {
+                if let Some((_, _, _, ref mut code)) = current {
+                    code.push_str(&text);
+                }
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                if let Some((language, attributes, line, code)) = current.take() {
+                    let outcome = run_code_block(&runners[&language], &attributes, &code, root);
+                    results.push(BlockResult {
+                        name: language,
+                        line,
+                        in_source: true,
+                        outcome,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    results
+}
+
+fn run_code_block(runner: &TestRunner, attributes: &[String], code: &str, root: &Path) -> Outcome {
+    let has = |attribute: &str| attributes.iter().any(|a| a == attribute);
+    if has("ignore") || has("no_run") {
+        return Outcome::Ignored;
+    }
+    let should_fail = has("should_fail") || has("should_panic");
+
+    match run_command(&runner.command, code, root) {
+        Ok((true, _)) if !should_fail => Outcome::Passed,
+        Ok((false, _)) if should_fail => Outcome::Passed,
+        Ok((true, _)) => Outcome::Failed(String::from("the code block should have failed")),
+        Ok((false, output)) => Outcome::Failed(output),
+        Err(e) => Outcome::Failed(format!("{:?}", e)),
+    }
+}
+
+/// Run `command` with `input` on its stdin, returning whether it succeeded
+/// along with everything it printed.
+fn run_command(command: &str, input: &str, root: &Path) -> Result<(bool, String)> {
+    let mut words = Shlex::new(command);
+    let executable = match words.next() {
+        Some(e) => e,
+        None => bail!("Command string was empty"),
+    };
+
+    let mut child = Command::new(&executable)
+        .args(words)
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Unable to run `{}`", command))?;
+
+    {
+        let mut stdin = child.stdin.take().expect("Child has stdin");
+        // A command which doesn't read its input closes the pipe early,
+        // which isn't our problem.
+        let _ = stdin.write_all(input.as_bytes());
+    }
+
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Error waiting for `{}` to finish", command))?;
+    let printed = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    Ok((output.status.success(), printed.trim().to_string()))
+}
+
+/// Split the `---- <test> stdout ----` sections out of `rustdoc`'s output.
+fn failure_messages(stdout: &str, header: &Regex) -> Vec<(String, String)> {
+    let mut failures: Vec<(String, String)> = Vec::new();
+    let mut current: Option<(String, String)> = None;
+
+    for line in stdout.lines() {
+        if let Some(caps) = header.captures(line) {
+            failures.extend(current.take());
+            current = Some((caps[1].to_string(), String::new()));
+        } else if line == "failures:" {
+            failures.extend(current.take());
+        } else if let Some((_, ref mut message)) = current {
+            message.push_str(line);
+            message.push('\n');
+        }
+    }
+    failures.extend(current);
+
+    for (_, message) in &mut failures {
+        *message = message.trim().to_string();
+    }
+    failures
+}
+
+/// The results of testing a book.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TestReport {
+    /// The book's title, if it has one.
+    pub title: Option<String>,
+    /// A report for every chapter which was tested, in the order they appear
+    /// in the book.
+    pub chapters: Vec<ChapterReport>,
+}
+
+impl TestReport {
+    /// Did any of the book's tests fail?
+    pub fn failed(&self) -> bool {
+        self.chapters.iter().any(ChapterReport::failed)
+    }
+
+    /// Log the output of every failed test, followed by a summary of the
+    /// whole run.
+    pub fn log(&self) {
+        for chapter in &self.chapters {
+            if let Some(ref error) = chapter.error {
+                error!(
+                    "rustdoc returned an error for {}:\n{}",
+                    chapter.path.display(),
+                    error
+                );
+            }
+            for block in &chapter.blocks {
+                if let Outcome::Failed(ref message) = block.outcome {
+                    if block.in_source {
+                        error!(
+                            "{}:{}: code block failed\n{}",
+                            chapter.path.display(),
+                            block.line,
+                            message
+                        );
+                    } else {
+                        error!(
+                            "{} ({}): code block failed\n{}",
+                            chapter.path.display(),
+                            block.location(),
+                            message
+                        );
+                    }
+                }
+            }
+        }
+
+        if self.failed() {
+            error!("{}", self);
+        } else {
+            info!("{}", self);
+        }
+    }
+
+    /// Render the report as JUnit XML, with one test suite per chapter.
+    pub fn to_junit_xml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let errors = self.chapters.iter().filter(|ch| ch.error.is_some()).count();
+        xml.push_str(&format!(
+            "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\">\n",
+            utils::escape_xml(self.title.as_deref().unwrap_or("mdbook")),
+            self.total(|_| true) + errors,
+            self.total(BlockResult::is_failure),
+            errors,
+        ));
+
+        for chapter in &self.chapters {
+            let path = utils::escape_xml(&chapter.path.display().to_string());
+            let errors = chapter.error.iter().count();
+            xml.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\">\n",
+                path,
+                chapter.blocks.len() + errors,
+                chapter.count(BlockResult::is_failure),
+                errors,
+                chapter.count(BlockResult::is_ignored),
+            ));
+
+            for block in &chapter.blocks {
+                let line = if block.in_source {
+                    format!(" line=\"{}\"", block.line)
+                } else {
+                    String::new()
+                };
+                xml.push_str(&format!(
+                    "    <testcase name=\"{} ({})\" classname=\"{}\" file=\"{}\"{}",
+                    utils::escape_xml(&block.name),
+                    block.location(),
+                    path,
+                    path,
+                    line
+                ));
+                match block.outcome {
+                    Outcome::Passed => xml.push_str("/>\n"),
+                    Outcome::Ignored => xml.push_str(">\n      <skipped/>\n    </testcase>\n"),
+                    Outcome::Failed(ref message) => xml.push_str(&format!(
+                        ">\n      <failure message=\"code block failed\">{}</failure>\n    </testcase>\n",
+                        utils::escape_xml(message)
+                    )),
+                }
+            }
+
+            // Errors which aren't about a particular code block get a test
+            // case of their own
+            if let Some(ref error) = chapter.error {
+                xml.push_str(&format!(
+                    "    <testcase name=\"rustdoc\" classname=\"{}\" file=\"{}\">\n      \
+                     <error message=\"rustdoc returned an error\">{}</error>\n    </testcase>\n",
+                    path,
+                    path,
+                    utils::escape_xml(error)
+                ));
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+
+    fn total(&self, f: fn(&BlockResult) -> bool) -> usize {
+        self.chapters.iter().map(|ch| ch.count(f)).sum()
+    }
+}
+
+impl Display for TestReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Test results:")?;
+
+        for chapter in &self.chapters {
+            let status = if chapter.failed() { "FAILED" } else { "ok" };
+            writeln!(
+                f,
+                "    {} ... {} ({} passed, {} failed, {} ignored)",
+                chapter.path.display(),
+                status,
+                chapter.count(BlockResult::is_pass),
+                chapter.failures(),
+                chapter.count(BlockResult::is_ignored),
+            )?;
+            for block in chapter.blocks.iter().filter(|block| block.is_failure()) {
+                writeln!(f, "        {}: {}", block.location(), block.name)?;
+            }
+        }
+
+        let chapters = match self.chapters.len() {
+            1 => String::from("1 chapter"),
+            n => format!("{} chapters", n),
+        };
+        write!(
+            f,
+            "{} tested: {} passed, {} failed, {} ignored",
+            chapters,
+            self.total(BlockResult::is_pass),
+            self.chapters
+                .iter()
+                .map(ChapterReport::failures)
+                .sum::<usize>(),
+            self.total(BlockResult::is_ignored),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STDOUT: &str = "
+running 3 tests
+test /tmp/book/ch.md - Title (line 3) ... ok
+test /tmp/book/ch.md - Title::Sub (line 13) ... ignored
+test /tmp/book/ch.md - Title::Sub (line 9) ... FAILED
+
+failures:
+
+---- /tmp/book/ch.md - Title::Sub (line 9) stdout ----
+error[E0308]: mismatched types
+Couldn't compile the test.
+
+failures:
+    /tmp/book/ch.md - Title::Sub (line 9)
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+
+    fn chapter_report() -> ChapterReport {
+        ChapterReport::from_rustdoc_output(
+            "Chapter",
+            Path::new("ch.md"),
+            Path::new("/tmp/book/ch.md"),
+            false,
+            STDOUT,
+            "",
+        )
+    }
+
+    #[test]
+    fn rustdoc_output_is_split_into_code_blocks() {
+        let got = chapter_report();
+
+        let should_be = vec![
+            BlockResult {
+                name: String::from("Title"),
+                line: 3,
+                in_source: true,
+                outcome: Outcome::Passed,
+            },
+            BlockResult {
+                name: String::from("Title::Sub"),
+                line: 9,
+                in_source: true,
+                outcome: Outcome::Failed(String::from(
+                    "error[E0308]: mismatched types\nCouldn't compile the test.",
+                )),
+            },
+            BlockResult {
+                name: String::from("Title::Sub"),
+                line: 13,
+                in_source: true,
+                outcome: Outcome::Ignored,
+            },
+        ];
+        assert_eq!(got.blocks, should_be);
+        assert!(got.error.is_none());
+        assert!(got.failed());
+    }
+
+    #[test]
+    fn failures_without_any_results_are_kept() {
+        let got = ChapterReport::from_rustdoc_output(
+            "Chapter",
+            Path::new("ch.md"),
+            Path::new("/tmp/book/ch.md"),
+            false,
+            "",
+            "error: couldn't read ch.md",
+        );
+
+        assert!(got.blocks.is_empty());
+        assert!(got.error.unwrap().contains("couldn't read ch.md"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn code_blocks_are_piped_to_their_runner() {
+        let content = "# Chapter
+
+```sh
+test \"$(cat /dev/null)\" = \"\"
+```
+
+```sh,should_fail
+exit 1
+```
+
+```sh,ignore
+exit 1
+```
+
+```sh
+echo oops >&2; exit 1
+```
+
+```python
+not run
+```
+";
+        let mut runners = BTreeMap::new();
+        runners.insert(
+            String::from("sh"),
+            TestRunner {
+                command: String::from("sh"),
+            },
+        );
+
+        let got = run_code_blocks(content, &runners, Path::new("."));
+        let outcomes: Vec<_> = got.iter().map(|b| (b.line, b.outcome.clone())).collect();
+
+        let should_be = vec![
+            (3, Outcome::Passed),
+            (7, Outcome::Passed),
+            (11, Outcome::Ignored),
+            (15, Outcome::Failed(String::from("oops"))),
+        ];
+        assert_eq!(outcomes, should_be);
+        assert_eq!(got[0].name, "sh");
+    }
+
+    #[test]
+    fn block_lines_point_into_the_source_file() {
+        let source = "---
+title: Chapter
+---
+# Chapter
+
+```rust
+{{#include example.rs}}
+```
+
+```rust
+fn second() {}
+```
+";
+        let content = "# Chapter
+
+```rust
+fn included() {}
+fn main() {}
+```
+
+```rust
+fn second() {}
+```
+";
+        let block = |line| BlockResult {
+            name: String::from("Chapter"),
+            line,
+            in_source: true,
+            outcome: Outcome::Passed,
+        };
+        let mut blocks = vec![block(3), block(8)];
+
+        map_lines_to_source(&mut blocks, content, source);
+
+        let lines: Vec<_> = blocks.iter().map(|b| (b.line, b.in_source)).collect();
+        assert_eq!(lines, vec![(3, false), (10, true)]);
+        assert_eq!(blocks[0].location(), "line 3 of the preprocessed chapter");
+        assert_eq!(blocks[1].location(), "line 10");
+    }
+
+    #[test]
+    fn summary_lists_failing_blocks() {
+        let report = TestReport {
+            title: None,
+            chapters: vec![chapter_report()],
+        };
+
+        let should_be = "Test results:
+    ch.md ... FAILED (1 passed, 1 failed, 1 ignored)
+        line 9: Title::Sub
+1 chapter tested: 1 passed, 1 failed, 1 ignored";
+        assert_eq!(report.to_string(), should_be);
+    }
+
+    #[test]
+    fn junit_xml_has_a_suite_per_chapter() {
+        let report = TestReport {
+            title: Some(String::from("My Book")),
+            chapters: vec![chapter_report()],
+        };
+
+        let xml = report.to_junit_xml();
+        assert!(xml.contains(r#"<testsuites name="My Book" tests="3" failures="1" errors="0">"#));
+        assert!(xml
+            .contains(r#"<testsuite name="ch.md" tests="3" failures="1" errors="0" skipped="1">"#));
+        assert!(xml.contains(
+            r#"<testcase name="Title (line 3)" classname="ch.md" file="ch.md" line="3"/>"#
+        ));
+        assert!(xml.contains("<failure message=\"code block failed\">error[E0308]"));
+        assert!(xml.contains("<skipped/>"));
+    }
+
+    #[test]
+    fn rustdoc_errors_are_counted_and_get_their_own_test_case() {
+        let mut chapter = chapter_report();
+        chapter.error = Some(String::from("Unable to run rustdoc"));
+        let report = TestReport {
+            title: None,
+            chapters: vec![chapter],
+        };
+
+        assert!(report
+            .to_string()
+            .ends_with("1 chapter tested: 1 passed, 2 failed, 1 ignored"));
+
+        let xml = report.to_junit_xml();
+        assert!(xml.contains(r#"<testsuites name="mdbook" tests="4" failures="1" errors="1">"#));
+        assert!(xml
+            .contains(r#"<testsuite name="ch.md" tests="4" failures="1" errors="1" skipped="1">"#));
+        assert!(xml.contains(
+            "    <testcase name=\"rustdoc\" classname=\"ch.md\" file=\"ch.md\">\n      \
+             <error message=\"rustdoc returned an error\">Unable to run rustdoc</error>\n    \
+             </testcase>\n"
+        ));
+    }
+}
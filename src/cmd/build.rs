@@ -1,5 +1,6 @@
 use crate::{get_book_dir, open};
-use clap::{App, ArgMatches, SubCommand};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use mdbook::diagnostic::{self, Diagnostic};
 use mdbook::errors::Result;
 use mdbook::MDBook;
 
@@ -17,10 +18,31 @@ pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
              (Defaults to the Current Directory when omitted)'",
         )
         .arg_from_usage("-o, --open 'Opens the compiled book in a web browser'")
+        .arg(
+            Arg::with_name("message-format")
+                .long("message-format")
+                .takes_value(true)
+                .possible_values(&["human", "json"])
+                .default_value("human")
+                .help("How errors and warnings about the book's source are reported"),
+        )
 }
 
 // Build command implementation
 pub fn execute(args: &ArgMatches) -> Result<()> {
+    let json = args.value_of("message-format") == Some("json");
+    diagnostic::set_json_output(json);
+
+    let result = build(args);
+    if let Err(ref e) = result {
+        if json {
+            Diagnostic::from_error(e).emit();
+        }
+    }
+    result
+}
+
+fn build(args: &ArgMatches) -> Result<()> {
     let book_dir = get_book_dir(args);
     let mut book = MDBook::load(&book_dir)?;
 
@@ -32,7 +54,11 @@ pub fn execute(args: &ArgMatches) -> Result<()> {
 
     if args.is_present("open") {
         // FIXME: What's the right behaviour if we don't use the HTML renderer?
-        open(book.build_dir_for("html").join("index.html"));
+        let mut build_dir = book.build_dir_for("html");
+        if book.config.book.multilingual {
+            build_dir.push(book.config.book.default_language());
+        }
+        open(build_dir.join("index.html"));
     }
 
     Ok(())
@@ -1,6 +1,7 @@
 use crate::get_book_dir;
 use anyhow::Context;
 use clap::{App, ArgMatches, SubCommand};
+use mdbook::renderer::HtmlHandlebars;
 use mdbook::MDBook;
 use std::fs;
 
@@ -34,6 +35,7 @@ pub fn execute(args: &ArgMatches) -> mdbook::errors::Result<()> {
         fs::remove_dir_all(&dir_to_remove)
             .with_context(|| "Unable to remove the build directory")?;
     }
+    HtmlHandlebars::remove_incremental_state(&book.root)?;
 
     Ok(())
 }
@@ -5,6 +5,7 @@ pub mod clean;
 pub mod init;
 #[cfg(feature = "serve")]
 pub mod serve;
+pub mod summary;
 pub mod test;
 #[cfg(feature = "watch")]
 pub mod watch;
@@ -9,7 +9,7 @@ use mdbook::utils;
 use mdbook::utils::fs::get_404_output_file;
 use mdbook::MDBook;
 use std::net::{SocketAddr, ToSocketAddrs};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tokio::sync::broadcast;
 use warp::ws::Message;
 use warp::Filter;
@@ -72,6 +72,11 @@ pub fn execute(args: &ArgMatches) -> Result<()> {
         }
         // Override site-url for local serving of the 404 file
         book.config.set("output.html.site-url", "/").unwrap();
+        // Only re-render the chapters that changed between builds, unless the
+        // book has its own setting
+        if book.config.get("output.html.incremental").is_none() {
+            book.config.set("output.html.incremental", true).unwrap();
+        }
     };
     update_config(&mut book);
     book.build()?;
@@ -87,7 +92,14 @@ pub fn execute(args: &ArgMatches) -> Result<()> {
         .map(toml::Value::as_str)
         .and_then(std::convert::identity) // flatten
         .map(ToString::to_string);
-    let file_404 = get_404_output_file(&input_404);
+    let mut file_404 = PathBuf::from(get_404_output_file(&input_404));
+    let mut serving_url = format!("http://{}", address);
+    // Each translation of a multilingual book has its own 404 page
+    if book.config.book.multilingual {
+        let language = book.config.book.default_language();
+        file_404 = Path::new(language).join(file_404);
+        serving_url = format!("{}/{}/", serving_url, language);
+    }
 
     // A channel used to broadcast to any websockets to reload when a file changes.
     let (tx, _rx) = tokio::sync::broadcast::channel::<Message>(100);
@@ -97,7 +109,6 @@ pub fn execute(args: &ArgMatches) -> Result<()> {
         serve(build_dir, sockaddr, reload_tx, &file_404);
     });
 
-    let serving_url = format!("http://{}", address);
     info!("Serving on: {}", serving_url);
 
     if open_browser {
@@ -133,7 +144,7 @@ async fn serve(
     build_dir: PathBuf,
     address: SocketAddr,
     reload_tx: broadcast::Sender<Message>,
-    file_404: &str,
+    file_404: &Path,
 ) {
     // A warp Filter which captures `reload_tx` and provides an `rx` copy to
     // receive reload messages.
@@ -0,0 +1,114 @@
+use crate::get_book_dir;
+use anyhow::{bail, Context};
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+use mdbook::book::{
+    append_to_summary, expand_nested_summaries, find_unlinked_chapters, generate_summary,
+    parse_summary,
+};
+use mdbook::config::Config;
+use mdbook::errors::Result;
+use std::fs;
+use std::path::PathBuf;
+
+// Create clap subcommand arguments
+pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("summary")
+        .about("Generates SUMMARY.md, or finds the chapters missing from it")
+        .arg_from_usage(
+            "[dir] 'Root directory for the book{n}\
+             (Defaults to the Current Directory when omitted)'",
+        )
+        .arg_from_usage(
+            "--generate 'Write a SUMMARY.md listing every chapter in the source directory'",
+        )
+        .arg_from_usage("--force 'Replace an existing SUMMARY.md when generating one'")
+        .arg_from_usage("--append 'Add the chapters missing from SUMMARY.md to its end'")
+        .arg_from_usage("--check 'Fail if any chapters are missing from SUMMARY.md'")
+        .arg(
+            Arg::with_name("ignore")
+                .short("i")
+                .long("ignore")
+                .value_name("path")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help(
+                    "A file or directory in the source directory which isn't a chapter{n}\
+                     (Can be given more than once)",
+                ),
+        )
+        .group(ArgGroup::with_name("mode").args(&["generate", "append", "check"]))
+}
+
+// Summary command implementation
+pub fn execute(args: &ArgMatches) -> Result<()> {
+    let book_dir = get_book_dir(args);
+    let config_location = book_dir.join("book.toml");
+    let mut config = if config_location.exists() {
+        Config::from_disk(&config_location)?
+    } else {
+        Config::default()
+    };
+    config.update_from_env();
+
+    let src_dir = book_dir.join(&config.book.src);
+    let summary_path = src_dir.join("SUMMARY.md");
+
+    let mut ignore: Vec<PathBuf> = args
+        .values_of("ignore")
+        .map(|paths| paths.map(PathBuf::from).collect())
+        .unwrap_or_default();
+    let input_404 = config
+        .html_config()
+        .and_then(|html_config| html_config.input_404)
+        .unwrap_or_else(|| String::from("404.md"));
+    ignore.push(input_404.into());
+    // Translations are in `src/<language>`
+    ignore.extend(config.language.keys().map(PathBuf::from));
+    let build_dir = book_dir.join(&config.build.build_dir);
+    if let Ok(build_dir) = build_dir.strip_prefix(&src_dir) {
+        if !build_dir.as_os_str().is_empty() {
+            ignore.push(build_dir.to_path_buf());
+        }
+    }
+
+    if args.is_present("generate") {
+        if summary_path.exists() && !args.is_present("force") {
+            bail!(
+                "{} already exists, use --force to replace it",
+                summary_path.display()
+            );
+        }
+        let summary = generate_summary(&src_dir, &ignore)?;
+        fs::write(&summary_path, summary)
+            .with_context(|| format!("Unable to write {}", summary_path.display()))?;
+        info!("Generated {}", summary_path.display());
+        return Ok(());
+    }
+
+    let text = fs::read_to_string(&summary_path)
+        .with_context(|| format!("Couldn't open SUMMARY.md in {:?} directory", src_dir))?;
+    let summary = parse_summary(&text).with_context(|| "Summary parsing failed")?;
+    let summary = expand_nested_summaries(summary, &src_dir)?;
+    let unlinked = find_unlinked_chapters(&src_dir, &summary, &ignore)?;
+
+    if unlinked.is_empty() {
+        info!("Every chapter is in SUMMARY.md");
+    } else if args.is_present("append") {
+        let appended = append_to_summary(&text, &src_dir, &unlinked)?;
+        fs::write(&summary_path, appended)
+            .with_context(|| format!("Unable to write {}", summary_path.display()))?;
+        for file in &unlinked {
+            info!("Added {} to SUMMARY.md", file.display());
+        }
+    } else {
+        for file in &unlinked {
+            warn!("{} is missing from SUMMARY.md", file.display());
+        }
+        if args.is_present("check") {
+            bail!("{} chapter(s) are missing from SUMMARY.md", unlinked.len());
+        }
+    }
+
+    Ok(())
+}
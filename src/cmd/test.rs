@@ -1,7 +1,10 @@
 use crate::get_book_dir;
+use anyhow::{bail, Context};
 use clap::{App, Arg, ArgMatches, SubCommand};
+use mdbook::book::TestOptions;
 use mdbook::errors::Result;
 use mdbook::MDBook;
+use std::fs;
 
 // Create clap subcommand arguments
 pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
@@ -25,6 +28,14 @@ pub fn make_subcommand<'a, 'b>() -> App<'a, 'b> {
             .multiple(true)
             .empty_values(false)
             .help("A comma-separated list of directories to add to {n}the crate search path when building tests"))
+        .arg_from_usage(
+            "-c, --chapter=[chapter] 'Only test the chapter with this name or source path'",
+        )
+        .arg_from_usage("--junit=[file] 'Write the results to a JUnit XML file'")
+        .arg_from_usage(
+            "-j, --jobs=[jobs] 'How many chapters to test at the same time{n}\
+             (Defaults to the number of CPUs)'",
+        )
 }
 
 // test command implementation
@@ -40,7 +51,32 @@ pub fn execute(args: &ArgMatches) -> Result<()> {
         book.config.build.build_dir = dest_dir.into();
     }
 
-    book.test(library_paths)?;
+    let jobs = match args.value_of("jobs") {
+        Some(jobs) => match jobs.parse() {
+            Ok(jobs) if jobs > 0 => Some(jobs),
+            _ => bail!(
+                "The number of jobs must be a positive integer, got `{}`",
+                jobs
+            ),
+        },
+        None => None,
+    };
 
+    let options = TestOptions {
+        library_paths,
+        chapter: args.value_of("chapter"),
+        jobs,
+    };
+    let report = book.test_with_options(&options)?;
+    report.log();
+
+    if let Some(junit) = args.value_of("junit") {
+        fs::write(junit, report.to_junit_xml())
+            .with_context(|| format!("Unable to write the JUnit report to {}", junit))?;
+    }
+
+    if report.failed() {
+        bail!("One or more tests failed");
+    }
     Ok(())
 }
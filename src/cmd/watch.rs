@@ -34,6 +34,15 @@ pub fn execute(args: &ArgMatches) -> Result<()> {
         if let Some(dest_dir) = args.value_of("dest-dir") {
             book.config.build.build_dir = dest_dir.into();
         }
+        // Only re-render the chapters that changed, unless the book has its
+        // own setting, without accidentally enabling the HTML renderer for
+        // books which don't use it.
+        let uses_html = book.config.get("output").is_none() || book.config.html_config().is_some();
+        if uses_html && book.config.get("output.html.incremental").is_none() {
+            book.config
+                .set("output.html.incremental", true)
+                .expect("incremental update failed");
+        }
     };
     update_config(&mut book);
 
@@ -50,7 +50,7 @@
 #![deny(missing_docs)]
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs::File;
 use std::io::Read;
@@ -72,6 +72,8 @@ pub struct Config {
     pub build: BuildConfig,
     /// Information about Rust language support.
     pub rust: RustConfig,
+    /// The translations of a multilingual book, keyed by language code.
+    pub language: BTreeMap<String, LanguageConfig>,
     rest: Value,
 }
 
@@ -227,10 +229,12 @@ impl Config {
         let value = Value::try_from(value)
             .with_context(|| "Unable to represent the item as a JSON Value")?;
 
-        if index.starts_with("book.") {
-            self.book.update_value(&index[5..], value);
-        } else if index.starts_with("build.") {
-            self.build.update_value(&index[6..], value);
+        if let Some(key) = index.strip_prefix("book.") {
+            self.book.update_value(key, value);
+        } else if let Some(key) = index.strip_prefix("build.") {
+            self.build.update_value(key, value);
+        } else if let Some(key) = index.strip_prefix("language.") {
+            self.language.update_value(key, value);
         } else {
             self.rest.insert(index, value);
         }
@@ -290,6 +294,7 @@ impl Default for Config {
             book: BookConfig::default(),
             build: BuildConfig::default(),
             rust: RustConfig::default(),
+            language: BTreeMap::new(),
             rest: Value::Table(Table::default()),
         }
     }
@@ -339,10 +344,17 @@ impl<'de> Deserialize<'de> for Config {
             .transpose()?
             .unwrap_or_default();
 
+        let language: BTreeMap<String, LanguageConfig> = table
+            .remove("language")
+            .map(|language| language.try_into().map_err(D::Error::custom))
+            .transpose()?
+            .unwrap_or_default();
+
         Ok(Config {
             book,
             build,
             rust,
+            language,
             rest: Value::Table(table),
         })
     }
@@ -366,6 +378,12 @@ impl Serialize for Config {
             table.insert("rust", rust_config);
         }
 
+        if !self.language.is_empty() {
+            let language_config =
+                Value::try_from(&self.language).expect("should always be serializable");
+            table.insert("language", language_config);
+        }
+
         table.serialize(s)
     }
 }
@@ -417,6 +435,9 @@ pub struct BookConfig {
     pub multilingual: bool,
     /// The main language of the book.
     pub language: Option<String>,
+    /// How the numbered chapters are labelled.
+    #[serde(skip_serializing_if = "Numbering::is_default")]
+    pub numbering: Numbering,
 }
 
 impl Default for BookConfig {
@@ -428,8 +449,106 @@ impl Default for BookConfig {
             src: PathBuf::from("src"),
             multilingual: false,
             language: Some(String::from("en")),
+            numbering: Numbering::default(),
+        }
+    }
+}
+
+impl BookConfig {
+    /// Get the language code of the book's default language.
+    ///
+    /// For a multilingual book this is the language whose source directory
+    /// is used as a fallback for untranslated chapters.
+    pub fn default_language(&self) -> &str {
+        self.language.as_deref().unwrap_or("en")
+    }
+}
+
+/// How the numbered chapters are labelled, configured by `[book.numbering]`.
+///
+/// Only the top-level number of a chapter takes the style, so with
+/// [`NumberingStyle::UpperAlpha`] the chapters are labelled "A.", "A.1." and
+/// so on.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Numbering {
+    /// The style of the chapters' numbers. Default: `arabic`.
+    pub style: Option<NumberingStyle>,
+    /// The numbering of the chapters in particular parts, by part title.
+    pub parts: HashMap<String, PartNumbering>,
+}
+
+impl Numbering {
+    /// The style of the numbers of the chapters after the part title `part`,
+    /// or before the first part title if it's `None`.
+    pub fn style(&self, part: Option<&str>) -> NumberingStyle {
+        match part.and_then(|part| self.parts.get(part)) {
+            Some(PartNumbering {
+                style: Some(style), ..
+            }) => *style,
+            Some(PartNumbering { appendix: true, .. }) => NumberingStyle::UpperAlpha,
+            _ => self.style.unwrap_or(NumberingStyle::Arabic),
         }
     }
+
+    /// Whether the numbering of the chapters after the part title `part`
+    /// starts again instead of continuing from the previous part.
+    pub fn restarts(&self, part: &str) -> bool {
+        matches!(
+            self.parts.get(part),
+            Some(PartNumbering { restart: true, .. }) | Some(PartNumbering { appendix: true, .. })
+        )
+    }
+
+    fn is_default(&self) -> bool {
+        self.style.is_none() && self.parts.is_empty()
+    }
+}
+
+/// The numbering of the chapters in one part of the book.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PartNumbering {
+    /// The style of the part's chapter numbers, instead of the book's.
+    pub style: Option<NumberingStyle>,
+    /// Number the part's chapters from the start again. Default: `false`.
+    pub restart: bool,
+    /// The part is the book's appendices, which are lettered "A.", "B." and
+    /// so on from the start again. Default: `false`.
+    pub appendix: bool,
+}
+
+/// How a chapter's number is written.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NumberingStyle {
+    /// 1, 2, 3
+    Arabic,
+    /// I, II, III
+    UpperRoman,
+    /// i, ii, iii
+    LowerRoman,
+    /// A, B, C
+    UpperAlpha,
+    /// a, b, c
+    LowerAlpha,
+}
+
+/// Configuration for a single translation of a multilingual book.
+///
+/// Each translation is declared as a `[language.<code>]` table, and its
+/// sources live in the `<code>` subdirectory of `book.src`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LanguageConfig {
+    /// The human readable name of the language (e.g. "日本語"), shown in the
+    /// language switcher. Defaults to the language code.
+    pub name: Option<String>,
+    /// The book's title in this language. Defaults to `book.title`.
+    pub title: Option<String>,
+    /// The book's description in this language. Defaults to
+    /// `book.description`.
+    pub description: Option<String>,
 }
 
 /// Configuration for the build procedure.
@@ -491,6 +610,9 @@ pub struct HtmlConfig {
     pub preferred_dark_theme: Option<String>,
     /// Use "smart quotes" instead of the usual `"` character.
     pub curly_quotes: bool,
+    /// Render blockquotes starting with a `[!TYPE]` marker, and blocks fenced
+    /// by `:::` lines, as admonitions.
+    pub admonitions: bool,
     /// Should mathjax be enabled?
     pub mathjax_support: bool,
     /// Whether to fonts.css and respective font files to the output directory.
@@ -509,8 +631,12 @@ pub struct HtmlConfig {
     pub playground: Playground,
     /// Print settings.
     pub print: Print,
+    /// Settings for the "On this page" navigation.
+    pub page_toc: PageToc,
     /// Don't render section labels.
     pub no_section_label: bool,
+    /// Show the chapters containing the current one above its content.
+    pub breadcrumbs: bool,
     /// Search settings. If `None`, the default will be used.
     pub search: Option<Search>,
     /// Git repository url. If `None`, the git button will not be shown.
@@ -522,6 +648,13 @@ pub struct HtmlConfig {
     pub input_404: Option<String>,
     /// Absolute url to site, used to emit correct paths for the 404 page, which might be accessed in a deeply nested directory
     pub site_url: Option<String>,
+    /// The image shown when a page is shared, either an absolute URL or a
+    /// path relative to the book's root.
+    pub social_image: Option<String>,
+    /// The `sitemap.xml` generated when `site_url` is set.
+    pub sitemap: Sitemap,
+    /// The Atom feed generated from a section of the book.
+    pub feed: Feed,
     /// The DNS subdomain or apex domain at which your book will be hosted. This
     /// string will be written to a file named CNAME in the root of your site,
     /// as required by GitHub Pages (see [*Managing a custom domain for your
@@ -533,6 +666,11 @@ pub struct HtmlConfig {
     /// directly jumping to editing the currently viewed page.
     /// Contains {path} that is replaced with chapter source file path
     pub edit_url_template: Option<String>,
+    /// Fail the build if a chapter links to a chapter, file or anchor which
+    /// doesn't exist in the book.
+    pub check_links: bool,
+    /// Where code blocks are syntax highlighted.
+    pub highlight: Highlight,
     /// This is used as a bit of a workaround for the `mdbook serve` command.
     /// Basically, because you set the websocket port from the command line, the
     /// `mdbook serve` command needs a way to let the HTML renderer know where
@@ -541,9 +679,27 @@ pub struct HtmlConfig {
     /// This config item *should not be edited* by the end user.
     #[doc(hidden)]
     pub livereload_url: Option<String>,
+    /// Only re-render the chapters which changed since the previous build.
+    /// `mdbook serve` and `mdbook watch` turn this on to speed up rebuilds.
+    ///
+    /// This config item *should not be edited* by the end user.
+    #[doc(hidden)]
+    pub incremental: bool,
     /// The mapping from old pages to new pages/URLs to use when generating
     /// redirects.
     pub redirect: HashMap<String, String>,
+    /// The templates to render chapters with, keyed by a pattern matching the
+    /// paths of the chapters which use them. Chapters which don't match any
+    /// pattern use `index.hbs`.
+    pub templates: HashMap<String, String>,
+    /// Additional handlebars partials, keyed by name, loaded from files in the
+    /// theme directory.
+    pub partials: HashMap<String, PathBuf>,
+    /// Additional handlebars helpers, keyed by name, loaded from [rhai]
+    /// scripts in the theme directory.
+    ///
+    /// [rhai]: https://rhai.rs/
+    pub helpers: HashMap<String, PathBuf>,
 }
 
 impl Default for HtmlConfig {
@@ -553,6 +709,7 @@ impl Default for HtmlConfig {
             default_theme: None,
             preferred_dark_theme: None,
             curly_quotes: false,
+            admonitions: false,
             mathjax_support: false,
             copy_fonts: true,
             google_analytics: None,
@@ -561,16 +718,27 @@ impl Default for HtmlConfig {
             fold: Fold::default(),
             playground: Playground::default(),
             print: Print::default(),
+            page_toc: PageToc::default(),
             no_section_label: false,
+            breadcrumbs: false,
             search: None,
             git_repository_url: None,
             git_repository_icon: None,
             edit_url_template: None,
+            check_links: false,
+            highlight: Highlight::Client,
             input_404: None,
             site_url: None,
+            social_image: None,
+            sitemap: Sitemap::default(),
+            feed: Feed::default(),
             cname: None,
             livereload_url: None,
+            incremental: false,
             redirect: HashMap::new(),
+            templates: HashMap::new(),
+            partials: HashMap::new(),
+            helpers: HashMap::new(),
         }
     }
 }
@@ -586,6 +754,16 @@ impl HtmlConfig {
     }
 }
 
+/// Where the HTML renderer's code blocks are syntax highlighted.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Highlight {
+    /// In the reader's browser, using highlight.js.
+    Client,
+    /// While the book is built, so pages are highlighted without JavaScript.
+    Server,
+}
+
 /// Configuration for how to render the print icon, print.html, and print.css.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -600,6 +778,80 @@ impl Default for Print {
     }
 }
 
+/// Configuration for the "On this page" navigation listing the headings of
+/// the current chapter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PageToc {
+    /// Whether the navigation is shown. Default: `false`.
+    pub enable: bool,
+    /// The deepest level of heading listed, counting the chapter's title as
+    /// level 1. Default: `3`.
+    pub depth: u8,
+}
+
+impl Default for PageToc {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            depth: 3,
+        }
+    }
+}
+
+/// Configuration for the `sitemap.xml` listing every chapter, which is
+/// generated when `site-url` is set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Sitemap {
+    /// Whether the sitemap is generated. Default: `true`.
+    pub enable: bool,
+    /// Where the date each chapter was last modified comes from.
+    /// Default: `mtime`.
+    pub lastmod: LastModified,
+    /// Also generate a `robots.txt` pointing to the sitemap, unless the book
+    /// has its own. Default: `false`.
+    pub robots_txt: bool,
+}
+
+impl Default for Sitemap {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            lastmod: LastModified::Mtime,
+            robots_txt: false,
+        }
+    }
+}
+
+/// Where the `lastmod` dates of the sitemap come from.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LastModified {
+    /// The modification time of each chapter's source file.
+    Mtime,
+    /// The date of the last git commit changing each chapter's source file,
+    /// falling back to its modification time.
+    Git,
+    /// Leave the dates out.
+    Omit,
+}
+
+/// Configuration for the Atom feed (`feed.xml`) listing the chapters in one
+/// section of the book, like its release notes.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Feed {
+    /// Whether the feed is generated. Default: `false`.
+    pub enable: bool,
+    /// The part title or chapter name in `SUMMARY.md` whose chapters are the
+    /// feed's entries.
+    pub section: Option<String>,
+    /// The title of the feed. Defaults to the book's title followed by the
+    /// section's name.
+    pub title: Option<String>,
+}
+
 /// Configuration for how to fold chapters of sidebar.
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
@@ -689,6 +941,42 @@ impl Default for Search {
     }
 }
 
+/// Configuration for the EPUB renderer.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct EpubConfig {
+    /// Use "smart quotes" instead of the usual `"` character.
+    pub curly_quotes: bool,
+    /// Render blockquotes starting with a `[!TYPE]` marker, and blocks fenced
+    /// by `:::` lines, as admonitions.
+    pub admonitions: bool,
+    /// Additional CSS stylesheets to link from every chapter.
+    pub additional_css: Vec<PathBuf>,
+    /// An image in the source directory to use as the book's cover.
+    pub cover_image: Option<PathBuf>,
+    /// The unique identifier of the publication, such as its ISBN or a
+    /// `urn:uuid:`. Defaults to one derived from the book's title.
+    pub identifier: Option<String>,
+}
+
+/// Configuration for `mdbook test`, from the `[test]` table.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TestConfig {
+    /// Commands which test code blocks in languages other than Rust, keyed
+    /// by the language in the code block's info string.
+    pub runners: BTreeMap<String, TestRunner>,
+}
+
+/// A command which tests code blocks written in a particular language.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TestRunner {
+    /// The command to run. Each code block is written to its standard input,
+    /// and it should exit successfully if the code block is valid.
+    pub command: String,
+}
+
 /// Allows you to "update" any arbitrary field in a struct by round-tripping via
 /// a `toml::Value`.
 ///
@@ -764,6 +1052,7 @@ mod tests {
             multilingual: true,
             src: PathBuf::from("source"),
             language: Some(String::from("ja")),
+            numbering: Numbering::default(),
         };
         let build_should_be = BuildConfig {
             build_dir: PathBuf::from("outputs"),
@@ -806,6 +1095,32 @@ mod tests {
         assert_eq!(got.html_config().unwrap(), html_should_be);
     }
 
+    #[test]
+    fn load_the_languages_of_a_multilingual_book() {
+        let src = r#"
+        [book]
+        multilingual = true
+        language = "en"
+
+        [language.en]
+        name = "English"
+
+        [language.ja]
+        name = "日本語"
+        title = "本"
+        "#;
+
+        let got = Config::from_str(src).unwrap();
+
+        let languages: Vec<_> = got.language.keys().collect();
+        assert_eq!(languages, vec!["en", "ja"]);
+        assert_eq!(got.language["ja"].name.as_deref(), Some("日本語"));
+        assert_eq!(got.language["ja"].title.as_deref(), Some("本"));
+        assert_eq!(got.language["en"].title, None);
+        assert_eq!(got.book.default_language(), "en");
+        assert!(got.get("language").is_none());
+    }
+
     #[test]
     fn edition_2015() {
         let src = r#"
@@ -1098,6 +1413,37 @@ mod tests {
         assert_eq!(&get_404_output_file(&html_config.input_404), "missing.html");
     }
 
+    #[test]
+    fn numbering_styles_per_part() {
+        let src = r#"
+        [book.numbering]
+        style = "upper-roman"
+
+        [book.numbering.parts."Reference"]
+        style = "lower-alpha"
+        restart = true
+
+        [book.numbering.parts."Appendices"]
+        appendix = true
+        "#;
+
+        let got = Config::from_str(src).unwrap();
+        let numbering = &got.book.numbering;
+        assert_eq!(numbering.style(None), NumberingStyle::UpperRoman);
+        assert_eq!(numbering.style(Some("Guide")), NumberingStyle::UpperRoman);
+        assert_eq!(
+            numbering.style(Some("Reference")),
+            NumberingStyle::LowerAlpha
+        );
+        assert_eq!(
+            numbering.style(Some("Appendices")),
+            NumberingStyle::UpperAlpha
+        );
+        assert!(!numbering.restarts("Guide"));
+        assert!(numbering.restarts("Reference"));
+        assert!(numbering.restarts("Appendices"));
+    }
+
     #[test]
     #[should_panic(expected = "Invalid configuration file")]
     fn invalid_language_type_error() {
@@ -0,0 +1,296 @@
+//! Diagnostics pointing at the place in a book's source which caused a
+//! problem.
+//!
+//! A [`Diagnostic`] is a normal error type, so it can be returned through
+//! `anyhow` like any other error, and later recovered from the error chain
+//! with [`Diagnostic::from_error`]. Problems which aren't fatal can be
+//! reported with [`Diagnostic::emit`].
+//!
+//! Diagnostics are printed rustc-style by default:
+//!
+//! ```text
+//! The link items for nested chapters must only contain a hyperlink
+//!  --> src/SUMMARY.md:3:5
+//!   |
+//! 3 |   - [Nested](nested.md) and some text
+//!   |     ^^^^^^^^^^^^^^^^^^^
+//! ```
+//!
+//! After calling [`set_json_output`] they are instead written to stdout as
+//! one JSON object per line, for editors and other tools to consume.
+
+use crate::errors::*;
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Emit diagnostics as JSON on stdout instead of logging them.
+pub fn set_json_output(enabled: bool) {
+    JSON_OUTPUT.store(enabled, Ordering::SeqCst);
+}
+
+/// How serious a diagnostic is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// The problem stops the book from being built.
+    Error,
+    /// The book can still be built, but probably not the way the author
+    /// intended.
+    Warning,
+}
+
+/// A region of a source file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    /// The byte offset the span starts at.
+    pub start: usize,
+    /// The byte offset just after the end of the span.
+    pub end: usize,
+    /// The 1-based line the span starts on.
+    pub line: usize,
+    /// The 1-based column (in characters) the span starts at.
+    pub column: usize,
+    /// An optional message explaining what is wrong with this part of the
+    /// source.
+    pub label: Option<String>,
+    /// The full line of source the span starts on, used when printing the
+    /// diagnostic.
+    #[serde(skip)]
+    source_line: String,
+    /// How many characters of `source_line` the span covers.
+    #[serde(skip)]
+    underline: usize,
+}
+
+/// A problem with a book, along with where in its source it was found.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// How serious the problem is.
+    pub level: Level,
+    /// A description of the problem.
+    pub message: String,
+    /// The file the problem was found in, if it's known.
+    pub file: Option<PathBuf>,
+    /// The part of `file` the problem was found in.
+    pub span: Option<Span>,
+    /// Any additional information which may help fix the problem.
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Create an error diagnostic with the provided message.
+    pub fn error<S: Into<String>>(message: S) -> Diagnostic {
+        Diagnostic {
+            level: Level::Error,
+            message: message.into(),
+            file: None,
+            span: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Create a warning diagnostic with the provided message.
+    pub fn warning<S: Into<String>>(message: S) -> Diagnostic {
+        Diagnostic {
+            level: Level::Warning,
+            ..Diagnostic::error(message)
+        }
+    }
+
+    /// Set the file the problem was found in.
+    pub fn with_file<P: Into<PathBuf>>(mut self, file: P) -> Diagnostic {
+        self.file = Some(file.into());
+        self
+    }
+
+    /// Point at the byte range `span` of `source`, which is the content of
+    /// the diagnostic's file.
+    pub fn with_span(mut self, source: &str, span: Range<usize>, label: Option<String>) -> Self {
+        let before = &source[..span.start];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = source[span.start..]
+            .find('\n')
+            .map(|i| span.start + i)
+            .unwrap_or_else(|| source.len());
+
+        self.span = Some(Span {
+            start: span.start,
+            end: span.end,
+            line: before.matches('\n').count() + 1,
+            column: source[line_start..span.start].chars().count() + 1,
+            label,
+            source_line: source[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_string(),
+            underline: source[span.start..span.end.min(line_end)]
+                .chars()
+                .count()
+                .max(1),
+        });
+        self
+    }
+
+    /// Add a note with extra information about the problem.
+    pub fn with_note<S: Into<String>>(mut self, note: S) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Find the diagnostic in an error's chain of causes, or create one from
+    /// the error's messages if there isn't any.
+    pub fn from_error(error: &Error) -> Diagnostic {
+        if let Some(diagnostic) = error.downcast_ref::<Diagnostic>() {
+            return diagnostic.clone();
+        }
+
+        let messages: Vec<_> = error.chain().map(ToString::to_string).collect();
+        Diagnostic::error(messages.join(": "))
+    }
+
+    /// Set the file of the diagnostic in an error's chain of causes, if it
+    /// doesn't already know which file it came from.
+    pub fn set_file_in(error: &mut Error, file: &Path) {
+        if let Some(diagnostic) = error.downcast_mut::<Diagnostic>() {
+            if diagnostic.file.is_none() {
+                diagnostic.file = Some(file.to_path_buf());
+            }
+        }
+    }
+
+    /// Report a diagnostic which doesn't stop the book from being built.
+    pub fn emit(&self) {
+        if JSON_OUTPUT.load(Ordering::SeqCst) {
+            println!(
+                "{}",
+                serde_json::to_string(self).expect("Diagnostics are always serializable")
+            );
+        } else {
+            match self.level {
+                Level::Error => error!("{}", self),
+                Level::Warning => warn!("{}", self),
+            }
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+
+        let span = match self.span {
+            Some(ref span) => span,
+            None => {
+                if let Some(ref file) = self.file {
+                    write!(f, "\n --> {}", file.display())?;
+                }
+                return write_notes(f, &self.notes, 1);
+            }
+        };
+
+        let file = self
+            .file
+            .as_ref()
+            .map(|file| file.display().to_string())
+            .unwrap_or_else(|| String::from("<unknown>"));
+        let gutter = span.line.to_string().len();
+        let indent = " ".repeat(gutter);
+
+        write!(f, "\n{}--> {}:{}:{}", indent, file, span.line, span.column)?;
+        write!(f, "\n{} |", indent)?;
+        write!(f, "\n{} | {}", span.line, span.source_line)?;
+
+        write!(
+            f,
+            "\n{} | {}{}",
+            indent,
+            " ".repeat(span.column - 1),
+            "^".repeat(span.underline.max(1))
+        )?;
+        if let Some(ref label) = span.label {
+            write!(f, " {}", label)?;
+        }
+
+        write_notes(f, &self.notes, gutter + 1)
+    }
+}
+
+fn write_notes(f: &mut Formatter<'_>, notes: &[String], indent: usize) -> fmt::Result {
+    for note in notes {
+        write!(f, "\n{}= note: {}", " ".repeat(indent), note)?;
+    }
+    Ok(())
+}
+
+impl std::error::Error for Diagnostic {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "# Summary\n\n- [First](first.md)\n  - [Nested](nested.md) oops\n";
+
+    #[test]
+    fn span_knows_its_line_and_column() {
+        let start = SOURCE.find("[Nested]").unwrap();
+        let diag = Diagnostic::error("bad").with_span(SOURCE, start..start + 19, None);
+        let span = diag.span.unwrap();
+
+        assert_eq!(span.line, 4);
+        assert_eq!(span.column, 5);
+        assert_eq!(span.source_line, "  - [Nested](nested.md) oops");
+    }
+
+    #[test]
+    fn display_looks_like_rustc() {
+        let start = SOURCE.find("[Nested]").unwrap();
+        let diag = Diagnostic::error("Nested links must only contain a hyperlink")
+            .with_file("src/SUMMARY.md")
+            .with_span(SOURCE, start..start + 19, Some(String::from("this item")))
+            .with_note("remove the trailing text");
+
+        let should_be = "Nested links must only contain a hyperlink
+ --> src/SUMMARY.md:4:5
+  |
+4 |   - [Nested](nested.md) oops
+  |     ^^^^^^^^^^^^^^^^^^^ this item
+  = note: remove the trailing text";
+        assert_eq!(diag.to_string(), should_be);
+    }
+
+    #[test]
+    fn display_without_a_span() {
+        let diag = Diagnostic::error("Chapter file not found").with_file("src/missing.md");
+
+        assert_eq!(
+            diag.to_string(),
+            "Chapter file not found\n --> src/missing.md"
+        );
+    }
+
+    #[test]
+    fn diagnostics_are_found_in_the_error_chain() {
+        let mut error = Error::new(Diagnostic::error("inner")).context("outer");
+        Diagnostic::set_file_in(&mut error, Path::new("SUMMARY.md"));
+
+        let diag = Diagnostic::from_error(&error);
+        assert_eq!(diag.message, "inner");
+        assert_eq!(diag.file, Some(PathBuf::from("SUMMARY.md")));
+
+        let plain = anyhow::anyhow!("inner").context("outer");
+        assert_eq!(Diagnostic::from_error(&plain).message, "outer: inner");
+    }
+
+    #[test]
+    fn json_uses_lowercase_levels() {
+        let diag = Diagnostic::warning("careful").with_span("abc", 1..2, None);
+        let json = serde_json::to_value(&diag).unwrap();
+
+        assert_eq!(json["level"], "warning");
+        assert_eq!(json["span"]["column"], 2);
+        assert!(json["span"].get("source_line").is_none());
+    }
+}
@@ -99,6 +99,7 @@ extern crate pretty_assertions;
 
 pub mod book;
 pub mod config;
+pub mod diagnostic;
 pub mod preprocess;
 pub mod renderer;
 pub mod theme;
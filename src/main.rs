@@ -33,6 +33,7 @@ fn main() {
         #[cfg(feature = "serve")]
         ("serve", Some(sub_matches)) => cmd::serve::execute(sub_matches),
         ("test", Some(sub_matches)) => cmd::test::execute(sub_matches),
+        ("summary", Some(sub_matches)) => cmd::summary::execute(sub_matches),
         ("completions", Some(sub_matches)) => (|| {
             let shell: Shell = sub_matches
                 .value_of("shell")
@@ -70,6 +71,7 @@ fn create_clap_app<'a, 'b>() -> App<'a, 'b> {
         .subcommand(cmd::build::make_subcommand())
         .subcommand(cmd::test::make_subcommand())
         .subcommand(cmd::clean::make_subcommand())
+        .subcommand(cmd::summary::make_subcommand())
         .subcommand(
             SubCommand::with_name("completions")
                 .about("Generate shell completions for your shell to stdout")
@@ -1,3 +1,4 @@
+use crate::diagnostic::Diagnostic;
 use crate::errors::*;
 use crate::utils::{
     take_anchored_lines, take_lines, take_rustdoc_include_anchored_lines,
@@ -47,14 +48,17 @@ impl Preprocessor for LinkPreprocessor {
         book.for_each_mut(|section: &mut BookItem| {
             if let BookItem::Chapter(ref mut ch) = *section {
                 if let Some(ref chapter_path) = ch.path {
-                    let base = chapter_path
+                    // Untranslated chapters come from another language's
+                    // directory, so includes are relative to their source.
+                    let source_path = ch.source_path.as_ref().unwrap_or(chapter_path);
+                    let base = source_path
                         .parent()
                         .map(|dir| src_dir.join(dir))
                         .expect("All book items have a parent");
 
                     let mut chapter_title = ch.name.clone();
-                    let content =
-                        replace_all(&ch.content, base, chapter_path, 0, &mut chapter_title);
+                    let source = src_dir.join(source_path);
+                    let content = replace_all(&ch.content, base, &source, 0, &mut chapter_title);
                     ch.content = content;
                     if chapter_title != ch.name {
                         ctx.chapter_titles
@@ -106,18 +110,28 @@ where
                         replaced.push_str(&new_content);
                     }
                 } else {
-                    error!(
+                    Diagnostic::error(format!(
                         "Stack depth exceeded in {}. Check for cyclic includes",
                         source.display()
-                    );
+                    ))
+                    .with_file(source)
+                    .emit();
                 }
                 previous_end_index = link.end_index;
             }
             Err(e) => {
-                error!("Error updating \"{}\", {}", link.link_text, e);
+                let mut diagnostic =
+                    Diagnostic::error(format!("Error updating \"{}\", {}", link.link_text, e))
+                        .with_file(source);
+                // Spans in nested includes would point into the included file
+                if depth == 0 {
+                    let span = link.start_index..link.end_index;
+                    diagnostic = with_source_span(diagnostic, source, s, span);
+                }
                 for cause in e.chain().skip(1) {
-                    warn!("Caused By: {}", cause);
+                    diagnostic = diagnostic.with_note(format!("Caused By: {}", cause));
                 }
+                diagnostic.emit();
 
                 // This should make sure we include the raw `{{# ... }}` snippet
                 // in the page content if there are any errors.
@@ -130,6 +144,29 @@ where
     replaced
 }
 
+/// Point a diagnostic at `span` of a chapter's `content`, within its source
+/// file. The front matter stripped when the chapter was loaded shifts every
+/// offset, so they're measured from the end of the file. If an earlier
+/// preprocessor has changed the content, the span is labelled as a position in
+/// the preprocessed chapter instead.
+fn with_source_span(
+    diagnostic: Diagnostic,
+    source: &Path,
+    content: &str,
+    span: Range<usize>,
+) -> Diagnostic {
+    match fs::read_to_string(source) {
+        Ok(ref file) if file.ends_with(content) => {
+            let offset = file.len() - content.len();
+            diagnostic.with_span(file, offset + span.start..offset + span.end, None)
+        }
+        _ => {
+            let label = "position in the preprocessed chapter".to_string();
+            diagnostic.with_span(content, span, Some(label))
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 enum LinkType<'a> {
     Escaped,
@@ -933,4 +970,24 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn diagnostic_spans_skip_the_front_matter() {
+        let temp = tempfile::Builder::new().prefix("links").tempdir().unwrap();
+        let source = temp.path().join("chapter.md");
+        let content = "# Chapter\n\n{{#include missing.md}}\n";
+        fs::write(&source, format!("---\ntitle: Chapter\n---\n{}", content)).unwrap();
+        let span = 11..33;
+
+        let diagnostic =
+            with_source_span(Diagnostic::error("oops"), &source, content, span.clone());
+        let got = diagnostic.span.unwrap();
+        assert_eq!((got.line, got.column, got.label), (6, 1, None));
+
+        let changed = content.replace("Chapter", "Changed");
+        let diagnostic = with_source_span(Diagnostic::error("oops"), &source, &changed, span);
+        let got = diagnostic.span.unwrap();
+        assert_eq!(got.line, 3);
+        assert!(got.label.is_some());
+    }
 }
@@ -0,0 +1,305 @@
+use pulldown_cmark::{Event, Tag};
+use regex::Regex;
+use std::ops::Range;
+use std::path::Path;
+
+use super::{Preprocessor, PreprocessorContext};
+use crate::book::{Book, BookItem};
+use crate::diagnostic::Diagnostic;
+use crate::errors::*;
+use crate::utils;
+
+mod mathml;
+
+/// A preprocessor for rendering TeX math to MathML while the book is built,
+/// so readers don't need to load MathJax to see equations.
+///
+/// Display math is delimited by `$$ ... $$` or `\\[ ... \\]`, and inline
+/// math by `$ ... $` or `\\( ... \\)`. Math inside code is left alone.
+#[derive(Default)]
+pub struct MathPreprocessor;
+
+impl MathPreprocessor {
+    pub(crate) const NAME: &'static str = "math";
+
+    /// Create a new `MathPreprocessor`.
+    pub fn new() -> Self {
+        MathPreprocessor
+    }
+}
+
+impl Preprocessor for MathPreprocessor {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn run(&self, ctx: &PreprocessorContext, mut book: Book) -> Result<Book> {
+        let src_dir = ctx.root.join(&ctx.config.book.src);
+
+        book.for_each_mut(|section: &mut BookItem| {
+            if let BookItem::Chapter(ref mut ch) = *section {
+                if let Some(ref chapter_path) = ch.path {
+                    let file = src_dir.join(chapter_path);
+                    ch.content = render_math(&ch.content, &file);
+                }
+            }
+        });
+
+        Ok(book)
+    }
+
+    fn supports_renderer(&self, renderer: &str) -> bool {
+        renderer == "html" || renderer == "epub"
+    }
+}
+
+/// The math found in a chapter.
+struct Formula {
+    /// The span of the formula in the chapter, including its delimiters.
+    span: Range<usize>,
+    tex: String,
+    display: bool,
+}
+
+/// Replace every formula in `content` with its MathML. Formulas which can't
+/// be converted are reported and left as they are.
+fn render_math(content: &str, file: &Path) -> String {
+    let mut rendered = String::with_capacity(content.len());
+    let mut previous_end = 0;
+
+    for formula in find_formulas(content) {
+        match mathml::to_mathml(&formula.tex, formula.display) {
+            Ok(mathml) => {
+                rendered.push_str(&content[previous_end..formula.span.start]);
+                rendered.push_str(&mathml);
+                previous_end = formula.span.end;
+            }
+            Err(e) => Diagnostic::warning(format!("Unable to render math: {}", e))
+                .with_file(file)
+                .with_span(content, formula.span, None)
+                .emit(),
+        }
+    }
+
+    rendered.push_str(&content[previous_end..]);
+    rendered
+}
+
+fn find_formulas(content: &str) -> Vec<Formula> {
+    lazy_static! {
+        static ref BLANK_LINE: Regex = Regex::new(r"\n[ \t]*\r?\n").unwrap();
+    }
+
+    let skipped = code_and_html(content);
+    let mut formulas = Vec::new();
+    let mut i = 0;
+
+    while i < content.len() {
+        if let Some(range) = skipped.iter().find(|range| range.contains(&i)) {
+            i = range.end;
+            continue;
+        }
+
+        // Formulas can't run into code or HTML
+        let limit = skipped
+            .iter()
+            .map(|range| range.start)
+            .find(|&start| start > i)
+            .unwrap_or(content.len());
+        let rest = &content[i..limit];
+
+        let formula = if rest.starts_with(r"\\[") {
+            delimited(rest, r"\\[", r"\\]", true).map(|(end, tex)| (end, unescape(tex), true))
+        } else if rest.starts_with(r"\\(") {
+            delimited(rest, r"\\(", r"\\)", false).map(|(end, tex)| (end, unescape(tex), false))
+        } else if rest.starts_with(r"\$") {
+            i += 2;
+            continue;
+        } else if rest.starts_with("$$") {
+            delimited(rest, "$$", "$$", true).map(|(end, tex)| (end, tex.to_string(), true))
+        } else if rest.starts_with('$') {
+            let paragraph = match BLANK_LINE.find(rest) {
+                Some(m) => &rest[..m.start()],
+                None => rest,
+            };
+            inline_dollars(paragraph).map(|(end, tex)| (end, tex.to_string(), false))
+        } else {
+            None
+        };
+
+        match formula {
+            Some((end, tex, display)) => {
+                formulas.push(Formula {
+                    span: i..i + end,
+                    tex,
+                    display,
+                });
+                i += end;
+            }
+            None => i += rest.chars().next().map(char::len_utf8).unwrap_or(1),
+        }
+    }
+
+    formulas
+}
+
+/// The parts of the chapter which are code or raw HTML.
+fn code_and_html(content: &str) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+
+    for (event, range) in utils::new_cmark_parser(content).into_offset_iter() {
+        let is_code_or_html = matches!(
+            event,
+            Event::Start(Tag::CodeBlock(_)) | Event::Code(_) | Event::Html(_)
+        );
+        // Code blocks contain their own events
+        let is_nested = match ranges.last() {
+            Some(last) => last.end > range.start,
+            None => false,
+        };
+        if is_code_or_html && !is_nested {
+            ranges.push(range);
+        }
+    }
+
+    ranges
+}
+
+/// Find the end of a formula starting with `open` and ending with `close`,
+/// returning the length of the whole formula and the TeX inside it.
+fn delimited<'a>(
+    text: &'a str,
+    open: &str,
+    close: &str,
+    display: bool,
+) -> Option<(usize, &'a str)> {
+    let inner = &text[open.len()..];
+    let end = inner.find(close)?;
+    let tex = &inner[..end];
+
+    if tex.trim().is_empty() || (!display && tex.contains("\n\n")) {
+        return None;
+    }
+    Some((open.len() + end + close.len(), tex))
+}
+
+/// Find the end of inline math delimited by single dollar signs, following
+/// the same rules as pandoc so prices like "$5 and $10" aren't mistaken for
+/// math: the opening `$` mustn't be followed by whitespace, and the closing
+/// `$` mustn't follow whitespace or be followed by a digit.
+fn inline_dollars(text: &str) -> Option<(usize, &str)> {
+    let inner = &text[1..];
+    if inner.starts_with(char::is_whitespace) || inner.is_empty() {
+        return None;
+    }
+
+    let mut escaped = false;
+    for (i, c) in inner.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' => escaped = true,
+            '$' if i > 0 => {
+                let after_space = inner[..i].ends_with(char::is_whitespace);
+                let before_digit = inner[i + 1..].starts_with(|c: char| c.is_ascii_digit());
+                if after_space || before_digit {
+                    continue;
+                }
+                return Some((i + 2, &inner[..i]));
+            }
+            '$' => return None,
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Undo markdown's backslash escapes, which authors already had to use for
+/// `\\(` and `\\[` math when it was rendered by MathJax.
+fn unescape(tex: &str) -> String {
+    let mut unescaped = String::with_capacity(tex.len());
+    let mut chars = tex.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(&next) if c == '\\' && next.is_ascii_punctuation() => {
+                unescaped.push(next);
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formulas(content: &str) -> Vec<(&str, bool)> {
+        find_formulas(content)
+            .into_iter()
+            .map(|formula| (&content[formula.span], formula.display))
+            .collect()
+    }
+
+    #[test]
+    fn all_delimiters_are_found() {
+        let content =
+            "Inline $x^2$ and \\\\( y \\\\), display:\n\n$$\n\\sum x\n$$\n\n\\\\[ z \\\\]\n";
+
+        assert_eq!(
+            formulas(content),
+            vec![
+                ("$x^2$", false),
+                ("\\\\( y \\\\)", false),
+                ("$$\n\\sum x\n$$", true),
+                ("\\\\[ z \\\\]", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn dollar_amounts_are_not_math() {
+        assert!(formulas("It costs $5 and $10.").is_empty());
+        assert!(formulas("Between $ 20 $ and $30 $.").is_empty());
+        assert!(formulas("Not \\$math$ either").is_empty());
+        assert!(formulas("Starts $here\n\nbut ends$ later").is_empty());
+    }
+
+    #[test]
+    fn math_in_code_and_html_is_ignored() {
+        let content = "`$x$` <span title=\"$y$\">a</span>\n\n```\n$$z$$\n```\n";
+        assert!(formulas(content).is_empty());
+    }
+
+    #[test]
+    fn markdown_escapes_are_removed_from_mathjax_delimiters() {
+        let found = find_formulas(r"\\[ a \\\\ b \_c \\]");
+        assert_eq!(found[0].tex, r" a \\ b _c ");
+
+        let found = find_formulas(r"$a \\ b \_c$");
+        assert_eq!(found[0].tex, r"a \\ b \_c");
+    }
+
+    #[test]
+    fn formulas_are_replaced_with_mathml() {
+        let got = render_math("Area: $\\pi r^2$.", Path::new("chapter.md"));
+
+        assert_eq!(
+            got,
+            "Area: <math xmlns=\"http://www.w3.org/1998/Math/MathML\" alttext=\"&#92;pi r&#94;2\">\
+             <mrow><mi>π</mi><msup><mi>r</mi><mn>2</mn></msup></mrow></math>."
+        );
+    }
+
+    #[test]
+    fn invalid_formulas_are_left_alone() {
+        let content = "Oops: $\\frac{1}$";
+        assert_eq!(render_math(content, Path::new("chapter.md")), content);
+    }
+}
@@ -0,0 +1,896 @@
+//! A converter from (a commonly used subset of) TeX math to MathML.
+//!
+//! Everything is converted as it's parsed, so there is no intermediate syntax
+//! tree. The text inside MathML elements never contains ASCII punctuation
+//! (it's written as character references instead), which means the output
+//! can be embedded in markdown without the markdown parser interfering.
+
+use std::fmt::Write;
+
+/// Convert a TeX formula to a `<math>` element.
+pub(crate) fn to_mathml(tex: &str, display: bool) -> Result<String, String> {
+    let mut parser = Parser {
+        tokens: tokenize(tex),
+        pos: 0,
+        display,
+        variant: None,
+    };
+    let body = parser.parse_lines(None)?;
+
+    let mut alttext = String::new();
+    for c in tex.trim().chars() {
+        match c {
+            '\n' | '\r' => alttext.push(' '),
+            _ => push_escaped(&mut alttext, c),
+        }
+    }
+
+    Ok(format!(
+        r#"<math xmlns="http://www.w3.org/1998/Math/MathML"{} alttext="{}">{}</math>"#,
+        if display { r#" display="block""# } else { "" },
+        alttext,
+        body
+    ))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A control sequence, without its backslash.
+    Command(String),
+    Char(char),
+    Space,
+    Open,
+    Close,
+    Superscript,
+    Subscript,
+    Align,
+}
+
+fn tokenize(tex: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = tex.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\\' => match chars.next() {
+                Some(c) if c.is_ascii_alphabetic() => {
+                    let mut name = c.to_string();
+                    while let Some(&c) = chars.peek() {
+                        if !c.is_ascii_alphabetic() {
+                            break;
+                        }
+                        name.push(c);
+                        chars.next();
+                    }
+                    Token::Command(name)
+                }
+                Some(c) => Token::Command(c.to_string()),
+                None => Token::Char('\\'),
+            },
+            '{' => Token::Open,
+            '}' => Token::Close,
+            '^' => Token::Superscript,
+            '_' => Token::Subscript,
+            '&' => Token::Align,
+            '%' => {
+                // Comments run to the end of the line
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                continue;
+            }
+            c if c.is_whitespace() => Token::Space,
+            c => Token::Char(c),
+        };
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+/// A parsed element, along with whether scripts attached to it go above and
+/// below (like `\sum`) rather than to the side.
+struct Atom {
+    mathml: String,
+    limits: bool,
+}
+
+impl Atom {
+    fn new(mathml: String) -> Atom {
+        Atom {
+            mathml,
+            limits: false,
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    display: bool,
+    /// The `mathvariant` applied to identifiers by commands like `\mathbf`.
+    variant: Option<&'static str>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(&Token::Space) {
+            self.pos += 1;
+        }
+    }
+
+    /// Parse rows separated by `\\`, made of cells separated by `&`, up to
+    /// the `\end` of the environment `env` (or the end of the formula). A
+    /// single cell is returned as is, otherwise the rows become a table.
+    fn parse_lines(&mut self, env: Option<&str>) -> Result<String, String> {
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut cells = Vec::new();
+
+        loop {
+            cells.push(self.parse_row()?);
+
+            match self.next() {
+                Some(Token::Align) => {}
+                Some(Token::Command(ref command)) if command == "\\" || command == "cr" => {
+                    rows.push(std::mem::take(&mut cells));
+                }
+                Some(Token::Command(ref command)) if command == "end" => {
+                    let name = self.read_text_argument()?;
+                    if Some(name.as_str()) != env {
+                        return Err(format!("unexpected `\\end{{{}}}`", name));
+                    }
+                    break;
+                }
+                None if env.is_none() => break,
+                None => return Err(format!("missing `\\end{{{}}}`", env.unwrap_or_default())),
+                Some(Token::Close) => return Err(String::from("unmatched `}`")),
+                Some(Token::Command(command)) => {
+                    return Err(format!("unexpected `\\{}`", command));
+                }
+                Some(token) => return Err(format!("unexpected {:?}", token)),
+            }
+        }
+
+        // A trailing `\\` doesn't start another row
+        if !(cells.len() == 1 && cells[0].is_empty() && !rows.is_empty()) {
+            rows.push(cells);
+        }
+
+        if env.is_none() && rows.len() == 1 && rows[0].len() == 1 {
+            return Ok(rows.pop().unwrap().pop().unwrap());
+        }
+
+        let columnalign = match env {
+            Some("cases") => r#" columnalign="left left""#,
+            Some("aligned") | Some("align") | Some("align*") | Some("split") | None => {
+                r#" columnalign="right left right left right left""#
+            }
+            _ => "",
+        };
+        let mut table = format!("<mtable{}>", columnalign);
+        for row in rows {
+            table.push_str("<mtr>");
+            for cell in row {
+                write!(table, "<mtd>{}</mtd>", cell).unwrap();
+            }
+            table.push_str("</mtr>");
+        }
+        table.push_str("</mtable>");
+        Ok(table)
+    }
+
+    /// Parse elements up to the end of the current group, row or cell,
+    /// returning them as a single element.
+    fn parse_row(&mut self) -> Result<String, String> {
+        let mut nodes = Vec::new();
+
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                None | Some(Token::Close) | Some(Token::Align) => break,
+                Some(Token::Command(command))
+                    if command == "\\"
+                        || command == "cr"
+                        || command == "end"
+                        || command == "right" =>
+                {
+                    break
+                }
+                _ => {}
+            }
+
+            if let Some(atom) = self.parse_atom()? {
+                nodes.push(self.parse_scripts(atom)?);
+            }
+        }
+
+        Ok(match nodes.len() {
+            1 => nodes.pop().unwrap(),
+            _ => format!("<mrow>{}</mrow>", nodes.concat()),
+        })
+    }
+
+    /// Attach any sub- and superscripts following `base` to it.
+    fn parse_scripts(&mut self, base: Atom) -> Result<String, String> {
+        let mut sub = None;
+        let mut sup = None;
+
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some(Token::Subscript) if sub.is_none() => {
+                    self.pos += 1;
+                    sub = Some(self.parse_argument()?);
+                }
+                Some(Token::Superscript) if sup.is_none() => {
+                    self.pos += 1;
+                    sup = Some(self.parse_argument()?);
+                }
+                Some(Token::Char('\'')) if sup.is_none() => {
+                    let mut primes = String::new();
+                    while self.peek() == Some(&Token::Char('\'')) {
+                        self.pos += 1;
+                        primes.push('′');
+                    }
+                    sup = Some(format!("<mo>{}</mo>", primes));
+                }
+                Some(Token::Subscript) | Some(Token::Superscript) => {
+                    return Err(String::from("double subscript or superscript"));
+                }
+                _ => break,
+            }
+        }
+
+        let (under, over, both) = if base.limits && self.display {
+            ("munder", "mover", "munderover")
+        } else {
+            ("msub", "msup", "msubsup")
+        };
+        let base = base.mathml;
+
+        Ok(match (sub, sup) {
+            (None, None) => base,
+            (Some(sub), None) => format!("<{0}>{1}{2}</{0}>", under, base, sub),
+            (None, Some(sup)) => format!("<{0}>{1}{2}</{0}>", over, base, sup),
+            (Some(sub), Some(sup)) => format!("<{0}>{1}{2}{3}</{0}>", both, base, sub, sup),
+        })
+    }
+
+    /// Parse the argument of a command or script, which is either a group
+    /// or a single token.
+    fn parse_argument(&mut self) -> Result<String, String> {
+        self.skip_spaces();
+        match self.peek() {
+            Some(Token::Open) => {
+                self.pos += 1;
+                let row = self.parse_row()?;
+                self.expect_close()?;
+                Ok(row)
+            }
+            None => Err(String::from("missing argument")),
+            _ => match self.parse_atom()? {
+                Some(atom) => Ok(atom.mathml),
+                None => Err(String::from("missing argument")),
+            },
+        }
+    }
+
+    fn expect_close(&mut self) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Close) => Ok(()),
+            _ => Err(String::from("missing `}`")),
+        }
+    }
+
+    /// Read a group's contents as plain text, as used by `\text` and
+    /// `\begin`.
+    fn read_text_argument(&mut self) -> Result<String, String> {
+        self.skip_spaces();
+        if self.next() != Some(Token::Open) {
+            return Err(String::from("expected `{`"));
+        }
+
+        let mut text = String::new();
+        let mut depth = 0;
+        loop {
+            match self.next() {
+                Some(Token::Open) => depth += 1,
+                Some(Token::Close) if depth == 0 => return Ok(text),
+                Some(Token::Close) => depth -= 1,
+                Some(Token::Char(c)) => text.push(c),
+                Some(Token::Space) => text.push(' '),
+                Some(Token::Command(command)) => match command.as_str() {
+                    " " | "," | ";" | ":" => text.push(' '),
+                    c if c.len() == 1 => text.push_str(c),
+                    _ => return Err(format!("unsupported `\\{}` in text", command)),
+                },
+                Some(Token::Superscript) => text.push('^'),
+                Some(Token::Subscript) => text.push('_'),
+                Some(Token::Align) => text.push('&'),
+                None => return Err(String::from("missing `}`")),
+            }
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Option<Atom>, String> {
+        let token = match self.next() {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+
+        let mathml = match token {
+            Token::Open => {
+                let row = self.parse_row()?;
+                self.expect_close()?;
+                row
+            }
+            Token::Char(c) if c.is_ascii_digit() || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(&Token::Char(c)) = self.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    number.push(c);
+                    self.pos += 1;
+                }
+                if number == "." {
+                    element("mo", ".")
+                } else {
+                    element("mn", &number)
+                }
+            }
+            Token::Char(c) if c.is_alphabetic() => self.identifier(&c.to_string()),
+            Token::Char('-') => element("mo", "−"),
+            Token::Char('*') => element("mo", "∗"),
+            Token::Char('\'') => element("mo", "′"),
+            Token::Char('~') => r#"<mspace width="0.333em"/>"#.to_string(),
+            Token::Char(c) => element("mo", &c.to_string()),
+            Token::Command(command) => return self.parse_command(&command),
+            Token::Superscript | Token::Subscript => {
+                // A script without a base, like `^2`
+                self.pos -= 1;
+                return Ok(Some(Atom::new(String::from("<mrow></mrow>"))));
+            }
+            Token::Space | Token::Close | Token::Align => return Ok(None),
+        };
+
+        Ok(Some(Atom::new(mathml)))
+    }
+
+    fn identifier(&self, name: &str) -> String {
+        match self.variant {
+            Some(variant) => format!(
+                r#"<mi mathvariant="{}">{}</mi>"#,
+                variant,
+                escape_text(name)
+            ),
+            None => element("mi", name),
+        }
+    }
+
+    fn parse_command(&mut self, command: &str) -> Result<Option<Atom>, String> {
+        if let Some(symbol) = greek(command) {
+            let mathml = if symbol.chars().all(char::is_uppercase) && self.variant.is_none() {
+                format!(r#"<mi mathvariant="normal">{}</mi>"#, symbol)
+            } else {
+                self.identifier(symbol)
+            };
+            return Ok(Some(Atom::new(mathml)));
+        }
+        if let Some(symbol) = identifier_symbol(command) {
+            return Ok(Some(Atom::new(element("mi", symbol))));
+        }
+        if let Some(symbol) = operator(command) {
+            return Ok(Some(Atom::new(element("mo", symbol))));
+        }
+        if let Some((symbol, limits)) = large_operator(command) {
+            return Ok(Some(Atom {
+                mathml: format!(r#"<mo largeop="true">{}</mo>"#, symbol),
+                limits,
+            }));
+        }
+        if let Some(limits) = function(command) {
+            return Ok(Some(Atom {
+                mathml: element("mi", command),
+                limits,
+            }));
+        }
+        if let Some(width) = space(command) {
+            return Ok(Some(Atom::new(format!(r#"<mspace width="{}"/>"#, width))));
+        }
+        if let Some(variant) = font(command) {
+            let outer = self.variant.replace(variant);
+            let argument = self.parse_argument();
+            self.variant = outer;
+            return Ok(Some(Atom::new(argument?)));
+        }
+        if let Some(accent) = accent(command) {
+            let base = self.parse_argument()?;
+            return Ok(Some(Atom::new(format!(
+                r#"<mover accent="true">{}<mo stretchy="false">{}</mo></mover>"#,
+                base, accent
+            ))));
+        }
+
+        let mathml = match command {
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.parse_argument()?;
+                let denominator = self.parse_argument()?;
+                format!("<mfrac>{}{}</mfrac>", numerator, denominator)
+            }
+            "binom" => {
+                let n = self.parse_argument()?;
+                let k = self.parse_argument()?;
+                format!(
+                    r#"<mrow><mo>(</mo><mfrac linethickness="0">{}{}</mfrac><mo>)</mo></mrow>"#,
+                    n, k
+                )
+            }
+            "sqrt" => {
+                self.skip_spaces();
+                if self.peek() == Some(&Token::Char('[')) {
+                    self.pos += 1;
+                    let mut index = Vec::new();
+                    while self.peek() != Some(&Token::Char(']')) {
+                        match self.parse_atom()? {
+                            Some(atom) => index.push(atom.mathml),
+                            None if self.peek().is_none() => {
+                                return Err(String::from("missing `]`"))
+                            }
+                            None => {}
+                        }
+                    }
+                    self.pos += 1;
+                    let base = self.parse_argument()?;
+                    format!("<mroot>{}<mrow>{}</mrow></mroot>", base, index.concat())
+                } else {
+                    format!("<msqrt>{}</msqrt>", self.parse_argument()?)
+                }
+            }
+            "overline" => format!(
+                r#"<mover accent="true">{}<mo>‾</mo></mover>"#,
+                self.parse_argument()?
+            ),
+            "underline" => format!(
+                r#"<munder accentunder="true">{}<mo>_</mo></munder>"#,
+                self.parse_argument()?
+            ),
+            "text" | "textrm" | "textit" | "textbf" | "mbox" => {
+                element("mtext", &self.read_text_argument()?)
+            }
+            "operatorname" => {
+                return Ok(Some(Atom::new(element("mi", &self.read_text_argument()?))))
+            }
+            "left" => {
+                let open = self.read_delimiter()?;
+                let body = self.parse_row()?;
+                if self.next() != Some(Token::Command(String::from("right"))) {
+                    return Err(String::from("`\\left` without a matching `\\right`"));
+                }
+                let close = self.read_delimiter()?;
+                format!("<mrow>{}{}{}</mrow>", fence(&open), body, fence(&close))
+            }
+            "middle" => fence(&self.read_delimiter()?),
+            "begin" => {
+                let name = self.read_text_argument()?;
+                if name == "array" {
+                    // The column specification isn't supported
+                    self.read_text_argument()?;
+                }
+                let table = self.parse_lines(Some(&name))?;
+                let (open, close) = match name.as_str() {
+                    "matrix" | "array" | "aligned" | "align" | "align*" | "gathered" | "gather"
+                    | "gather*" | "split" | "smallmatrix" => ("", ""),
+                    "pmatrix" => ("(", ")"),
+                    "bmatrix" => ("[", "]"),
+                    "Bmatrix" => ("{", "}"),
+                    "vmatrix" => ("|", "|"),
+                    "Vmatrix" => ("‖", "‖"),
+                    "cases" => ("{", ""),
+                    _ => return Err(format!("unsupported environment `{}`", name)),
+                };
+                if open.is_empty() && close.is_empty() {
+                    table
+                } else {
+                    format!("<mrow>{}{}{}</mrow>", fence(open), table, fence(close))
+                }
+            }
+            "displaystyle" | "textstyle" | "scriptstyle" | "limits" | "nolimits" | "big"
+            | "Big" | "bigg" | "Bigg" | "bigl" | "bigr" | "Bigl" | "Bigr" | "biggl" | "biggr"
+            | "Biggl" | "Biggr" => return Ok(None),
+            "\\" | "cr" | "end" | "right" => return Err(format!("unexpected `\\{}`", command)),
+            _ => return Err(format!("unsupported command `\\{}`", command)),
+        };
+
+        Ok(Some(Atom::new(mathml)))
+    }
+
+    /// Read the delimiter following `\left`, `\middle` or `\right`, where
+    /// `.` means no delimiter.
+    fn read_delimiter(&mut self) -> Result<String, String> {
+        self.skip_spaces();
+        match self.next() {
+            Some(Token::Char('.')) => Ok(String::new()),
+            Some(Token::Char(c)) => Ok(c.to_string()),
+            Some(Token::Command(command)) => match operator(&command) {
+                Some(symbol) => Ok(symbol.to_string()),
+                None => Err(format!("`\\{}` isn't a delimiter", command)),
+            },
+            _ => Err(String::from("missing delimiter")),
+        }
+    }
+}
+
+fn fence(delimiter: &str) -> String {
+    if delimiter.is_empty() {
+        String::new()
+    } else {
+        format!(
+            r#"<mo fence="true" stretchy="true">{}</mo>"#,
+            escape_text(delimiter)
+        )
+    }
+}
+
+fn element(name: &str, text: &str) -> String {
+    format!("<{0}>{1}</{0}>", name, escape_text(text))
+}
+
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        push_escaped(&mut escaped, c);
+    }
+    escaped
+}
+
+/// Write ASCII punctuation as character references, so that neither HTML
+/// nor markdown treat it as markup.
+fn push_escaped(escaped: &mut String, c: char) {
+    if c.is_ascii_punctuation() {
+        write!(escaped, "&#{};", c as u32).unwrap();
+    } else {
+        escaped.push(c);
+    }
+}
+
+fn greek(command: &str) -> Option<&'static str> {
+    let symbol = match command {
+        "alpha" => "α",
+        "beta" => "β",
+        "gamma" => "γ",
+        "delta" => "δ",
+        "epsilon" => "ϵ",
+        "varepsilon" => "ε",
+        "zeta" => "ζ",
+        "eta" => "η",
+        "theta" => "θ",
+        "vartheta" => "ϑ",
+        "iota" => "ι",
+        "kappa" => "κ",
+        "lambda" => "λ",
+        "mu" => "μ",
+        "nu" => "ν",
+        "xi" => "ξ",
+        "pi" => "π",
+        "varpi" => "ϖ",
+        "rho" => "ρ",
+        "varrho" => "ϱ",
+        "sigma" => "σ",
+        "varsigma" => "ς",
+        "tau" => "τ",
+        "upsilon" => "υ",
+        "phi" => "ϕ",
+        "varphi" => "φ",
+        "chi" => "χ",
+        "psi" => "ψ",
+        "omega" => "ω",
+        "Gamma" => "Γ",
+        "Delta" => "Δ",
+        "Theta" => "Θ",
+        "Lambda" => "Λ",
+        "Xi" => "Ξ",
+        "Pi" => "Π",
+        "Sigma" => "Σ",
+        "Upsilon" => "Υ",
+        "Phi" => "Φ",
+        "Psi" => "Ψ",
+        "Omega" => "Ω",
+        _ => return None,
+    };
+    Some(symbol)
+}
+
+/// Symbols which are written as identifiers rather than operators.
+fn identifier_symbol(command: &str) -> Option<&'static str> {
+    let symbol = match command {
+        "infty" => "∞",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "hbar" => "ℏ",
+        "ell" => "ℓ",
+        "aleph" => "ℵ",
+        "Re" => "ℜ",
+        "Im" => "ℑ",
+        "wp" => "℘",
+        "emptyset" | "varnothing" => "∅",
+        "imath" => "ı",
+        "jmath" => "ȷ",
+        _ => return None,
+    };
+    Some(symbol)
+}
+
+fn operator(command: &str) -> Option<&'static str> {
+    let symbol = match command {
+        "pm" => "±",
+        "mp" => "∓",
+        "times" => "×",
+        "div" => "÷",
+        "cdot" => "⋅",
+        "ast" => "∗",
+        "star" => "⋆",
+        "circ" => "∘",
+        "bullet" => "∙",
+        "oplus" => "⊕",
+        "ominus" => "⊖",
+        "otimes" => "⊗",
+        "odot" => "⊙",
+        "le" | "leq" => "≤",
+        "ge" | "geq" => "≥",
+        "ne" | "neq" => "≠",
+        "ll" => "≪",
+        "gg" => "≫",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "simeq" => "≃",
+        "cong" => "≅",
+        "propto" => "∝",
+        "to" | "rightarrow" => "→",
+        "gets" | "leftarrow" => "←",
+        "leftrightarrow" => "↔",
+        "Rightarrow" => "⇒",
+        "Leftarrow" => "⇐",
+        "Leftrightarrow" => "⇔",
+        "implies" | "Longrightarrow" => "⟹",
+        "impliedby" | "Longleftarrow" => "⟸",
+        "iff" | "Longleftrightarrow" => "⟺",
+        "longrightarrow" => "⟶",
+        "longleftarrow" => "⟵",
+        "mapsto" => "↦",
+        "uparrow" => "↑",
+        "downarrow" => "↓",
+        "in" => "∈",
+        "notin" => "∉",
+        "ni" => "∋",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "supset" => "⊃",
+        "supseteq" => "⊇",
+        "cup" => "∪",
+        "cap" => "∩",
+        "setminus" => "∖",
+        "forall" => "∀",
+        "exists" => "∃",
+        "nexists" => "∄",
+        "neg" | "lnot" => "¬",
+        "land" | "wedge" => "∧",
+        "lor" | "vee" => "∨",
+        "perp" => "⊥",
+        "parallel" => "∥",
+        "mid" => "∣",
+        "angle" => "∠",
+        "triangle" => "△",
+        "prime" => "′",
+        "ldots" | "dots" => "…",
+        "cdots" => "⋯",
+        "vdots" => "⋮",
+        "ddots" => "⋱",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "vert" | "lvert" | "rvert" => "|",
+        "Vert" | "lVert" | "rVert" | "|" => "‖",
+        "colon" => ":",
+        "{" | "lbrace" => "{",
+        "}" | "rbrace" => "}",
+        "#" => "#",
+        "$" => "$",
+        "%" => "%",
+        "&" => "&",
+        "_" => "_",
+        _ => return None,
+    };
+    Some(symbol)
+}
+
+/// Operators which are drawn larger in display mode, and whether their
+/// scripts go above and below them.
+fn large_operator(command: &str) -> Option<(&'static str, bool)> {
+    let operator = match command {
+        "sum" => ("∑", true),
+        "prod" => ("∏", true),
+        "coprod" => ("∐", true),
+        "bigcup" => ("⋃", true),
+        "bigcap" => ("⋂", true),
+        "bigoplus" => ("⨁", true),
+        "bigotimes" => ("⨂", true),
+        "bigvee" => ("⋁", true),
+        "bigwedge" => ("⋀", true),
+        "int" => ("∫", false),
+        "iint" => ("∬", false),
+        "iiint" => ("∭", false),
+        "oint" => ("∮", false),
+        _ => return None,
+    };
+    Some(operator)
+}
+
+/// Named functions, and whether their scripts go above and below them.
+fn function(command: &str) -> Option<bool> {
+    match command {
+        "sin" | "cos" | "tan" | "cot" | "sec" | "csc" | "arcsin" | "arccos" | "arctan" | "sinh"
+        | "cosh" | "tanh" | "coth" | "log" | "ln" | "lg" | "exp" | "dim" | "ker" | "deg"
+        | "arg" | "hom" => Some(false),
+        "lim" | "liminf" | "limsup" | "min" | "max" | "sup" | "inf" | "det" | "gcd" | "Pr" => {
+            Some(true)
+        }
+        _ => None,
+    }
+}
+
+fn space(command: &str) -> Option<&'static str> {
+    let width = match command {
+        "," | "thinspace" => "0.167em",
+        ":" | ">" | "medspace" => "0.222em",
+        ";" | "thickspace" => "0.278em",
+        "!" | "negthinspace" => "-0.167em",
+        " " => "0.333em",
+        "quad" => "1em",
+        "qquad" => "2em",
+        _ => return None,
+    };
+    Some(width)
+}
+
+fn font(command: &str) -> Option<&'static str> {
+    let variant = match command {
+        "mathbf" => "bold",
+        "mathit" => "italic",
+        "mathrm" => "normal",
+        "mathbb" => "double-struck",
+        "mathcal" => "script",
+        "mathfrak" => "fraktur",
+        "mathsf" => "sans-serif",
+        "mathtt" => "monospace",
+        "boldsymbol" => "bold-italic",
+        _ => return None,
+    };
+    Some(variant)
+}
+
+fn accent(command: &str) -> Option<&'static str> {
+    let accent = match command {
+        "hat" | "widehat" => "^",
+        "bar" => "¯",
+        "tilde" | "widetilde" => "~",
+        "vec" => "→",
+        "dot" => "˙",
+        "ddot" => "¨",
+        "acute" => "´",
+        "grave" => "`",
+        "check" => "ˇ",
+        "breve" => "˘",
+        _ => return None,
+    };
+    Some(accent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(tex: &str) -> String {
+        let math = to_mathml(tex, false).unwrap();
+        let start = math.find('>').unwrap() + 1;
+        math[start..math.len() - "</math>".len()].to_string()
+    }
+
+    #[test]
+    fn identifiers_numbers_and_operators() {
+        assert_eq!(
+            convert("x + 12.5 = y"),
+            "<mrow><mi>x</mi><mo>&#43;</mo><mn>12&#46;5</mn><mo>&#61;</mo><mi>y</mi></mrow>"
+        );
+    }
+
+    #[test]
+    fn scripts_attach_to_the_previous_element() {
+        assert_eq!(
+            convert("x_i^2"),
+            "<msubsup><mi>x</mi><mi>i</mi><mn>2</mn></msubsup>"
+        );
+        assert_eq!(
+            convert("e^{i\\pi}"),
+            "<msup><mi>e</mi><mrow><mi>i</mi><mi>π</mi></mrow></msup>"
+        );
+        assert_eq!(convert("f'"), "<msup><mi>f</mi><mo>′</mo></msup>");
+    }
+
+    #[test]
+    fn large_operators_use_limits_when_displayed() {
+        let inline = to_mathml("\\sum_{i=0}^n i", false).unwrap();
+        assert!(inline.contains("<msubsup><mo largeop=\"true\">∑</mo>"));
+
+        let display = to_mathml("\\sum_{i=0}^n i", true).unwrap();
+        assert!(display.contains(" display=\"block\""));
+        assert!(display.contains("<munderover><mo largeop=\"true\">∑</mo>"));
+    }
+
+    #[test]
+    fn fractions_and_roots() {
+        assert_eq!(
+            convert("\\frac{1}{\\sqrt{2}}"),
+            "<mfrac><mn>1</mn><msqrt><mn>2</mn></msqrt></mfrac>"
+        );
+        assert_eq!(
+            convert("\\sqrt[3]{x}"),
+            "<mroot><mi>x</mi><mrow><mn>3</mn></mrow></mroot>"
+        );
+    }
+
+    #[test]
+    fn fonts_text_and_fences() {
+        assert_eq!(convert("\\mathbf{v}"), "<mi mathvariant=\"bold\">v</mi>");
+        assert_eq!(
+            convert("\\text{if } x"),
+            "<mrow><mtext>if </mtext><mi>x</mi></mrow>"
+        );
+        assert_eq!(
+            convert("\\left( x \\right."),
+            "<mrow><mo fence=\"true\" stretchy=\"true\">&#40;</mo><mi>x</mi></mrow>"
+        );
+    }
+
+    #[test]
+    fn environments_become_tables() {
+        assert_eq!(
+            convert("\\begin{pmatrix} a & b \\\\ c & d \\end{pmatrix}"),
+            "<mrow><mo fence=\"true\" stretchy=\"true\">&#40;</mo><mtable>\
+             <mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr>\
+             <mtr><mtd><mi>c</mi></mtd><mtd><mi>d</mi></mtd></mtr>\
+             </mtable><mo fence=\"true\" stretchy=\"true\">&#41;</mo></mrow>"
+        );
+    }
+
+    #[test]
+    fn tex_is_kept_as_alternative_text() {
+        let math = to_mathml("a < b", false).unwrap();
+        assert!(math.contains(r#"alttext="a &#60; b""#));
+    }
+
+    #[test]
+    fn invalid_formulas_are_errors() {
+        assert!(to_mathml("\\frac{1}", false).is_err());
+        assert!(to_mathml("\\unknown", false).is_err());
+        assert!(to_mathml("\\begin{pmatrix} a", false).is_err());
+        assert!(to_mathml("{x", false).is_err());
+        assert!(to_mathml("x}", false).is_err());
+    }
+}
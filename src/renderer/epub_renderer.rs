@@ -0,0 +1,660 @@
+use crate::book::{Book, BookItem, Chapter};
+use crate::config::{Config, EpubConfig};
+use crate::errors::*;
+use crate::renderer::html_handlebars::build_header_links;
+use crate::renderer::{RenderContext, Renderer};
+use crate::utils;
+
+use pulldown_cmark::{Event, Parser};
+use regex::{Captures, Regex};
+
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use zip::write::{FileOptions, ZipWriter};
+use zip::CompressionMethod;
+
+/// The name of the file the book is written to, inside the destination
+/// directory.
+const EPUB_FILE: &str = "book.epub";
+
+/// The stylesheet every chapter links to, before any `additional-css`.
+const STYLESHEET: &str = r#"body {
+    font-family: serif;
+    line-height: 1.5;
+}
+code, pre {
+    font-family: monospace;
+}
+pre {
+    white-space: pre-wrap;
+}
+table {
+    border-collapse: collapse;
+}
+td, th {
+    border: 1px solid;
+    padding: 0.2em 0.5em;
+}
+"#;
+
+/// A renderer which packages the book into a single EPUB 3 file.
+///
+/// Chapters are rendered with the same markdown pipeline as the HTML
+/// renderer, so links between chapters and heading ids stay consistent
+/// between the two outputs.
+#[derive(Default)]
+pub struct EpubRenderer;
+
+impl EpubRenderer {
+    /// Create a new `EpubRenderer` instance.
+    pub fn new() -> Self {
+        EpubRenderer
+    }
+}
+
+impl Renderer for EpubRenderer {
+    fn name(&self) -> &str {
+        "epub"
+    }
+
+    fn render(&self, ctx: &RenderContext) -> Result<()> {
+        let destination = &ctx.destination;
+        let epub_config: EpubConfig = ctx
+            .config
+            .get_deserialized_opt("output.epub")
+            .with_context(|| "Parsing configuration [output.epub]")?
+            .unwrap_or_default();
+        let src_dir = ctx.root.join(&ctx.config.book.src);
+
+        if destination.exists() {
+            utils::fs::remove_dir_content(destination)
+                .with_context(|| "Unable to remove stale EPUB output")?;
+        }
+        fs::create_dir_all(destination)
+            .with_context(|| "Unexpected error when constructing destination path")?;
+
+        trace!("epub render");
+        let build_dir = ctx.root.join(&ctx.config.build.build_dir);
+        let resources = find_resources(&src_dir, &build_dir)?;
+        let stylesheets = stylesheets(&epub_config);
+        let chapters = chapters(&ctx.book);
+        if chapters.is_empty() {
+            bail!("An EPUB needs at least one chapter");
+        }
+
+        let mut epub = EpubWriter::new(File::create(destination.join(EPUB_FILE))?)?;
+
+        epub.add_file("META-INF/container.xml", CONTAINER_XML.as_bytes())?;
+        epub.add_file(
+            "OEBPS/content.opf",
+            package_document(
+                &ctx.config,
+                &epub_config,
+                &chapters,
+                &resources,
+                &stylesheets,
+            )?
+            .as_bytes(),
+        )?;
+        epub.add_file("OEBPS/nav.xhtml", navigation_document(ctx).as_bytes())?;
+        epub.add_file("OEBPS/stylesheet.css", STYLESHEET.as_bytes())?;
+
+        for (path, custom_css) in stylesheets.iter().skip(1).zip(&epub_config.additional_css) {
+            let contents = fs::read(ctx.root.join(custom_css)).with_context(|| {
+                format!("Unable to read the stylesheet {}", custom_css.display())
+            })?;
+            epub.add_file(&format!("OEBPS/{}", path), &contents)?;
+        }
+
+        for ch in &chapters {
+            let path = chapter_href(ch);
+            debug!("Rendering {}", path);
+            let xhtml = chapter_document(ch, &ctx.config, &epub_config, &stylesheets);
+            epub.add_file(&format!("OEBPS/{}", path), xhtml.as_bytes())?;
+        }
+
+        for resource in &resources {
+            let contents = fs::read(src_dir.join(&resource.path))?;
+            epub.add_file(&format!("OEBPS/{}", resource.href), &contents)?;
+        }
+
+        epub.finish()?;
+        debug!("Creating {} ✓", EPUB_FILE);
+
+        Ok(())
+    }
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+/// A thin wrapper around a `ZipWriter` which knows about the EPUB's
+/// requirement for an uncompressed `mimetype` file at the very start of the
+/// archive.
+struct EpubWriter {
+    zip: ZipWriter<File>,
+}
+
+impl EpubWriter {
+    fn new(file: File) -> Result<EpubWriter> {
+        let mut zip = ZipWriter::new(file);
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        zip.start_file("mimetype", stored)?;
+        zip.write_all(b"application/epub+zip")?;
+
+        Ok(EpubWriter { zip })
+    }
+
+    fn add_file(&mut self, name: &str, contents: &[u8]) -> Result<()> {
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        self.zip
+            .start_file(name, options)
+            .with_context(|| format!("Unable to add {} to the EPUB", name))?;
+        self.zip.write_all(contents)?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.zip.finish()?;
+        Ok(())
+    }
+}
+
+/// A file from the source directory, such as an image, which chapters may
+/// refer to.
+#[derive(Debug, PartialEq)]
+struct Resource {
+    /// The path relative to the source directory.
+    path: PathBuf,
+    /// The path relative to the package document, using forward slashes.
+    href: String,
+    media_type: &'static str,
+}
+
+fn find_resources(src_dir: &Path, build_dir: &Path) -> Result<Vec<Resource>> {
+    fn walk(dir: &Path, src_dir: &Path, build_dir: &Path, out: &mut Vec<Resource>) -> Result<()> {
+        let mut entries = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        entries.sort();
+
+        for path in entries {
+            if path.is_dir() {
+                if path != build_dir {
+                    walk(&path, src_dir, build_dir, out)?;
+                }
+                continue;
+            }
+
+            let media_type = match path.extension().and_then(|ext| ext.to_str()) {
+                Some(ext) => match media_type(ext) {
+                    Some(media_type) => media_type,
+                    None => continue,
+                },
+                None => continue,
+            };
+            let relative = path.strip_prefix(src_dir).expect("walked from src_dir");
+            out.push(Resource {
+                href: to_href(relative),
+                path: relative.to_path_buf(),
+                media_type,
+            });
+        }
+
+        Ok(())
+    }
+
+    let mut resources = Vec::new();
+    if src_dir.is_dir() {
+        walk(src_dir, src_dir, build_dir, &mut resources)?;
+    }
+    Ok(resources)
+}
+
+/// The media types of the files in the source directory which get packaged
+/// alongside the chapters.
+fn media_type(extension: &str) -> Option<&'static str> {
+    let media_type = match extension.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "css" => "text/css",
+        "otf" => "font/otf",
+        "ttf" => "font/ttf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        _ => return None,
+    };
+    Some(media_type)
+}
+
+/// Turn a relative path into a URL path, which always uses forward slashes.
+fn to_href(path: &Path) -> String {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Every chapter which gets its own page, in reading order.
+fn chapters(book: &Book) -> Vec<&Chapter> {
+    book.iter()
+        .filter_map(|item| match item {
+            BookItem::Chapter(ch) if !ch.is_draft_chapter() => Some(ch),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Chapters keep the `.html` extension used by the HTML renderer, so links
+/// rewritten by `utils::render_markdown` still point at the right file.
+fn chapter_href(ch: &Chapter) -> String {
+    to_href(&ch.path.as_ref().unwrap().with_extension("html"))
+}
+
+/// The stylesheets each chapter links to, relative to the package document.
+fn stylesheets(epub_config: &EpubConfig) -> Vec<String> {
+    let mut stylesheets = vec![String::from("stylesheet.css")];
+    stylesheets.extend(epub_config.additional_css.iter().map(|css| {
+        let name = css
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("custom.css");
+        format!("custom/{}", name)
+    }));
+    stylesheets
+}
+
+fn package_document(
+    config: &Config,
+    epub_config: &EpubConfig,
+    chapters: &[&Chapter],
+    resources: &[Resource],
+    stylesheets: &[String],
+) -> Result<String> {
+    let book = &config.book;
+    let title = book.title.as_deref().unwrap_or("Untitled");
+    let language = book.language.as_deref().unwrap_or("en");
+    let identifier = match epub_config.identifier {
+        Some(ref identifier) => identifier.clone(),
+        None => format!("urn:mdbook:{}", utils::normalize_id(title)),
+    };
+    let modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+
+    let mut opf = String::new();
+    writeln!(opf, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        opf,
+        r#"<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" xml:lang="{}">"#,
+        utils::escape_xml(language)
+    )?;
+    writeln!(
+        opf,
+        r#"  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">"#
+    )?;
+    writeln!(
+        opf,
+        r#"    <dc:identifier id="book-id">{}</dc:identifier>"#,
+        utils::escape_xml(&identifier)
+    )?;
+    writeln!(opf, "    <dc:title>{}</dc:title>", utils::escape_xml(title))?;
+    writeln!(
+        opf,
+        "    <dc:language>{}</dc:language>",
+        utils::escape_xml(language)
+    )?;
+    for author in &book.authors {
+        writeln!(
+            opf,
+            "    <dc:creator>{}</dc:creator>",
+            utils::escape_xml(author)
+        )?;
+    }
+    if let Some(ref description) = book.description {
+        writeln!(
+            opf,
+            "    <dc:description>{}</dc:description>",
+            utils::escape_xml(description)
+        )?;
+    }
+    writeln!(
+        opf,
+        r#"    <meta property="dcterms:modified">{}</meta>"#,
+        modified
+    )?;
+    writeln!(opf, "  </metadata>")?;
+
+    writeln!(opf, "  <manifest>")?;
+    writeln!(
+        opf,
+        r#"    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#
+    )?;
+    for (i, css) in stylesheets.iter().enumerate() {
+        writeln!(
+            opf,
+            r#"    <item id="stylesheet-{}" href="{}" media-type="text/css"/>"#,
+            i,
+            utils::escape_xml(css)
+        )?;
+    }
+    for (i, ch) in chapters.iter().enumerate() {
+        // Chapters with math rendered by the math preprocessor contain MathML
+        let properties = if ch.content.contains("<math") {
+            r#" properties="mathml""#
+        } else {
+            ""
+        };
+        writeln!(
+            opf,
+            r#"    <item id="chapter-{}" href="{}" media-type="application/xhtml+xml"{}/>"#,
+            i,
+            utils::escape_xml(&chapter_href(ch)),
+            properties
+        )?;
+    }
+    let cover_image = epub_config.cover_image.as_deref();
+    if let Some(cover) = cover_image {
+        if !resources.iter().any(|resource| resource.path == cover) {
+            bail!(
+                "The cover image {} could not be found in the source directory",
+                cover.display()
+            );
+        }
+    }
+    for (i, resource) in resources.iter().enumerate() {
+        let properties = if Some(resource.path.as_path()) == cover_image {
+            r#" properties="cover-image""#
+        } else {
+            ""
+        };
+        writeln!(
+            opf,
+            r#"    <item id="resource-{}" href="{}" media-type="{}"{}/>"#,
+            i,
+            utils::escape_xml(&resource.href),
+            resource.media_type,
+            properties
+        )?;
+    }
+    writeln!(opf, "  </manifest>")?;
+
+    writeln!(opf, "  <spine>")?;
+    for (i, ch) in chapters.iter().enumerate() {
+        // Hidden chapters are only reached by following a link to them
+        let linear = if ch.hidden { r#" linear="no""# } else { "" };
+        writeln!(opf, r#"    <itemref idref="chapter-{}"{}/>"#, i, linear)?;
+    }
+    writeln!(opf, "  </spine>")?;
+    writeln!(opf, "</package>")?;
+
+    Ok(opf)
+}
+
+fn navigation_document(ctx: &RenderContext) -> String {
+    let book = &ctx.config.book;
+    let title = book.title.as_deref().unwrap_or("Untitled");
+    let language = book.language.as_deref().unwrap_or("en");
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" lang="{lang}" xml:lang="{lang}">
+<head>
+<meta charset="UTF-8" />
+<title>{title}</title>
+</head>
+<body>
+<nav epub:type="toc" id="toc">
+<h1>{title}</h1>
+{items}</nav>
+</body>
+</html>
+"#,
+        lang = utils::escape_xml(language),
+        title = utils::escape_xml(title),
+        items = navigation_items(
+            &ctx.book.sections,
+            &mut ctx.book.section_labels(&book.numbering).iter()
+        ),
+    )
+}
+
+/// Render the table of contents as nested ordered lists. Part titles and
+/// separators have no page to point at, so they're left out, along with
+/// hidden chapters. The `labels` are the book's `section_labels()`, in the
+/// order the items are walked.
+fn navigation_items<'a>(
+    items: &[BookItem],
+    labels: &mut impl Iterator<Item = &'a Option<String>>,
+) -> String {
+    let mut list = String::new();
+
+    for item in items {
+        let section = labels.next().and_then(Option::as_ref);
+        let ch = match item {
+            BookItem::Chapter(ch) if !ch.hidden => ch,
+            _ => continue,
+        };
+        let nested = navigation_items(&ch.sub_items, labels);
+        let label = match section {
+            Some(section) => format!("{} {}", section, utils::escape_xml(&ch.name)),
+            None => utils::escape_xml(&ch.name),
+        };
+
+        let entry = if ch.is_draft_chapter() {
+            // A list item without a link must be followed by a nested list.
+            if nested.is_empty() {
+                continue;
+            }
+            format!("<span>{}</span>", label)
+        } else {
+            format!(
+                r#"<a href="{}">{}</a>"#,
+                utils::escape_xml(&chapter_href(ch)),
+                label
+            )
+        };
+
+        list.push_str("<li>");
+        list.push_str(&entry);
+        list.push_str(&nested);
+        list.push_str("</li>\n");
+    }
+
+    if list.is_empty() {
+        list
+    } else {
+        format!("<ol>\n{}</ol>\n", list)
+    }
+}
+
+fn chapter_document(
+    ch: &Chapter,
+    config: &Config,
+    epub_config: &EpubConfig,
+    stylesheets: &[String],
+) -> String {
+    let path = ch.path.as_ref().unwrap();
+    let path_to_root = utils::fs::path_to_root(path);
+    let language = config.book.language.as_deref().unwrap_or("en");
+    let content = to_xhtml(&build_header_links(&utils::render_markdown_with_options(
+        &ch.content,
+        epub_config.curly_quotes,
+        epub_config.admonitions,
+        None,
+    )));
+
+    let links: String = stylesheets
+        .iter()
+        .map(|css| {
+            format!(
+                r#"<link rel="stylesheet" type="text/css" href="{}{}" />"#,
+                path_to_root,
+                utils::escape_xml(css)
+            ) + "\n"
+        })
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" lang="{lang}" xml:lang="{lang}">
+<head>
+<meta charset="UTF-8" />
+<title>{title}</title>
+{links}</head>
+<body>
+{content}
+</body>
+</html>
+"#,
+        lang = utils::escape_xml(language),
+        title = utils::escape_xml(&ch.name),
+        links = links,
+        content = content,
+    )
+}
+
+/// Make the HTML rendered from a chapter well-formed XHTML. The markdown
+/// renderer's own output already is, but any raw HTML in the chapter is
+/// passed through as written, so tag and attribute names are lowercased,
+/// attribute values are quoted, void elements like `<br>` are closed, and
+/// named character references other than XML's own become numeric ones.
+fn to_xhtml(html: &str) -> String {
+    lazy_static! {
+        static ref TAG: Regex = Regex::new(
+            r#"<(/?)([A-Za-z][A-Za-z0-9-]*)((?:\s+[^\s"'<>/=]+(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'=<>`]+))?)*)\s*(/?)>"#
+        )
+        .unwrap();
+        static ref ATTRIBUTE: Regex = Regex::new(
+            r#"([^\s"'<>/=]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#
+        )
+        .unwrap();
+        static ref REFERENCE: Regex =
+            Regex::new(r"&(#[0-9]+;|#[xX][0-9A-Fa-f]+;|[A-Za-z][A-Za-z0-9]*;)?").unwrap();
+    }
+    const VOID_ELEMENTS: &[&str] = &[
+        "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param",
+        "source", "track", "wbr",
+    ];
+
+    let tags = TAG.replace_all(html, |caps: &Captures<'_>| {
+        let name = caps[2].to_ascii_lowercase();
+        if !caps[1].is_empty() {
+            return format!("</{}>", name);
+        }
+
+        let mut tag = format!("<{}", name);
+        for attribute in ATTRIBUTE.captures_iter(&caps[3]) {
+            // Boolean attributes like `checked` are written out in full
+            let value = (2..=4)
+                .find_map(|i| attribute.get(i))
+                .map_or(&attribute[1], |value| value.as_str());
+            let value = value.replace('"', "&quot;").replace('<', "&lt;");
+            write!(tag, r#" {}="{}""#, attribute[1].to_ascii_lowercase(), value).unwrap();
+        }
+        if !caps[4].is_empty() || VOID_ELEMENTS.contains(&name.as_str()) {
+            tag.push_str(" /");
+        }
+        tag.push('>');
+        tag
+    });
+
+    REFERENCE
+        .replace_all(&tags, |caps: &Captures<'_>| match caps.get(1) {
+            None => String::from("&amp;"),
+            Some(reference) => match reference.as_str() {
+                "amp;" | "lt;" | "gt;" | "quot;" | "apos;" => caps[0].to_string(),
+                r if r.starts_with('#') => caps[0].to_string(),
+                _ => match decode_entity(&caps[0]) {
+                    Some(decoded) => decoded
+                        .chars()
+                        .map(|c| format!("&#{};", c as u32))
+                        .collect(),
+                    None => format!("&amp;{}", reference.as_str()),
+                },
+            },
+        })
+        .into_owned()
+}
+
+/// The text a named character reference like `&nbsp;` stands for, decoded by
+/// the markdown parser, which knows every one defined by HTML.
+fn decode_entity(reference: &str) -> Option<String> {
+    let decoded = Parser::new(reference).fold(String::new(), |mut decoded, event| {
+        if let Event::Text(text) = event {
+            decoded.push_str(&text);
+        }
+        decoded
+    });
+    if decoded == reference {
+        None
+    } else {
+        Some(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book::SectionNumber;
+
+    #[test]
+    fn raw_html_becomes_well_formed_xhtml() {
+        let html = "<p>Line<BR>break&nbsp;&hellip; &amp; R&D &#169;</p>\n\
+                    <input type=checkbox checked><img src='a.png' alt=\"\">\n\
+                    <hr/><p>&unknown;</p>";
+
+        assert_eq!(
+            to_xhtml(html),
+            "<p>Line<br />break&#160;&#8230; &amp; R&amp;D &#169;</p>\n\
+             <input type=\"checkbox\" checked=\"checked\" /><img src=\"a.png\" alt=\"\" />\n\
+             <hr /><p>&amp;unknown;</p>"
+        );
+    }
+
+    #[test]
+    fn navigation_nests_sub_chapters_and_skips_drafts() {
+        let mut nested = Chapter::new("Nested", String::new(), "first/nested.md", Vec::new());
+        nested.number = Some(SectionNumber(vec![1, 1]));
+        let mut first = Chapter::new("First", String::new(), "first/index.md", Vec::new());
+        first.number = Some(SectionNumber(vec![1]));
+        first.sub_items.push(BookItem::Chapter(nested));
+        let draft = Chapter::new_draft("Draft", Vec::new());
+
+        let items = vec![
+            BookItem::Chapter(first),
+            BookItem::Separator,
+            BookItem::Chapter(draft),
+        ];
+
+        let labels = [Some("1.".to_string()), Some("1.1.".to_string()), None, None];
+        assert_eq!(
+            navigation_items(&items, &mut labels.iter()),
+            "<ol>\n<li><a href=\"first/index.html\">1. First</a><ol>\n\
+             <li><a href=\"first/nested.html\">1.1. Nested</a></li>\n</ol>\n</li>\n</ol>\n"
+        );
+    }
+
+    #[test]
+    fn only_known_media_types_are_packaged() {
+        assert_eq!(media_type("PNG"), Some("image/png"));
+        assert_eq!(media_type("svg"), Some("image/svg+xml"));
+        assert_eq!(media_type("md"), None);
+        assert_eq!(media_type("rs"), None);
+    }
+}
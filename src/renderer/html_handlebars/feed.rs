@@ -0,0 +1,284 @@
+//! The Atom feed (`feed.xml`) generated from one section of the book, so
+//! readers can follow things like its release notes.
+
+use std::path::Path;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use super::hbs_renderer::absolute_site_url;
+use super::sitemap::modification_time;
+use crate::book::{Book, BookItem, Chapter};
+use crate::config::{Config, Feed, HtmlConfig};
+use crate::errors::*;
+use crate::utils::{self, escape_xml};
+
+struct Entry {
+    title: String,
+    url: String,
+    updated: DateTime<Utc>,
+    content: String,
+}
+
+/// The title of the feed.
+pub(super) fn title(config: &Config, feed: &Feed) -> String {
+    if let Some(ref title) = feed.title {
+        return title.clone();
+    }
+    match (&config.book.title, &feed.section) {
+        (Some(book_title), Some(section)) => format!("{}: {}", book_title, section),
+        (Some(title), None) | (None, Some(title)) => title.clone(),
+        (None, None) => String::new(),
+    }
+}
+
+/// Creates `feed.xml`, with an entry for each chapter in the configured
+/// section of the book.
+pub fn create_files(
+    config: &Config,
+    html_config: &HtmlConfig,
+    src_dir: &Path,
+    destination: &Path,
+    book: &Book,
+) -> Result<()> {
+    let section = match html_config.feed.section {
+        Some(ref section) => section,
+        None => {
+            bail!("`output.html.feed.section` must name the part or chapter to use as the feed")
+        }
+    };
+    let base = match absolute_site_url(&config.book, html_config) {
+        Some(base) => base,
+        None => {
+            // `mdbook serve` sets a path-only site-url, so this isn't an error
+            warn!(
+                "Not generating feed.xml because `output.html.site-url` isn't an absolute URL, \
+                 like \"https://example.com/book/\""
+            );
+            return Ok(());
+        }
+    };
+    let chapters = match section_chapters(book, section) {
+        Some(chapters) => chapters,
+        None => bail!(
+            "The feed's section \"{}\" isn't a part title or chapter in SUMMARY.md",
+            section
+        ),
+    };
+
+    let mut entries = Vec::with_capacity(chapters.len());
+    for ch in chapters {
+        let path = match ch.path {
+            Some(ref path) => path,
+            None => continue,
+        };
+        let url = format!(
+            "{}{}",
+            base,
+            utils::fs::normalize_path(&path.with_extension("html").to_string_lossy())
+        );
+        let content = utils::render_markdown_with_options(
+            &ch.content,
+            html_config.curly_quotes,
+            html_config.admonitions,
+            Some(path),
+        );
+
+        entries.push(Entry {
+            title: ch.name.clone(),
+            url,
+            updated: updated(ch, src_dir)?,
+            content,
+        });
+    }
+    // Newest first
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.updated));
+
+    let feed_url = format!("{}feed.xml", base);
+    let updated = entries
+        .first()
+        .map(|entry| entry.updated)
+        .unwrap_or_else(Utc::now);
+
+    let mut feed = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\">\n",
+    );
+    feed.push_str(&format!(
+        "  <title>{}</title>\n",
+        escape_xml(&title(config, &html_config.feed))
+    ));
+    feed.push_str(&format!("  <id>{}</id>\n", escape_xml(&feed_url)));
+    feed.push_str(&format!(
+        "  <link rel=\"self\" href=\"{}\"/>\n",
+        escape_xml(&feed_url)
+    ));
+    feed.push_str(&format!(
+        "  <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n",
+        escape_xml(&base)
+    ));
+    feed.push_str(&format!("  <updated>{}</updated>\n", timestamp(&updated)));
+    // Atom requires an author, which the entries inherit from the feed.
+    let authors = if config.book.authors.is_empty() {
+        vec![title(config, &html_config.feed)]
+    } else {
+        config.book.authors.clone()
+    };
+    for author in authors {
+        feed.push_str(&format!(
+            "  <author><name>{}</name></author>\n",
+            escape_xml(&author)
+        ));
+    }
+
+    for entry in entries {
+        feed.push_str("  <entry>\n");
+        feed.push_str(&format!(
+            "    <title>{}</title>\n",
+            escape_xml(&entry.title)
+        ));
+        feed.push_str(&format!("    <id>{}</id>\n", escape_xml(&entry.url)));
+        feed.push_str(&format!(
+            "    <link rel=\"alternate\" type=\"text/html\" href=\"{}\"/>\n",
+            escape_xml(&entry.url)
+        ));
+        feed.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            timestamp(&entry.updated)
+        ));
+        // Links in the content are relative to the root of the book.
+        feed.push_str(&format!(
+            "    <content type=\"html\" xml:base=\"{}\">{}</content>\n",
+            escape_xml(&base),
+            escape_xml(&entry.content)
+        ));
+        feed.push_str("  </entry>\n");
+    }
+
+    feed.push_str("</feed>\n");
+    utils::fs::write_file(destination, "feed.xml", feed.as_bytes())?;
+    debug!("Creating feed.xml ✓");
+
+    Ok(())
+}
+
+/// The chapters following the part title named `section`, up to the next
+/// part title, or the chapters nested in the chapter named `section`.
+fn section_chapters<'a>(book: &'a Book, section: &str) -> Option<Vec<&'a Chapter>> {
+    let mut chapters = Vec::new();
+
+    let part = book
+        .sections
+        .iter()
+        .position(|item| matches!(item, BookItem::PartTitle(title) if title == section));
+    if let Some(start) = part {
+        let rest = &book.sections[start + 1..];
+        let end = rest
+            .iter()
+            .position(|item| matches!(item, BookItem::PartTitle(_)))
+            .unwrap_or(rest.len());
+        collect_chapters(&rest[..end], &mut chapters);
+        return Some(chapters);
+    }
+
+    for item in book.iter() {
+        if let BookItem::Chapter(ref ch) = *item {
+            if ch.name == section {
+                collect_chapters(&ch.sub_items, &mut chapters);
+                return Some(chapters);
+            }
+        }
+    }
+
+    None
+}
+
+fn collect_chapters<'a>(items: &'a [BookItem], chapters: &mut Vec<&'a Chapter>) {
+    for item in items {
+        if let BookItem::Chapter(ref ch) = *item {
+            chapters.push(ch);
+            collect_chapters(&ch.sub_items, chapters);
+        }
+    }
+}
+
+/// When the chapter was last updated, taken from the `date` in its front
+/// matter or the modification time of its source file.
+fn updated(ch: &Chapter, src_dir: &Path) -> Result<DateTime<Utc>> {
+    if let Some(date) = ch.metadata.get("date") {
+        return match date.as_str().and_then(parse_date) {
+            Some(date) => Ok(date),
+            None => bail!(
+                "The date {} of the chapter \"{}\" isn't formatted like 2021-06-01",
+                date,
+                ch.name
+            ),
+        };
+    }
+
+    let source = ch.source_path.as_ref().map(|source| src_dir.join(source));
+    Ok(source
+        .and_then(|source| modification_time(&source))
+        .unwrap_or_else(Utc::now))
+}
+
+/// Parse a date, or a date and time with an optional time zone.
+fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Some(date.with_timezone(&Utc));
+    }
+    let naive = NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDate::parse_from_str(date, "%Y-%m-%d").map(|d| d.and_hms(0, 0, 0)))
+        .ok()?;
+    Some(Utc.from_utc_datetime(&naive))
+}
+
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_can_have_times_and_time_zones() {
+        let parsed = |date| parse_date(date).map(|date| timestamp(&date));
+
+        assert_eq!(parsed("2021-06-01").unwrap(), "2021-06-01T00:00:00Z");
+        assert_eq!(
+            parsed("2021-06-01T12:30:00").unwrap(),
+            "2021-06-01T12:30:00Z"
+        );
+        assert_eq!(
+            parsed("2021-06-01T12:30:00+02:00").unwrap(),
+            "2021-06-01T10:30:00Z"
+        );
+        assert_eq!(parsed("June 1st"), None);
+    }
+
+    #[test]
+    fn sections_are_parts_or_chapters() {
+        let mut book = Book::new();
+        let mut news = Chapter::new("News", String::new(), "news.md", vec![]);
+        news.sub_items
+            .push(Chapter::new("1.0", String::new(), "news/1.0.md", vec![]).into());
+        book.push_item(news);
+        book.push_item(BookItem::PartTitle("Releases".to_string()));
+        book.push_item(Chapter::new("2.0", String::new(), "2.0.md", vec![]));
+        book.push_item(BookItem::PartTitle("Reference".to_string()));
+        book.push_item(Chapter::new("API", String::new(), "api.md", vec![]));
+
+        let names = |section| {
+            section_chapters(&book, section).map(|chapters| {
+                chapters
+                    .iter()
+                    .map(|ch| ch.name.as_str())
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(names("Releases"), Some(vec!["2.0"]));
+        assert_eq!(names("News"), Some(vec!["1.0"]));
+        assert_eq!(names("Missing"), None);
+    }
+}
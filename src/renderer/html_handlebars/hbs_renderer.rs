@@ -1,19 +1,26 @@
-use crate::book::{Book, BookItem};
-use crate::config::{BookConfig, Config, HtmlConfig, Playground, RustEdition};
+use crate::book::{normalize, Book, BookItem, Chapter};
+#[cfg(feature = "search")]
+use crate::config::Search;
+use crate::config::{BookConfig, Config, Highlight, HtmlConfig, Playground, RustEdition};
 use crate::errors::*;
-use crate::renderer::html_handlebars::helpers;
+use crate::renderer::html_handlebars::incremental::{self, BuildState, PageState};
+#[cfg(feature = "search")]
+use crate::renderer::html_handlebars::search;
+use crate::renderer::html_handlebars::{feed, helpers, highlight, links, sitemap};
 use crate::renderer::{RenderContext, Renderer};
 use crate::theme::{self, playground_editor, Theme};
 use crate::utils;
 
 use std::borrow::Cow;
-use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 
 use crate::utils::fs::get_404_output_file;
 use handlebars::Handlebars;
+use pulldown_cmark::{Event, Tag};
+use rayon::prelude::*;
 use regex::{Captures, Regex};
 
 #[derive(Default)]
@@ -24,50 +31,23 @@ impl HtmlHandlebars {
         HtmlHandlebars
     }
 
+    /// Remove the state kept by incremental builds of the book at `root`.
+    pub fn remove_incremental_state(root: &Path) -> Result<()> {
+        incremental::remove_all(root)
+    }
+
     fn render_item(
         &self,
         item: &BookItem,
-        mut ctx: RenderItemContext<'_>,
-        print_content: &mut String,
-    ) -> Result<()> {
-        // FIXME: This should be made DRY-er and rely less on mutable state
-
+        ctx: &RenderItemContext<'_>,
+        is_index: bool,
+        section: Option<&str>,
+    ) -> Result<Option<RenderedChapter>> {
         let (ch, path) = match item {
             BookItem::Chapter(ch) if !ch.is_draft_chapter() => (ch, ch.path.as_ref().unwrap()),
-            _ => return Ok(()),
+            _ => return Ok(None),
         };
 
-        if let Some(ref edit_url_template) = ctx.html_config.edit_url_template {
-            let full_path = ctx.book_config.src.to_str().unwrap_or_default().to_owned()
-                + "/"
-                + ch.source_path
-                    .clone()
-                    .unwrap_or_default()
-                    .to_str()
-                    .unwrap_or_default();
-
-            let edit_url = edit_url_template.replace("{path}", &full_path);
-            ctx.data
-                .insert("git_repository_edit_url".to_owned(), json!(edit_url));
-        }
-
-        let content = ch.content.clone();
-        let content = utils::render_markdown(&content, ctx.html_config.curly_quotes);
-
-        let fixed_content = utils::render_markdown_with_path(
-            &ch.content,
-            ctx.html_config.curly_quotes,
-            Some(&path),
-        );
-        if !ctx.is_index {
-            // Add page break between chapters
-            // See https://developer.mozilla.org/en-US/docs/Web/CSS/break-before and https://developer.mozilla.org/en-US/docs/Web/CSS/page-break-before
-            // Add both two CSS properties because of the compatibility issue
-            print_content
-                .push_str(r#"<div style="break-before: page; page-break-before: always;"></div>"#);
-        }
-        print_content.push_str(&fixed_content);
-
         // Update the context with data for this file
         let ctx_path = path
             .to_str()
@@ -93,41 +73,178 @@ impl HtmlHandlebars {
             ch.name.clone() + " - " + book_title
         };
 
-        ctx.data.insert("path".to_owned(), json!(path));
-        ctx.data.insert("content".to_owned(), json!(content));
-        ctx.data.insert("chapter_title".to_owned(), json!(ch.name));
-        ctx.data.insert("title".to_owned(), json!(title));
-        ctx.data.insert(
+        let hash = incremental::hash_of(&(
+            &ch.name,
+            &ch.content,
+            serde_json::to_string(&ch.metadata)?,
+            section,
+            path,
+            &ch.source_path,
+            &ch.parent_names,
+            &title,
+            is_index,
+            ch.hidden,
+        ));
+        // Unchanged chapters are skipped before any markdown is rendered
+        let previous_page = ctx
+            .previous_state
+            .and_then(|previous| previous.unchanged_page(ctx_path, hash));
+        if let Some(page) = previous_page {
+            debug!("Skipping unchanged {}", ctx_path);
+            return Ok(Some(RenderedChapter {
+                path: ctx_path.to_owned(),
+                page: page.clone(),
+                files: Vec::new(),
+            }));
+        }
+
+        let mut content = utils::render_markdown_with_options(
+            &ch.content,
+            ctx.html_config.curly_quotes,
+            ctx.html_config.admonitions,
+            None,
+        );
+
+        let fixed_content = utils::render_markdown_with_options(
+            &ch.content,
+            ctx.html_config.curly_quotes,
+            ctx.html_config.admonitions,
+            Some(path),
+        );
+        let mut print_content = String::new();
+        // Hidden chapters are left out of the print page
+        if !ch.hidden {
+            if !is_index {
+                // Add page break between chapters
+                // See https://developer.mozilla.org/en-US/docs/Web/CSS/break-before and https://developer.mozilla.org/en-US/docs/Web/CSS/page-break-before
+                // Add both two CSS properties because of the compatibility issue
+                print_content.push_str(
+                    r#"<div style="break-before: page; page-break-before: always;"></div>"#,
+                );
+            }
+            print_content.push_str(&fixed_content);
+        }
+
+        #[cfg(feature = "search")]
+        let search_documents = if ctx.search.enable {
+            search::documents(ctx.search, ctx.html_config.admonitions, ch)?
+        } else {
+            Vec::new()
+        };
+
+        let mut data = ctx.data.clone();
+        if let Some(ref edit_url_template) = ctx.html_config.edit_url_template {
+            // Untranslated chapters have a source path like `../en/intro.md`
+            let source_path = ch.source_path.clone().unwrap_or_default();
+            let full_path = normalize(&ctx.book_config.src.join(source_path));
+            let full_path = utils::fs::normalize_path(full_path.to_str().unwrap_or_default());
+
+            let edit_url = edit_url_template.replace("{path}", &full_path);
+            data.insert("git_repository_edit_url".to_owned(), json!(edit_url));
+        }
+
+        let page_toc = &ctx.html_config.page_toc;
+        if page_toc.enable {
+            // Link the headings now, so the page's navigation uses the same
+            // IDs as the headings do.
+            let (linked, headings) = collect_header_links(&content);
+            content = linked;
+            let headings: Vec<_> = headings
+                .into_iter()
+                .filter(|heading| heading.level >= 2 && heading.level <= page_toc.depth.into())
+                .collect();
+            data.insert("page_toc".to_owned(), json!(headings));
+        }
+
+        data.insert("path".to_owned(), json!(path));
+        data.insert("content".to_owned(), json!(content));
+        data.insert("chapter_title".to_owned(), json!(ch.name));
+        data.insert(
+            "breadcrumbs".to_owned(),
+            json!(ctx.breadcrumbs.get(path).cloned().unwrap_or_default()),
+        );
+        data.insert("chapter".to_owned(), json!({ "meta": ch.metadata }));
+        let description = match ch.metadata.get("description").and_then(|d| d.as_str()) {
+            Some(description) => Some(description.to_string()),
+            None => first_paragraph(&ch.content),
+        };
+        if let Some(description) = description {
+            data.insert("description".to_owned(), json!(description));
+        }
+        if let Some(base) = absolute_site_url(ctx.book_config, ctx.html_config) {
+            let page = utils::fs::normalize_path(&filepath.to_string_lossy());
+            data.insert(
+                "canonical_url".to_owned(),
+                json!(format!("{}{}", base, page)),
+            );
+        }
+        if let Some(image) = ch.metadata.get("image").and_then(|i| i.as_str()) {
+            if let Some(image) = social_image_url(ctx.book_config, ctx.html_config, image) {
+                data.insert("social_image".to_owned(), json!(image));
+            }
+        }
+        data.insert("title".to_owned(), json!(title));
+        data.insert(
             "path_to_root".to_owned(),
             json!(utils::fs::path_to_root(&path)),
         );
-        if let Some(ref section) = ch.number {
-            ctx.data
-                .insert("section".to_owned(), json!(section.to_string()));
+        if let Some(section) = section {
+            data.insert("section".to_owned(), json!(section));
+        }
+        // Switch to the same chapter in the translations which have it
+        if let Some(languages) = data.get_mut("languages").and_then(|l| l.as_array_mut()) {
+            let page = utils::fs::normalize_path(&filepath.to_string_lossy());
+            for language in languages {
+                let chapters = language["code"]
+                    .as_str()
+                    .and_then(|code| ctx.translations.get(code));
+                if matches!(chapters, Some(chapters) if chapters.contains(path)) {
+                    language["path"] = json!(page);
+                }
+            }
         }
 
         // Render the handlebars template with the data
-        debug!("Render template");
-        let rendered = ctx.handlebars.render("index", &ctx.data)?;
-
-        let rendered = self.post_process(rendered, &ctx.html_config.playground, ctx.edition);
-
-        // Write to file
-        debug!("Creating {}", filepath.display());
-        utils::fs::write_file(&ctx.destination, &filepath, rendered.as_bytes())?;
-
-        if ctx.is_index {
-            ctx.data.insert("path".to_owned(), json!("index.md"));
-            ctx.data.insert("path_to_root".to_owned(), json!(""));
-            ctx.data.insert("is_index".to_owned(), json!("true"));
-            let rendered_index = ctx.handlebars.render("index", &ctx.data)?;
-            let rendered_index =
-                self.post_process(rendered_index, &ctx.html_config.playground, ctx.edition);
-            debug!("Creating index.html from {}", ctx_path);
-            utils::fs::write_file(&ctx.destination, "index.html", rendered_index.as_bytes())?;
+        let template = chapter_template(ch, ctx_path, ctx.html_config);
+        if !ctx.handlebars.has_template(template) {
+            bail!(
+                "The chapter {} uses the template \"{}\", but there is no {}.hbs in the theme directory",
+                ctx_path,
+                template,
+                template
+            );
         }
+        debug!("Render template {}", template);
+        let rendered = ctx.handlebars.render(template, &data)?;
 
-        Ok(())
+        let rendered = self.post_process(rendered, ctx.html_config, ctx.edition);
+        let ids = if ctx.html_config.check_links {
+            links::element_ids(&rendered)
+        } else {
+            BTreeSet::new()
+        };
+        let mut files = vec![(filepath, rendered)];
+
+        if is_index {
+            data.insert("path".to_owned(), json!("index.md"));
+            data.insert("path_to_root".to_owned(), json!(""));
+            data.insert("is_index".to_owned(), json!("true"));
+            let rendered_index = ctx.handlebars.render(template, &data)?;
+            let rendered_index = self.post_process(rendered_index, ctx.html_config, ctx.edition);
+            files.push((PathBuf::from("index.html"), rendered_index));
+        }
+
+        Ok(Some(RenderedChapter {
+            path: ctx_path.to_owned(),
+            page: PageState {
+                hash,
+                print_content,
+                ids,
+                #[cfg(feature = "search")]
+                search_documents,
+            },
+            files,
+        }))
     }
 
     fn render_404(
@@ -156,7 +273,12 @@ impl HtmlHandlebars {
                     .to_string()
             }
         };
-        let html_content_404 = utils::render_markdown(&content_404, html_config.curly_quotes);
+        let html_content_404 = utils::render_markdown_with_options(
+            &content_404,
+            html_config.curly_quotes,
+            html_config.admonitions,
+            None,
+        );
 
         let mut data_404 = data.clone();
         let base_url = if let Some(site_url) = &html_config.site_url {
@@ -169,14 +291,19 @@ impl HtmlHandlebars {
             );
             "/"
         };
+        let base_url = if ctx.config.book.multilingual {
+            let language = ctx.config.book.default_language();
+            format!("{}/{}/", base_url.trim_end_matches('/'), language)
+        } else {
+            base_url.to_string()
+        };
         data_404.insert("base_url".to_owned(), json!(base_url));
         // Set a dummy path to ensure other paths (e.g. in the TOC) are generated correctly
         data_404.insert("path".to_owned(), json!("404.md"));
         data_404.insert("content".to_owned(), json!(html_content_404));
         let rendered = handlebars.render("index", &data_404)?;
 
-        let rendered =
-            self.post_process(rendered, &html_config.playground, ctx.config.rust.edition);
+        let rendered = self.post_process(rendered, html_config, ctx.config.rust.edition);
         let output_file = get_404_output_file(&html_config.input_404);
         utils::fs::write_file(&destination, output_file, rendered.as_bytes())?;
         debug!("Creating 404.html ✓");
@@ -187,12 +314,16 @@ impl HtmlHandlebars {
     fn post_process(
         &self,
         rendered: String,
-        playground_config: &Playground,
+        html_config: &HtmlConfig,
         edition: Option<RustEdition>,
     ) -> String {
         let rendered = build_header_links(&rendered);
         let rendered = fix_code_blocks(&rendered);
-        let rendered = add_playground_pre(&rendered, playground_config, edition);
+        let rendered = add_playground_pre(&rendered, &html_config.playground, edition);
+        let rendered = match html_config.highlight {
+            Highlight::Server => highlight::highlight_code_blocks(&rendered),
+            Highlight::Client => rendered,
+        };
 
         rendered
     }
@@ -336,6 +467,52 @@ impl HtmlHandlebars {
         handlebars.register_helper("theme_option", Box::new(helpers::theme::theme_option));
     }
 
+    /// Register the partials and script helpers the book has been configured
+    /// to load from its theme directory, returning their sources.
+    fn register_theme_extensions(
+        &self,
+        handlebars: &mut Handlebars<'_>,
+        html_config: &HtmlConfig,
+        theme_dir: &Path,
+    ) -> Result<BTreeMap<String, String>> {
+        let mut sources = BTreeMap::new();
+
+        for (name, path) in &html_config.partials {
+            let path = theme_dir.join(path);
+            let partial = fs::read_to_string(&path).with_context(|| {
+                format!(
+                    "Unable to read the {} partial from {}",
+                    name,
+                    path.display()
+                )
+            })?;
+            debug!("Register the {} handlebars partial", name);
+            handlebars
+                .register_partial(name, &partial)
+                .with_context(|| format!("Invalid template for the {} partial", name))?;
+            sources.insert(format!("partial {}", name), partial);
+        }
+
+        #[cfg(feature = "script-helpers")]
+        for (name, path) in &html_config.helpers {
+            let path = theme_dir.join(path);
+            let script = fs::read_to_string(&path).with_context(|| {
+                format!("Unable to read the {} helper from {}", name, path.display())
+            })?;
+            debug!("Register the {} handlebars helper", name);
+            handlebars
+                .register_script_helper(name, &script)
+                .with_context(|| format!("Invalid script for the {} helper", name))?;
+            sources.insert(format!("helper {}", name), script);
+        }
+        #[cfg(not(feature = "script-helpers"))]
+        if !html_config.helpers.is_empty() {
+            bail!("mdBook must be compiled with the `script-helpers` feature to use `output.html.helpers`");
+        }
+
+        Ok(sources)
+    }
+
     /// Copy across any additional CSS and JavaScript files which the book
     /// has been configured to use.
     fn copy_additional_css_and_js(
@@ -468,11 +645,6 @@ impl Renderer for HtmlHandlebars {
         let book = &ctx.book;
         let build_dir = ctx.root.join(&ctx.config.build.build_dir);
 
-        if destination.exists() {
-            utils::fs::remove_dir_content(destination)
-                .with_context(|| "Unable to remove stale HTML output")?;
-        }
-
         trace!("render");
         let mut handlebars = Handlebars::new();
 
@@ -491,11 +663,17 @@ impl Renderer for HtmlHandlebars {
             warn!("Please move your theme files to `./theme` for them to continue being used");
         }
 
-        let theme = theme::Theme::new(theme_dir);
+        let mut theme = theme::Theme::new(&theme_dir);
+        theme.load_templates(&theme_dir, template_names(&ctx.book, &html_config));
 
         debug!("Register the index handlebars template");
         handlebars.register_template_string("index", String::from_utf8(theme.index.clone())?)?;
 
+        for (name, template) in &theme.templates {
+            debug!("Register the {} handlebars template", name);
+            handlebars.register_template_string(name, String::from_utf8(template.clone())?)?;
+        }
+
         debug!("Register the head handlebars template");
         handlebars.register_partial("head", String::from_utf8(theme.head.clone())?)?;
 
@@ -508,31 +686,116 @@ impl Renderer for HtmlHandlebars {
 
         debug!("Register handlebars helpers");
         self.register_hbs_helpers(&mut handlebars, &html_config);
+        let theme_extensions =
+            self.register_theme_extensions(&mut handlebars, &html_config, &theme_dir)?;
 
         let mut data = make_data(&ctx.root, &book, &ctx.config, &html_config, &theme)?;
 
+        // Anything which affects every page invalidates the whole previous build.
+        let global_hash = incremental::hash_of(&(
+            serde_json::to_string(&data)?,
+            serde_json::to_value(&html_config)?.to_string(),
+            // The edition picks the playground's edition for every code block
+            serde_json::to_value(&ctx.config.rust)?.to_string(),
+            &theme.index,
+            &theme.head,
+            &theme.header,
+            &theme.redirect,
+            &theme.templates,
+            &theme_extensions,
+            &ctx.translations,
+        ));
+        let previous_state = if html_config.incremental {
+            BuildState::load(&ctx.root, destination).filter(|state| state.global == global_hash)
+        } else {
+            BuildState::remove(&ctx.root, destination)?;
+            None
+        };
+        let mut state = BuildState::new(global_hash);
+
+        if destination.exists() && previous_state.is_none() {
+            utils::fs::remove_dir_content(destination)
+                .with_context(|| "Unable to remove stale HTML output")?;
+        }
+
         // Print version
         let mut print_content = String::new();
 
         fs::create_dir_all(&destination)
             .with_context(|| "Unexpected error when constructing destination path")?;
 
-        let mut is_index = true;
-        for item in book.iter() {
-            let ctx = RenderItemContext {
-                handlebars: &handlebars,
-                destination: destination.to_path_buf(),
-                data: data.clone(),
-                is_index,
-                book_config: book_config.clone(),
-                html_config: html_config.clone(),
-                edition: ctx.config.rust.edition,
-                chapter_titles: &ctx.chapter_titles,
-            };
-            self.render_item(item, ctx, &mut print_content)?;
-            is_index = false;
+        #[cfg(feature = "search")]
+        let search = html_config.search.clone().unwrap_or_default();
+
+        // Chapters are rendered in parallel, then the print page and search
+        // index are put back together in the order they appear in the book.
+        let section_labels = book.section_labels(&book_config.numbering);
+        let render_ctx = RenderItemContext {
+            handlebars: &handlebars,
+            data: &data,
+            book_config,
+            html_config: &html_config,
+            edition: ctx.config.rust.edition,
+            chapter_titles: &ctx.chapter_titles,
+            breadcrumbs: &breadcrumbs(
+                &book.sections,
+                &section_labels,
+                !html_config.no_section_label,
+            ),
+            #[cfg(feature = "search")]
+            search: &search,
+            translations: &ctx.translations,
+            previous_state: previous_state.as_ref(),
+        };
+        let items: Vec<&BookItem> = book.iter().collect();
+        let rendered_chapters = items
+            .par_iter()
+            .zip(&section_labels)
+            .enumerate()
+            .map(|(i, (item, section))| {
+                self.render_item(item, &render_ctx, i == 0, section.as_deref())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let rendered_chapters: Vec<_> = rendered_chapters.into_iter().flatten().collect();
+
+        // Links are checked against the rendered pages, before any of them
+        // are written.
+        if html_config.check_links {
+            debug!("Check internal links");
+            let mut pages = HashMap::new();
+            for chapter in &rendered_chapters {
+                if pages.is_empty() {
+                    // The first chapter is also rendered as the book's index page.
+                    pages.insert(PathBuf::from("index.html"), &chapter.page.ids);
+                }
+                let path = Path::new(&chapter.path).with_extension("html");
+                pages.insert(path, &chapter.page.ids);
+            }
+            links::check_links(book, &src_dir, &pages)?;
         }
 
+        rendered_chapters
+            .par_iter()
+            .flat_map(|chapter| &chapter.files)
+            .try_for_each(|(path, rendered)| {
+                debug!("Creating {}", path.display());
+                utils::fs::write_file(destination, path, rendered.as_bytes())
+            })?;
+
+        let mut paths = Vec::new();
+        for chapter in rendered_chapters {
+            print_content.push_str(&chapter.page.print_content);
+            paths.push(chapter.path.clone());
+            state.pages.insert(chapter.path, chapter.page);
+        }
+
+        // The print page and search index cover every chapter, so they only
+        // need rebuilding if at least one of them changed.
+        let chapters_changed = match previous_state {
+            Some(ref previous) => !previous.has_same_pages(&state),
+            None => true,
+        };
+
         // Render 404 page
         if html_config.input_404 != Some("".to_string()) {
             self.render_404(ctx, &html_config, &src_dir, &mut handlebars, &mut data)?;
@@ -545,12 +808,11 @@ impl Renderer for HtmlHandlebars {
         }
 
         // Render the handlebars template with the data
-        if html_config.print.enable {
+        if html_config.print.enable && chapters_changed {
             debug!("Render template");
             let rendered = handlebars.render("index", &data)?;
 
-            let rendered =
-                self.post_process(rendered, &html_config.playground, ctx.config.rust.edition);
+            let rendered = self.post_process(rendered, &html_config, ctx.config.rust.edition);
 
             utils::fs::write_file(&destination, "print.html", rendered.as_bytes())?;
             debug!("Creating print.html ✓");
@@ -562,21 +824,46 @@ impl Renderer for HtmlHandlebars {
         self.copy_additional_css_and_js(&html_config, &ctx.root, &destination)
             .with_context(|| "Unable to copy across additional CSS and JS")?;
 
+        if html_config.sitemap.enable {
+            sitemap::create_files(&ctx.config.book, &html_config, &src_dir, destination, book)
+                .context("Unable to create sitemap.xml")?;
+        }
+        if html_config.feed.enable {
+            feed::create_files(&ctx.config, &html_config, &src_dir, destination, book)
+                .context("Unable to create feed.xml")?;
+        }
+
         // Render search index
         #[cfg(feature = "search")]
         {
-            let search = html_config.search.unwrap_or_default();
-            if search.enable {
-                super::search::create_files(&search, &destination, &book)?;
+            if search.enable && chapters_changed {
+                let documents = paths
+                    .iter()
+                    .flat_map(|path| &state.pages[path].search_documents);
+                search::create_files(&search, destination, documents)?;
             }
         }
 
         self.emit_redirects(&ctx.destination, &handlebars, &html_config.redirect)
             .context("Unable to emit redirects")?;
 
+        // The root of a multilingual book sends readers to the default language
+        if let Some(ref default_language) = ctx.default_language {
+            if book_config.language.as_ref() == Some(default_language) {
+                let root = destination.parent().unwrap_or(destination);
+                let url = format!("{}/index.html", default_language);
+                let rendered = handlebars.render("redirect", &json!({ "url": url }))?;
+                utils::fs::write_file(root, "index.html", rendered.as_bytes())?;
+            }
+        }
+
         // Copy all remaining files, avoid a recursive copy from/to the book build dir
         utils::fs::copy_files_except_ext(&src_dir, &destination, true, Some(&build_dir), &["md"])?;
 
+        if html_config.incremental {
+            state.save(&ctx.root, destination)?;
+        }
+
         Ok(())
     }
 }
@@ -609,10 +896,41 @@ fn make_data(
     if theme.favicon_svg.is_some() {
         data.insert("favicon_svg".to_owned(), json!("favicon.svg"));
     }
+    if let Some(image) = html_config
+        .social_image
+        .as_ref()
+        .and_then(|image| social_image_url(&config.book, html_config, image))
+    {
+        data.insert("social_image".to_owned(), json!(image));
+    }
+    // Without an absolute site-url there's no feed to link to
+    if html_config.feed.enable && absolute_site_url(&config.book, html_config).is_some() {
+        data.insert(
+            "feed_title".to_owned(),
+            json!(feed::title(config, &html_config.feed)),
+        );
+    }
     if let Some(ref livereload) = html_config.livereload_url {
         data.insert("livereload".to_owned(), json!(livereload));
     }
 
+    if config.book.multilingual {
+        let current_language = config.book.default_language();
+        let languages: Vec<_> = config
+            .language
+            .iter()
+            .map(|(code, language)| {
+                json!({
+                    "code": code,
+                    "name": language.name.as_deref().unwrap_or(code),
+                    "active": code == current_language,
+                    "path": "index.html",
+                })
+            })
+            .collect();
+        data.insert("languages".to_owned(), json!(languages));
+    }
+
     let default_theme = match html_config.default_theme {
         Some(ref theme) => theme.to_lowercase(),
         None => "light".to_string(),
@@ -637,6 +955,14 @@ fn make_data(
         data.insert("mathjax_support".to_owned(), json!(true));
     }
 
+    if html_config.highlight == Highlight::Server {
+        data.insert("server_highlighting".to_owned(), json!(true));
+    }
+
+    if html_config.breadcrumbs {
+        data.insert("show_breadcrumbs".to_owned(), json!(true));
+    }
+
     if html_config.copy_fonts {
         data.insert("copy_fonts".to_owned(), json!(true));
     }
@@ -706,8 +1032,11 @@ fn make_data(
     data.insert("git_repository_icon".to_owned(), json!(git_repository_icon));
 
     let mut chapters = vec![];
+    let mut levels = Vec::new();
+    toc_levels(&book.sections, 1, &mut levels);
+    let labels = book.section_labels(&config.book.numbering);
 
-    for item in book.iter() {
+    for ((item, level), label) in book.iter().zip(levels).zip(labels) {
         // Create the data to inject in the template
         let mut chapter = BTreeMap::new();
 
@@ -715,9 +1044,12 @@ fn make_data(
             BookItem::PartTitle(ref title) => {
                 chapter.insert("part".to_owned(), json!(title));
             }
+            // Hidden chapters are left out of the table of contents and the
+            // navigation between chapters.
+            BookItem::Chapter(ref ch) if ch.hidden => continue,
             BookItem::Chapter(ref ch) => {
-                if let Some(ref section) = ch.number {
-                    chapter.insert("section".to_owned(), json!(section.to_string()));
+                if let Some(section) = label {
+                    chapter.insert("section".to_owned(), json!(section));
                 }
 
                 chapter.insert(
@@ -736,6 +1068,13 @@ fn make_data(
             BookItem::Separator => {
                 chapter.insert("spacer".to_owned(), json!("_spacer_"));
             }
+            BookItem::ExternalLink(ref link) => {
+                chapter.insert("name".to_owned(), json!(link.name));
+                chapter.insert("url".to_owned(), json!(link.url));
+                // External links don't have a section number to tell how
+                // deeply they're nested.
+                chapter.insert("level".to_owned(), json!(level.to_string()));
+            }
         }
 
         chapters.push(chapter);
@@ -747,30 +1086,69 @@ fn make_data(
     Ok(data)
 }
 
+/// The nesting level of each item in the table of contents, in the same order
+/// as `Book::iter()`.
+fn toc_levels(items: &[BookItem], level: usize, levels: &mut Vec<usize>) {
+    for item in items {
+        levels.push(level);
+        if let BookItem::Chapter(ref ch) = *item {
+            toc_levels(&ch.sub_items, level + 1, levels);
+        }
+    }
+}
+
 /// Goes through the rendered HTML, making sure all header tags have
 /// an anchor respectively so people can link to sections directly.
-fn build_header_links(html: &str) -> String {
+pub(crate) fn build_header_links(html: &str) -> String {
+    collect_header_links(html).0
+}
+
+/// A heading linked by `build_header_links`.
+#[derive(Debug, PartialEq, Serialize)]
+struct Heading {
+    level: usize,
+    id: String,
+    /// The heading's content, with any HTML tags removed.
+    text: String,
+}
+
+/// Like `build_header_links`, but also returns the headings which were found.
+fn collect_header_links(html: &str) -> (String, Vec<Heading>) {
+    lazy_static! {
+        static ref TAG: Regex = Regex::new(r"<[^>]*>").unwrap();
+    }
+
     let regex = Regex::new(r"<h(\d)>(.*?)</h\d>").unwrap();
     let mut id_counter = HashMap::new();
+    let mut headings = Vec::new();
 
-    regex
+    let linked = regex
         .replace_all(html, |caps: &Captures<'_>| {
             let level = caps[1]
                 .parse()
                 .expect("Regex should ensure we only ever get numbers here");
 
-            insert_link_into_header(level, &caps[2], &mut id_counter)
+            let (header, id) = insert_link_into_header(level, &caps[2], &mut id_counter);
+            headings.push(Heading {
+                level,
+                id,
+                text: TAG.replace_all(&caps[2], "").trim().to_string(),
+            });
+            header
         })
-        .into_owned()
+        .into_owned();
+
+    (linked, headings)
 }
 
 /// Insert a sinle link into a header, making sure each link gets its own
-/// unique ID by appending an auto-incremented number (if necessary).
+/// unique ID by appending an auto-incremented number (if necessary). Returns
+/// the header along with its ID.
 fn insert_link_into_header(
     level: usize,
     content: &str,
     id_counter: &mut HashMap<String, usize>,
-) -> String {
+) -> (String, String) {
     let raw_id = utils::id_from_content(content);
 
     let id_count = id_counter.entry(raw_id.clone()).or_insert(0);
@@ -782,12 +1160,13 @@ fn insert_link_into_header(
 
     *id_count += 1;
 
-    format!(
+    let header = format!(
         r##"<h{level} id="{id}"><a class="header" href="#{id}">{text}</a></h{level}>"##,
         level = level,
         id = id,
         text = content
-    )
+    );
+    (header, id)
 }
 
 // The rust book uses annotations for rustdoc to test code snippets,
@@ -938,15 +1317,200 @@ fn partition_source(s: &str) -> (String, String) {
     (before, after)
 }
 
+/// The URL of the book's root, ending with a slash, if `site-url` is an
+/// absolute URL rather than just a path. Each translation of a multilingual
+/// book has its root in its own `<language>/` subdirectory.
+pub(super) fn absolute_site_url(
+    book_config: &BookConfig,
+    html_config: &HtmlConfig,
+) -> Option<String> {
+    let url = match html_config.site_url {
+        Some(ref url) if url.starts_with("http://") || url.starts_with("https://") => {
+            url.trim_end_matches('/')
+        }
+        _ => return None,
+    };
+    if book_config.multilingual {
+        Some(format!("{}/{}/", url, book_config.default_language()))
+    } else {
+        Some(format!("{}/", url))
+    }
+}
+
+/// The absolute URL of the image shown when a page is shared. Images which
+/// are relative to the book's root need `site-url` to be an absolute URL.
+fn social_image_url(
+    book_config: &BookConfig,
+    html_config: &HtmlConfig,
+    image: &str,
+) -> Option<String> {
+    if image.contains("://") {
+        return Some(image.to_string());
+    }
+    absolute_site_url(book_config, html_config).map(|base| base + image.trim_start_matches('/'))
+}
+
+/// The text of a chapter's first paragraph, shortened to a length suitable
+/// for the page's description.
+fn first_paragraph(content: &str) -> Option<String> {
+    const MAX_LENGTH: usize = 200;
+
+    let mut text = String::new();
+    let mut in_paragraph = false;
+    for event in utils::new_cmark_parser(content) {
+        match event {
+            Event::Start(Tag::Paragraph) => in_paragraph = true,
+            Event::End(Tag::Paragraph) if text.trim().is_empty() => in_paragraph = false,
+            Event::End(Tag::Paragraph) => break,
+            Event::Text(t) | Event::Code(t) if in_paragraph => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak if in_paragraph => text.push(' '),
+            _ => {}
+        }
+    }
+
+    let words: Vec<_> = text.split_whitespace().collect();
+    if words.is_empty() {
+        return None;
+    }
+    let mut description = String::new();
+    for word in &words {
+        let length = description.chars().count() + word.chars().count() + 1;
+        if length > MAX_LENGTH {
+            description.push('…');
+            break;
+        }
+        if !description.is_empty() {
+            description.push(' ');
+        }
+        description.push_str(word);
+    }
+    Some(description)
+}
+
+/// The chapters containing each chapter, outermost first, as a `name`, the
+/// `path` of their page (which is null for draft chapters) and their
+/// `section` label if `numbered` is set. The `labels` are the book's
+/// `section_labels()`.
+fn breadcrumbs(
+    items: &[BookItem],
+    labels: &[Option<String>],
+    numbered: bool,
+) -> HashMap<PathBuf, Vec<serde_json::Value>> {
+    fn walk<'a>(
+        items: &[BookItem],
+        labels: &mut impl Iterator<Item = &'a Option<String>>,
+        numbered: bool,
+        ancestors: &mut Vec<serde_json::Value>,
+        breadcrumbs: &mut HashMap<PathBuf, Vec<serde_json::Value>>,
+    ) {
+        for item in items {
+            // Every item has a label, in the same order as they're walked
+            let label = labels.next().and_then(Option::as_ref);
+            if let BookItem::Chapter(ch) = item {
+                let page = ch.path.as_ref().and_then(|path| {
+                    breadcrumbs.insert(path.clone(), ancestors.clone());
+                    let page = path.with_extension("html");
+                    page.to_str().map(utils::fs::normalize_path)
+                });
+                let section = label.filter(|_| numbered);
+                ancestors.push(json!({ "name": ch.name, "path": page, "section": section }));
+                walk(&ch.sub_items, labels, numbered, ancestors, breadcrumbs);
+                ancestors.pop();
+            }
+        }
+    }
+
+    let mut breadcrumbs = HashMap::new();
+    walk(
+        items,
+        &mut labels.iter(),
+        numbered,
+        &mut Vec::new(),
+        &mut breadcrumbs,
+    );
+    breadcrumbs
+}
+
+/// The name of the template a chapter is rendered with: the one named by the
+/// `template` key of its front matter, or else the one for the longest
+/// pattern in `output.html.templates` matching its path, or else `index`.
+fn chapter_template<'a>(ch: &'a Chapter, path: &str, html_config: &'a HtmlConfig) -> &'a str {
+    // Names which aren't page templates were warned about when loading them
+    let template = ch.metadata.get("template").and_then(|t| t.as_str());
+    if let Some(template) = template.filter(|t| theme::is_page_template(t)) {
+        return template;
+    }
+
+    let path = utils::fs::normalize_path(path);
+    html_config
+        .templates
+        .iter()
+        .filter(|(_, template)| theme::is_page_template(template))
+        .filter(|(pattern, _)| matches_pattern(pattern.as_bytes(), path.as_bytes()))
+        .max_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then_with(|| b.cmp(a)))
+        .map(|(_, template)| template.as_str())
+        .unwrap_or("index")
+}
+
+/// The names of the templates chapters are rendered with other than `index`,
+/// from `output.html.templates` and the chapters' front matter.
+fn template_names<'a>(book: &'a Book, html_config: &'a HtmlConfig) -> BTreeSet<&'a str> {
+    let front_matter = book.iter().filter_map(|item| match *item {
+        BookItem::Chapter(ref ch) => ch.metadata.get("template").and_then(|t| t.as_str()),
+        _ => None,
+    });
+    html_config
+        .templates
+        .values()
+        .map(String::as_str)
+        .chain(front_matter)
+        .collect()
+}
+
+/// Match a path against a pattern, where `*` matches anything except `/`
+/// and `**` matches anything, including any number of directories.
+fn matches_pattern(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            // `**/` also matches no directories at all
+            matches_pattern(rest, path)
+                || (0..path.len())
+                    .filter(|&i| path[i] == b'/')
+                    .any(|i| matches_pattern(rest, &path[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| matches_pattern(rest, &path[i..])),
+        [b'*', rest @ ..] => {
+            let segment_end = path.iter().position(|&c| c == b'/').unwrap_or(path.len());
+            (0..=segment_end).any(|i| matches_pattern(rest, &path[i..]))
+        }
+        [c, rest @ ..] => path.first() == Some(c) && matches_pattern(rest, &path[1..]),
+    }
+}
+
 struct RenderItemContext<'a> {
     handlebars: &'a Handlebars<'a>,
-    destination: PathBuf,
-    data: serde_json::Map<String, serde_json::Value>,
-    is_index: bool,
-    book_config: BookConfig,
-    html_config: HtmlConfig,
+    data: &'a serde_json::Map<String, serde_json::Value>,
+    book_config: &'a BookConfig,
+    html_config: &'a HtmlConfig,
     edition: Option<RustEdition>,
     chapter_titles: &'a HashMap<PathBuf, String>,
+    breadcrumbs: &'a HashMap<PathBuf, Vec<serde_json::Value>>,
+    #[cfg(feature = "search")]
+    search: &'a Search,
+    translations: &'a BTreeMap<String, BTreeSet<PathBuf>>,
+    previous_state: Option<&'a BuildState>,
+}
+
+/// What rendering a single chapter produced, besides its page.
+struct RenderedChapter {
+    /// The chapter's path, which keys its entry in the build state.
+    path: String,
+    /// What the page was rendered from and what it adds to the whole book.
+    page: PageState,
+    /// The files to write for the chapter, relative to the destination. An
+    /// unchanged chapter doesn't have any.
+    files: Vec<(PathBuf, String)>,
 }
 
 #[cfg(test)]
@@ -1090,4 +1654,80 @@ mod tests {
             assert_eq!(&*got, *should_be);
         }
     }
+
+    #[test]
+    fn patterns_match_paths() {
+        let matches =
+            |pattern: &str, path: &str| matches_pattern(pattern.as_bytes(), path.as_bytes());
+
+        assert!(matches("index.md", "index.md"));
+        assert!(!matches("index.md", "guide/index.md"));
+        assert!(matches("reference/*.md", "reference/api.md"));
+        assert!(!matches("reference/*.md", "reference/types/api.md"));
+        assert!(matches("reference/**", "reference/types/api.md"));
+        assert!(matches("**/index.md", "index.md"));
+        assert!(matches("**/index.md", "guide/nested/index.md"));
+        assert!(!matches("**/index.md", "guide/not_index.md"));
+    }
+
+    #[test]
+    fn chapters_choose_their_template() {
+        let mut html_config = HtmlConfig::default();
+        html_config
+            .templates
+            .insert(String::from("reference/**"), String::from("reference"));
+        html_config
+            .templates
+            .insert(String::from("reference/intro.md"), String::from("landing"));
+
+        let mut ch = Chapter::new("Api", String::new(), "reference/api.md", Vec::new());
+        assert_eq!(
+            chapter_template(&ch, "reference/api.md", &html_config),
+            "reference"
+        );
+        assert_eq!(
+            chapter_template(&ch, "reference/intro.md", &html_config),
+            "landing"
+        );
+        assert_eq!(chapter_template(&ch, "guide.md", &html_config), "index");
+
+        ch.metadata
+            .insert(String::from("template"), json!("custom"));
+        assert_eq!(
+            chapter_template(&ch, "reference/api.md", &html_config),
+            "custom"
+        );
+
+        // The theme's own templates and files outside the theme directory
+        // can't be chosen
+        for template in &["redirect", "../../whatever"] {
+            ch.metadata
+                .insert(String::from("template"), json!(template));
+            assert_eq!(
+                chapter_template(&ch, "reference/api.md", &html_config),
+                "reference"
+            );
+        }
+        html_config
+            .templates
+            .insert(String::from("guide.md"), String::from("head"));
+        assert_eq!(chapter_template(&ch, "guide.md", &html_config), "index");
+    }
+
+    #[test]
+    fn descriptions_come_from_the_first_paragraph() {
+        let content =
+            "# Title\n\n![](image.png)\n\nThe *first*\nreal `paragraph`.\n\nNot this one.\n";
+        assert_eq!(
+            first_paragraph(content).unwrap(),
+            "The first real paragraph."
+        );
+
+        let long = "word ".repeat(100);
+        let description = first_paragraph(&long).unwrap();
+        assert!(description.ends_with("word…"));
+        assert!(description.chars().count() <= 200);
+
+        assert_eq!(first_paragraph("# Only a heading\n"), None);
+    }
 }
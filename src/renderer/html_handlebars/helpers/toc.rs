@@ -68,6 +68,8 @@ impl HelperDef for RenderToc {
 
             let (section, level) = if let Some(s) = item.get("section") {
                 (s.as_str(), s.matches('.').count())
+            } else if let Some(level) = item.get("level") {
+                ("", level.parse().unwrap_or(1))
             } else {
                 ("", 1)
             };
@@ -108,6 +110,20 @@ impl HelperDef for RenderToc {
                 continue;
             }
 
+            // External link
+            if let Some(url) = item.get("url") {
+                out.write("<a href=\"")?;
+                out.write(&handlebars::html_escape(url))?;
+                out.write("\" class=\"external-link\">")?;
+                write_escaped(
+                    out,
+                    item.get("name").map(String::as_str).unwrap_or_default(),
+                )?;
+                out.write(" <i class=\"fa fa-external-link\" aria-hidden=\"true\"></i>")?;
+                out.write("</a></li>")?;
+                continue;
+            }
+
             // Link
             let path_exists = if let Some(path) =
                 item.get("path")
@@ -0,0 +1,262 @@
+//! Syntax highlighting of code blocks at build time, used when
+//! `output.html.highlight` is set to `"server"`.
+//!
+//! Code is tokenized with the grammars bundled with `syntect`, and each token
+//! is wrapped in a `<span>` with the same `hljs-*` class highlight.js would
+//! have used, so the existing highlighting themes keep working.
+
+use crate::utils;
+
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::ops::Range;
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet};
+
+lazy_static! {
+    static ref SYNTAXES: SyntaxSet = SyntaxSet::load_defaults_newlines();
+
+    /// Maps TextMate scopes to highlight.js classes. The first scope which
+    /// is a prefix of a token's innermost matching scope wins, so more
+    /// specific scopes come first.
+    static ref CLASSES: Vec<(Scope, &'static str)> = [
+        ("comment", "comment"),
+        ("string.regexp", "regexp"),
+        ("string", "string"),
+        ("constant.numeric", "number"),
+        ("constant.character.escape", "string"),
+        ("constant", "literal"),
+        ("keyword.operator", ""),
+        ("keyword", "keyword"),
+        ("storage", "keyword"),
+        ("variable.language", "keyword"),
+        ("variable.parameter", "params"),
+        ("entity.name.tag", "name"),
+        ("entity.other.attribute-name", "attribute"),
+        ("entity.name", "title"),
+        ("support.function", "built_in"),
+        ("support", "type"),
+        ("meta.annotation", "meta"),
+        ("meta.preprocessor", "meta"),
+        ("markup.heading", "section"),
+        ("markup.bold", "strong"),
+        ("markup.italic", "emphasis"),
+        ("markup.inserted", "addition"),
+        ("markup.deleted", "deletion"),
+        ("markup.quote", "quote"),
+        ("markup.underline.link", "link"),
+        ("markup.list", "bullet"),
+    ]
+    .iter()
+    .map(|(scope, class)| (Scope::new(scope).unwrap(), *class))
+    .collect();
+}
+
+const BORING_START: &str = "<span class=\"boring\">";
+const BORING_END: &str = "</span>";
+
+/// Highlight the contents of every `<code class="language-*">` element in
+/// `html` whose language is known.
+///
+/// Every one of those elements gets the `hljs` class, even when it can't be
+/// highlighted, so it's styled like the other code blocks without
+/// highlight.js.
+pub(super) fn highlight_code_blocks(html: &str) -> String {
+    lazy_static! {
+        static ref CODE: Regex =
+            Regex::new(r#"(?s)<code([^>]*) class="([^"]+)"([^>]*)>(.*?)</code>"#).unwrap();
+    }
+
+    CODE.replace_all(html, |caps: &Captures<'_>| {
+        let classes = &caps[2];
+        let language = match classes
+            .split_whitespace()
+            .find_map(|class| class.strip_prefix("language-"))
+        {
+            Some(language) => language,
+            None => return caps[0].to_string(),
+        };
+
+        let code = match SYNTAXES.find_syntax_by_token(language) {
+            // The playground's editor does its own highlighting.
+            Some(syntax) if !classes.split_whitespace().any(|class| class == "editable") => {
+                Cow::Owned(highlight(&caps[4], syntax))
+            }
+            _ => Cow::Borrowed(&caps[4]),
+        };
+        let hljs = if classes.split_whitespace().any(|class| class == "hljs") {
+            ""
+        } else {
+            " hljs"
+        };
+
+        format!(
+            r#"<code{} class="{}{}"{}>{}</code>"#,
+            &caps[1], classes, hljs, &caps[3], code
+        )
+    })
+    .into_owned()
+}
+
+/// Highlight the (HTML escaped) contents of a code block.
+///
+/// The only markup in the code is the `<span class="boring">` which wraps
+/// whole lines hidden from Rust examples, so it is highlighted line by line,
+/// copying those tags across as they are.
+fn highlight(code: &str, syntax: &SyntaxReference) -> String {
+    let mut state = ParseState::new(syntax);
+    let mut stack = ScopeStack::new();
+    let mut highlighted = String::with_capacity(code.len() * 2);
+    let mut rest = code;
+
+    while !rest.is_empty() {
+        let end = rest.find('\n').map(|i| i + 1).unwrap_or_else(|| rest.len());
+        let mut line = &rest[..end];
+        rest = &rest[end..];
+
+        for tag in &[BORING_END, BORING_START] {
+            if line.starts_with(tag) {
+                highlighted.push_str(tag);
+                line = &line[tag.len()..];
+            }
+        }
+
+        let text = unescape(line);
+        let mut parsed_line = text.clone();
+        if !parsed_line.ends_with('\n') {
+            parsed_line.push('\n');
+        }
+        let ops = match state.parse_line(&parsed_line, &SYNTAXES) {
+            Ok(ops) => ops,
+            Err(e) => {
+                debug!("Unable to highlight a code block: {}", e);
+                return code.to_string();
+            }
+        };
+
+        let mut tokens = Vec::new();
+        let mut start = 0;
+        for (offset, op) in ops {
+            let offset = offset.min(text.len());
+            push_token(&mut tokens, &text, start..offset, &stack);
+            start = offset;
+            if stack.apply(&op).is_err() {
+                return code.to_string();
+            }
+        }
+        push_token(&mut tokens, &text, start..text.len(), &stack);
+
+        for (class, range) in tokens {
+            match class {
+                Some(class) => highlighted.push_str(&format!(
+                    r#"<span class="hljs-{}">{}</span>"#,
+                    class,
+                    utils::escape_xml(&text[range])
+                )),
+                None => highlighted.push_str(&utils::escape_xml(&text[range])),
+            }
+        }
+    }
+
+    highlighted
+}
+
+/// Add the token at `range` of the line to `tokens`, merging it into the
+/// previous token if they'd get the same class.
+fn push_token(
+    tokens: &mut Vec<(Option<&'static str>, Range<usize>)>,
+    text: &str,
+    range: Range<usize>,
+    stack: &ScopeStack,
+) {
+    if range.start == range.end {
+        return;
+    }
+
+    let class = if text[range.clone()].trim().is_empty() {
+        None
+    } else {
+        class_for(stack)
+    };
+    if let Some((previous_class, previous)) = tokens.last_mut() {
+        if *previous_class == class {
+            previous.end = range.end;
+            return;
+        }
+    }
+    tokens.push((class, range));
+}
+
+fn class_for(stack: &ScopeStack) -> Option<&'static str> {
+    for scope in stack.as_slice().iter().rev() {
+        let class = CLASSES
+            .iter()
+            .find(|(prefix, _)| prefix.is_prefix_of(*scope))
+            .map(|(_, class)| *class);
+        match class {
+            Some("") => return None,
+            Some(class) => return Some(class),
+            None => {}
+        }
+    }
+    None
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_get_highlight_js_classes() {
+        let html = r#"<pre><code class="language-rust">fn main() {
+    // &lt;hi&gt;
+    let x = &quot;a&quot;;
+}
+</code></pre>"#;
+
+        let got = highlight_code_blocks(html);
+
+        assert!(got.starts_with(r#"<pre><code class="language-rust hljs">"#));
+        assert!(got.contains(r#"<span class="hljs-keyword">fn</span>"#));
+        assert!(got.contains(r#"<span class="hljs-title">main</span>"#));
+        assert!(got.contains(r#"<span class="hljs-comment">// &lt;hi&gt;"#));
+        assert!(got.contains(r#"<span class="hljs-string">&quot;a&quot;</span>"#));
+    }
+
+    #[test]
+    fn hidden_lines_are_kept() {
+        let html = "<code class=\"language-rust\"><span class=\"boring\">fn main() {\n</span>    let x = 1;\n<span class=\"boring\">}\n</span></code>";
+
+        let got = highlight_code_blocks(html);
+
+        assert!(got.contains(
+            r#"<span class="boring"><span class="hljs-keyword">fn</span> <span class="hljs-title">main</span>"#
+        ));
+        assert!(got.contains("</span>    <span class=\"hljs-keyword\">let</span> x"));
+        assert!(got.ends_with("}\n</span></code>"));
+    }
+
+    #[test]
+    fn unknown_languages_and_editable_blocks_are_not_highlighted() {
+        let unknown = r#"<code class="language-not-a-language">some code</code>"#;
+        assert_eq!(
+            highlight_code_blocks(unknown),
+            r#"<code class="language-not-a-language hljs">some code</code>"#
+        );
+
+        let editable = r#"<code class="language-rust editable">fn main() {}</code>"#;
+        assert_eq!(
+            highlight_code_blocks(editable),
+            r#"<code class="language-rust editable hljs">fn main() {}</code>"#
+        );
+
+        let plain = r#"<code class="hljs">not a block</code>"#;
+        assert_eq!(highlight_code_blocks(plain), plain);
+    }
+}
@@ -0,0 +1,226 @@
+//! Book-keeping for incremental rebuilds of the HTML output.
+//!
+//! When `output.html.incremental` is set the renderer records a hash of
+//! everything that goes into each page, so the next build only has to
+//! re-render the pages whose inputs have changed. What each page contributes
+//! to the print page and the search index is recorded too, so those can be
+//! put back together without rendering the unchanged chapters. The state is
+//! kept in a
+//! `.mdbook-incremental` directory in the book's root, so they aren't
+//! published along with the book.
+
+use crate::errors::*;
+#[cfg(feature = "search")]
+use crate::renderer::html_handlebars::search::Document;
+use crate::utils;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The directory in a book's root which the build states are kept in.
+const STATE_DIR: &str = ".mdbook-incremental";
+
+/// What was rendered by the previous build.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(super) struct BuildState {
+    /// A hash of the inputs shared by every page, such as the table of
+    /// contents, the theme templates and the renderer's configuration.
+    pub global: u64,
+    /// What was rendered for each chapter, keyed by the chapter's path.
+    pub pages: HashMap<String, PageState>,
+}
+
+/// What was rendered for a single chapter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct PageState {
+    /// A hash of everything the chapter's page was rendered from.
+    pub hash: u64,
+    /// The chapter's part of the print page.
+    pub print_content: String,
+    /// The ids of the page's elements, which links can point at. They're
+    /// only collected when links are checked.
+    pub ids: BTreeSet<String>,
+    /// The chapter's entries in the search index.
+    #[cfg(feature = "search")]
+    pub search_documents: Vec<Document>,
+}
+
+impl BuildState {
+    /// Create an empty state for a build whose shared inputs hash to `global`.
+    pub fn new(global: u64) -> BuildState {
+        BuildState {
+            global,
+            pages: HashMap::new(),
+        }
+    }
+
+    /// Load the state left behind by the previous build of the book at
+    /// `root` into `destination`, if there is one and its output is still
+    /// there.
+    pub fn load(root: &Path, destination: &Path) -> Option<BuildState> {
+        let is_empty = fs::read_dir(destination).map_or(true, |mut dir| dir.next().is_none());
+        if is_empty {
+            return None;
+        }
+        let contents = fs::read_to_string(state_file(root, destination)).ok()?;
+
+        match serde_json::from_str(&contents) {
+            Ok(state) => Some(state),
+            Err(e) => {
+                warn!("Ignoring the corrupt incremental build state: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, root: &Path, destination: &Path) -> Result<()> {
+        let contents = serde_json::to_string(self)?;
+        utils::fs::create_file(&state_file(root, destination))?
+            .write_all(contents.as_bytes())
+            .with_context(|| "Unable to save the incremental build state")
+    }
+
+    /// Remove the state of the builds of the book at `root` into
+    /// `destination`, if there is one.
+    pub fn remove(root: &Path, destination: &Path) -> Result<()> {
+        let file = state_file(root, destination);
+        if file.exists() {
+            fs::remove_file(&file).with_context(|| {
+                format!(
+                    "Unable to remove the incremental build state {}",
+                    file.display()
+                )
+            })?;
+            // Only succeeds once the state of every destination is gone
+            let _ = fs::remove_dir(root.join(STATE_DIR));
+        }
+        Ok(())
+    }
+
+    /// The page at `path`, if the previous build rendered it from exactly
+    /// the same inputs.
+    pub fn unchanged_page(&self, path: &str, hash: u64) -> Option<&PageState> {
+        self.pages.get(path).filter(|page| page.hash == hash)
+    }
+
+    /// Whether both builds rendered the same chapters from the same inputs.
+    pub fn has_same_pages(&self, other: &BuildState) -> bool {
+        self.pages.len() == other.pages.len()
+            && self
+                .pages
+                .iter()
+                .all(|(path, page)| other.unchanged_page(path, page.hash).is_some())
+    }
+}
+
+/// Remove the state of every build of the book at `root`.
+pub(super) fn remove_all(root: &Path) -> Result<()> {
+    let dir = root.join(STATE_DIR);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).with_context(|| format!("Unable to remove {}", dir.display()))?;
+    }
+    Ok(())
+}
+
+/// The file the state of the builds of the book at `root` into
+/// `destination` is saved to.
+fn state_file(root: &Path, destination: &Path) -> PathBuf {
+    let destination = fs::canonicalize(destination).unwrap_or_else(|_| destination.to_owned());
+    let name = format!("{:016x}.json", hash_of(&destination));
+    root.join(STATE_DIR).join(name)
+}
+
+/// Hash anything which can be hashed.
+pub(super) fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::Builder as TempFileBuilder;
+
+    fn page(content: &str) -> PageState {
+        PageState {
+            hash: hash_of(content),
+            print_content: content.to_string(),
+            ids: BTreeSet::new(),
+            #[cfg(feature = "search")]
+            search_documents: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn state_survives_a_round_trip() {
+        let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+        let destination = temp.path().join("book");
+        utils::fs::write_file(&destination, "intro.html", b"").unwrap();
+        let mut state = BuildState::new(hash_of("global"));
+        state.pages.insert(String::from("intro.md"), page("intro"));
+
+        state.save(temp.path(), &destination).unwrap();
+        let got = BuildState::load(temp.path(), &destination).unwrap();
+
+        assert_eq!(got, state);
+        assert_eq!(
+            got.unchanged_page("intro.md", hash_of("intro")),
+            Some(&page("intro"))
+        );
+        assert!(got.unchanged_page("intro.md", hash_of("edited")).is_none());
+        assert!(got.unchanged_page("missing.md", hash_of("intro")).is_none());
+        assert!(got.has_same_pages(&state));
+        state.pages.insert(String::from("intro.md"), page("edited"));
+        assert!(!got.has_same_pages(&state));
+
+        // The state isn't part of the output, and is ignored once the
+        // output is removed
+        assert_eq!(fs::read_dir(&destination).unwrap().count(), 1);
+        assert!(temp.path().join(STATE_DIR).is_dir());
+        fs::remove_file(destination.join("intro.html")).unwrap();
+        assert!(BuildState::load(temp.path(), &destination).is_none());
+    }
+
+    #[test]
+    fn missing_or_corrupt_state_is_ignored() {
+        let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+        let destination = temp.path().join("book");
+        assert!(BuildState::load(temp.path(), &destination).is_none());
+
+        utils::fs::write_file(&destination, "intro.html", b"").unwrap();
+        assert!(BuildState::load(temp.path(), &destination).is_none());
+
+        let file = state_file(temp.path(), &destination);
+        utils::fs::create_file(&file)
+            .unwrap()
+            .write_all(b"not json")
+            .unwrap();
+        assert!(BuildState::load(temp.path(), &destination).is_none());
+    }
+
+    #[test]
+    fn state_can_be_removed() {
+        let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+        let first = temp.path().join("first");
+        let second = temp.path().join("second");
+        let state = BuildState::new(hash_of("global"));
+        state.save(temp.path(), &first).unwrap();
+        state.save(temp.path(), &second).unwrap();
+
+        BuildState::remove(temp.path(), &first).unwrap();
+        assert!(!state_file(temp.path(), &first).exists());
+        assert!(state_file(temp.path(), &second).exists());
+
+        BuildState::remove(temp.path(), &second).unwrap();
+        assert!(!temp.path().join(STATE_DIR).exists());
+
+        state.save(temp.path(), &first).unwrap();
+        remove_all(temp.path()).unwrap();
+        assert!(!temp.path().join(STATE_DIR).exists());
+    }
+}
@@ -0,0 +1,355 @@
+//! Validation of the internal links between chapters of a book.
+
+use crate::book::{Book, BookItem, Chapter};
+use crate::diagnostic::Diagnostic;
+use crate::errors::*;
+use crate::utils;
+
+use percent_encoding::percent_decode_str;
+use pulldown_cmark::{Event, Tag};
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fs;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+/// Maps the output path of every rendered page to the ids available in it.
+pub(crate) type Pages<'a> = HashMap<PathBuf, &'a BTreeSet<String>>;
+
+/// A link which doesn't point to an existing chapter, file or anchor.
+#[derive(Debug, Clone, PartialEq)]
+struct BrokenLink {
+    /// The chapter's source file, relative to the `SUMMARY.md` file.
+    source_path: PathBuf,
+    /// The link's destination, as written in the chapter.
+    dest: String,
+    /// Why the link is considered broken.
+    reason: String,
+}
+
+/// Check every relative link and `#fragment` in the book against the
+/// rendered `pages`, reporting a diagnostic for each broken one.
+pub(crate) fn check_links(book: &Book, src_dir: &Path, pages: &Pages<'_>) -> Result<()> {
+    let diagnostics = broken_link_diagnostics(book, src_dir, pages);
+    let count = diagnostics.len();
+    for diagnostic in diagnostics {
+        diagnostic.emit();
+    }
+
+    match count {
+        0 => Ok(()),
+        1 => bail!("Found 1 broken link"),
+        n => bail!("Found {} broken links", n),
+    }
+}
+
+fn broken_link_diagnostics(book: &Book, src_dir: &Path, pages: &Pages<'_>) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for ch in chapters(book) {
+        let broken = broken_links_in(ch, pages, src_dir);
+        if broken.is_empty() {
+            continue;
+        }
+
+        // The chapter's content has had its front matter stripped and its
+        // includes expanded, so the spans are found again in the file itself.
+        let file = src_dir.join(&broken[0].source_path);
+        let source = fs::read_to_string(&file).unwrap_or_default();
+        let mut spans = link_spans(&source);
+
+        for link in broken {
+            let diagnostic = Diagnostic::error(format!("broken link to `{}`", link.dest));
+            let diagnostic = match spans.get_mut(&link.dest).and_then(VecDeque::pop_front) {
+                Some(span) => diagnostic.with_span(&source, span, Some(link.reason)),
+                None => diagnostic.with_note(link.reason).with_note(
+                    "the link isn't in the chapter's own file, it may be in an included file",
+                ),
+            };
+            diagnostics.push(diagnostic.with_file(&file));
+        }
+    }
+
+    diagnostics
+}
+
+/// The byte ranges of the links in a chapter's source, in order, keyed by
+/// their destination.
+fn link_spans(source: &str) -> HashMap<String, VecDeque<Range<usize>>> {
+    let mut spans: HashMap<_, VecDeque<_>> = HashMap::new();
+    for (event, range) in utils::new_cmark_parser(source).into_offset_iter() {
+        if let Event::Start(Tag::Link(_, dest, _)) = event {
+            spans.entry(dest.to_string()).or_default().push_back(range);
+        }
+    }
+    spans
+}
+
+/// Every chapter which is rendered to a page.
+fn chapters(book: &Book) -> impl Iterator<Item = &Chapter> {
+    book.iter().filter_map(|item| match item {
+        BookItem::Chapter(ch) if !ch.is_draft_chapter() => Some(ch),
+        _ => None,
+    })
+}
+
+fn broken_links_in(ch: &Chapter, pages: &Pages<'_>, src_dir: &Path) -> Vec<BrokenLink> {
+    let path = ch.path.as_ref().unwrap();
+    let source_path = ch.source_path.clone().unwrap_or_else(|| path.clone());
+    let mut broken = Vec::new();
+
+    for event in utils::new_cmark_parser(&ch.content) {
+        let dest = match event {
+            Event::Start(Tag::Link(_, dest, _)) => dest,
+            _ => continue,
+        };
+
+        if let Err(reason) = check_link(&dest, path, pages, src_dir) {
+            broken.push(BrokenLink {
+                source_path: source_path.clone(),
+                dest: dest.to_string(),
+                reason,
+            });
+        }
+    }
+
+    broken
+}
+
+/// The ids of the elements in a rendered page, which links can point at.
+pub(crate) fn element_ids(html: &str) -> BTreeSet<String> {
+    lazy_static! {
+        static ref ID: Regex = Regex::new(r#"\s(?:id|name)="([^"]+)""#).unwrap();
+    }
+
+    ID.captures_iter(html)
+        .map(|caps| caps[1].to_string())
+        .collect()
+}
+
+fn check_link(
+    dest: &str,
+    chapter_path: &Path,
+    pages: &Pages<'_>,
+    src_dir: &Path,
+) -> std::result::Result<(), String> {
+    lazy_static! {
+        static ref SCHEME_LINK: Regex = Regex::new(r"^[a-z][a-z0-9+.-]*:").unwrap();
+    }
+
+    // External and absolute links can't be checked against the book.
+    if SCHEME_LINK.is_match(dest) || dest.starts_with('/') {
+        return Ok(());
+    }
+
+    let (target, fragment) = match dest.find('#') {
+        Some(idx) => (&dest[..idx], Some(&dest[idx + 1..])),
+        None => (dest, None),
+    };
+    let target = match target.find('?') {
+        Some(idx) => &target[..idx],
+        None => target,
+    };
+    let target = decode(target);
+    let fragment = fragment.map(decode);
+
+    let page = if target.is_empty() {
+        chapter_path.with_extension("html")
+    } else {
+        let base = chapter_path.parent().unwrap_or_else(|| Path::new(""));
+        let mut resolved = match normalize(&base.join(target.as_ref())) {
+            Some(resolved) => resolved,
+            // The link leaves the book, so there's nothing to check it against.
+            None => return Ok(()),
+        };
+        if target.ends_with('/') || resolved.as_os_str().is_empty() {
+            resolved.push("index.html");
+        }
+
+        match resolved.extension().and_then(|ext| ext.to_str()) {
+            Some("md") | Some("html") => resolved.with_extension("html"),
+            _ if src_dir.join(&resolved).exists() => return Ok(()),
+            _ => return Err(format!("`{}` does not exist", resolved.display())),
+        }
+    };
+
+    let ids = match pages.get(&page) {
+        Some(ids) => ids,
+        None if is_generated_page(&page) => return Ok(()),
+        None => return Err(format!("no chapter is rendered to `{}`", page.display())),
+    };
+
+    match fragment {
+        Some(fragment) if !fragment.is_empty() && !ids.contains(fragment.as_ref()) => Err(format!(
+            "`{}` has no heading or element with the id `{}`",
+            page.display(),
+            fragment
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Pages the HTML renderer emits which aren't backed by a chapter.
+fn is_generated_page(page: &Path) -> bool {
+    page == Path::new("print.html") || page == Path::new("404.html")
+}
+
+/// Lexically resolve `.` and `..` components, returning `None` if the path
+/// escapes the source directory.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::Normal(part) => normalized.push(part),
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    Some(normalized)
+}
+
+/// Undo the percent-encoding of a link's path or fragment, which the browser
+/// also does before looking for the file or element.
+fn decode(text: &str) -> Cow<'_, str> {
+    percent_decode_str(text).decode_utf8_lossy()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::html_handlebars::hbs_renderer::build_header_links;
+
+    fn book_with(chapters: &[(&str, &str)]) -> Book {
+        let mut book = Book::new();
+        for (path, content) in chapters {
+            let ch = Chapter::new(path, content.to_string(), *path, Vec::new());
+            book.push_item(ch);
+        }
+        book
+    }
+
+    /// The ids of each chapter's page, as the HTML renderer collects them.
+    fn ids_of(book: &Book) -> Vec<(PathBuf, BTreeSet<String>)> {
+        chapters(book)
+            .map(|ch| {
+                let html = build_header_links(&utils::render_markdown(&ch.content, false));
+                let path = ch.path.as_ref().unwrap().with_extension("html");
+                (path, element_ids(&html))
+            })
+            .collect()
+    }
+
+    fn broken_links(book: &Book) -> Vec<String> {
+        let ids = ids_of(book);
+        let pages = ids.iter().map(|(path, ids)| (path.clone(), ids)).collect();
+
+        broken_link_diagnostics(book, Path::new(""), &pages)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    }
+
+    #[test]
+    fn valid_links_are_accepted() {
+        let book = book_with(&[
+            (
+                "intro.md",
+                "# Intro\n\n[first](nested/first.md#some-heading)\n",
+            ),
+            (
+                "nested/first.md",
+                "# Some heading\n\n[back](../intro.md) [top](#some-heading) \
+                 [html](../intro.html#intro) [web](https://example.com/#nope) \
+                 <a id=\"custom\"></a> [custom](#custom)\n",
+            ),
+        ]);
+
+        assert_eq!(broken_links(&book), Vec::<String>::new());
+    }
+
+    #[test]
+    fn missing_chapters_and_anchors_are_reported() {
+        let book = book_with(&[(
+            "intro.md",
+            "# Intro\n\n[missing](missing.md)\n\n[anchor](intro.md#nope) [own](#intro)\n",
+        )]);
+
+        assert_eq!(
+            broken_links(&book),
+            vec![
+                "broken link to `missing.md`",
+                "broken link to `intro.md#nope`"
+            ]
+        );
+    }
+
+    #[test]
+    fn percent_encoded_links_are_decoded() {
+        let book = book_with(&[
+            ("foo bar.md", "# Café\n\n[self](#caf%C3%A9)\n"),
+            (
+                "intro.md",
+                "[spaced](foo%20bar.md#caf%C3%A9) [bad](foo%20bar.md#caf%C3%A8)\n",
+            ),
+        ]);
+
+        assert_eq!(
+            broken_links(&book),
+            vec!["broken link to `foo%20bar.md#caf%C3%A8`"]
+        );
+    }
+
+    #[test]
+    fn duplicate_headings_get_numbered_ids() {
+        let book = book_with(&[("a.md", "# Dup\n\n# Dup\n\n[ok](#dup-1) [bad](#dup-2)\n")]);
+
+        assert_eq!(broken_links(&book), vec!["broken link to `#dup-2`"]);
+    }
+
+    #[test]
+    fn links_leaving_the_book_are_ignored() {
+        let book = book_with(&[("a.md", "[parent](../../README.md)\n")]);
+
+        assert!(broken_links(&book).is_empty());
+    }
+
+    #[test]
+    fn broken_links_fail_the_check() {
+        let book = book_with(&[("a.md", "# A\n\n[ok](#a) [one](b.md) [two](#b)\n")]);
+        let ids = ids_of(&book);
+        let pages = ids.iter().map(|(path, ids)| (path.clone(), ids)).collect();
+
+        let err = check_links(&book, Path::new(""), &pages).unwrap_err();
+        assert_eq!(err.to_string(), "Found 2 broken links");
+        assert!(check_links(&book, Path::new(""), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn diagnostics_point_into_the_source_file() {
+        let temp = tempfile::Builder::new().prefix("links").tempdir().unwrap();
+        let source =
+            "---\ntitle: Intro\n---\n# Intro\n\n{{#include snippet.md}}\n\n  [gone](missing.md)\n";
+        fs::write(temp.path().join("intro.md"), source).unwrap();
+        let content = "# Intro\n\n[included](nope.md)\n\n  [gone](missing.md)\n";
+        let book = book_with(&[("intro.md", content)]);
+        let ids = ids_of(&book);
+        let pages = ids.iter().map(|(path, ids)| (path.clone(), ids)).collect();
+
+        let diagnostics = broken_link_diagnostics(&book, temp.path(), &pages);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics[0].span.is_none());
+        assert_eq!(diagnostics[0].notes.len(), 2);
+        let span = diagnostics[1].span.as_ref().unwrap();
+        assert_eq!((span.line, span.column), (8, 3));
+        assert_eq!(diagnostics[1].file, Some(temp.path().join("intro.md")));
+    }
+}
@@ -1,9 +1,15 @@
 #![allow(missing_docs)] // FIXME: Document this
 
+pub(crate) use self::hbs_renderer::build_header_links;
 pub use self::hbs_renderer::HtmlHandlebars;
 
+mod feed;
 mod hbs_renderer;
 mod helpers;
+mod highlight;
+mod incremental;
+mod links;
+mod sitemap;
 
 #[cfg(feature = "search")]
 mod search;
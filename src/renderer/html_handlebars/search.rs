@@ -5,19 +5,37 @@ use std::path::Path;
 use elasticlunr::Index;
 use pulldown_cmark::*;
 
-use crate::book::{Book, BookItem};
+use crate::book::Chapter;
 use crate::config::Search;
 use crate::errors::*;
 use crate::theme::searcher;
 use crate::utils;
 
-/// Creates all files required for search.
-pub fn create_files(search_config: &Search, destination: &Path, book: &Book) -> Result<()> {
+/// A section of a chapter, as it's added to the search index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct Document {
+    url: String,
+    title: String,
+    body: String,
+    breadcrumbs: String,
+}
+
+/// Creates all files required for search, indexing `documents` in order.
+pub fn create_files<'a>(
+    search_config: &Search,
+    destination: &Path,
+    documents: impl Iterator<Item = &'a Document>,
+) -> Result<()> {
     let mut index = Index::new(&["title", "body", "breadcrumbs"]);
-    let mut doc_urls = Vec::with_capacity(book.sections.len());
+    let mut doc_urls = Vec::new();
 
-    for item in book.iter() {
-        render_item(&mut index, &search_config, &mut doc_urls, item)?;
+    for document in documents {
+        let doc_ref = doc_urls.len().to_string();
+        doc_urls.push(document.url.clone());
+        index.add_doc(
+            &doc_ref,
+            &[&document.title, &document.body, &document.breadcrumbs],
+        );
     }
 
     let index = write_to_json(index, &search_config, doc_urls)?;
@@ -42,50 +60,60 @@ pub fn create_files(search_config: &Search, destination: &Path, book: &Book) ->
     Ok(())
 }
 
-/// Uses the given arguments to construct a search document, then inserts it to the given index.
+/// Uses the given arguments to construct a search document.
 fn add_doc(
-    index: &mut Index,
-    doc_urls: &mut Vec<String>,
+    documents: &mut Vec<Document>,
     anchor_base: &str,
     section_id: &Option<String>,
-    items: &[&str],
+    title: &str,
+    body: &str,
+    breadcrumbs: &str,
 ) {
     let url = if let Some(ref id) = *section_id {
         Cow::Owned(format!("{}#{}", anchor_base, id))
     } else {
         Cow::Borrowed(anchor_base)
     };
-    let url = utils::collapse_whitespace(url.trim());
-    let doc_ref = doc_urls.len().to_string();
-    doc_urls.push(url.into());
 
-    let items = items.iter().map(|&x| utils::collapse_whitespace(x.trim()));
-    index.add_doc(&doc_ref, items);
+    documents.push(Document {
+        url: utils::collapse_whitespace(url.trim()).into(),
+        title: utils::collapse_whitespace(title.trim()).into(),
+        body: utils::collapse_whitespace(body.trim()).into(),
+        breadcrumbs: utils::collapse_whitespace(breadcrumbs.trim()).into(),
+    });
 }
 
-/// Renders markdown into flat unformatted text and adds it to the search index.
-fn render_item(
-    index: &mut Index,
+/// Renders a chapter's markdown into flat unformatted text, split into the
+/// documents which go in the search index. Draft chapters have nothing to
+/// index.
+pub(super) fn documents(
     search_config: &Search,
-    doc_urls: &mut Vec<String>,
-    item: &BookItem,
-) -> Result<()> {
-    let chapter = match *item {
-        BookItem::Chapter(ref ch) if !ch.is_draft_chapter() => ch,
-        _ => return Ok(()),
+    admonitions: bool,
+    chapter: &Chapter,
+) -> Result<Vec<Document>> {
+    let mut documents = Vec::new();
+    let chapter_path = match chapter.path {
+        Some(ref path) => path,
+        None => return Ok(documents),
     };
-
-    let chapter_path = chapter
-        .path
-        .as_ref()
-        .expect("Checked that path exists above");
     let filepath = Path::new(&chapter_path).with_extension("html");
     let filepath = filepath
         .to_str()
         .with_context(|| "Could not convert HTML path to str")?;
     let anchor_base = utils::fs::normalize_path(filepath);
 
-    let mut p = utils::new_cmark_parser(&chapter.content).peekable();
+    let content = if admonitions {
+        utils::expand_fenced_admonitions(&chapter.content)
+    } else {
+        Cow::Borrowed(chapter.content.as_str())
+    };
+    let parser = utils::new_cmark_parser(&content);
+    let events = if admonitions {
+        utils::convert_admonitions(parser)
+    } else {
+        parser.collect()
+    };
+    let mut p = events.into_iter().peekable();
 
     let mut in_heading = false;
     let max_section_depth = u32::from(search_config.heading_split_level);
@@ -104,11 +132,12 @@ fn render_item(
                     // Section finished, the next heading is following now
                     // Write the data to the index, and clear it for the next section
                     add_doc(
-                        index,
-                        doc_urls,
+                        &mut documents,
                         &anchor_base,
                         &section_id,
-                        &[&heading, &body, &breadcrumbs.join(" » ")],
+                        &heading,
+                        &body,
+                        &breadcrumbs.join(" » "),
                     );
                     section_id = None;
                     heading.clear();
@@ -168,15 +197,16 @@ fn render_item(
     if !heading.is_empty() {
         // Make sure the last section is added to the index
         add_doc(
-            index,
-            doc_urls,
+            &mut documents,
             &anchor_base,
             &section_id,
-            &[&heading, &body, &breadcrumbs.join(" » ")],
+            &heading,
+            &body,
+            &breadcrumbs.join(" » "),
         );
     }
 
-    Ok(())
+    Ok(documents)
 }
 
 fn write_to_json(index: Index, search_config: &Search, doc_urls: Vec<String>) -> Result<String> {
@@ -0,0 +1,320 @@
+//! The `sitemap.xml` (and optionally `robots.txt`) generated when
+//! `output.html.site-url` is set, so search engines can find every chapter.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use chrono::{DateTime, Utc};
+
+use super::hbs_renderer::absolute_site_url;
+use crate::book::{Book, BookItem};
+use crate::config::{BookConfig, HtmlConfig, LastModified};
+use crate::errors::*;
+use crate::utils;
+
+/// Creates `sitemap.xml`, and `robots.txt` if it's enabled.
+pub fn create_files(
+    book_config: &BookConfig,
+    html_config: &HtmlConfig,
+    src_dir: &Path,
+    destination: &Path,
+    book: &Book,
+) -> Result<()> {
+    // `mdbook serve` and books hosted at a path have no absolute URL to list,
+    // which isn't worth a warning on every build.
+    let base = match absolute_site_url(book_config, html_config) {
+        Some(base) => base,
+        None => {
+            debug!("Not generating sitemap.xml without an absolute site-url");
+            return Ok(());
+        }
+    };
+
+    let redirects: HashSet<&str> = html_config
+        .redirect
+        .keys()
+        .map(|original| original.trim_start_matches('/'))
+        .collect();
+
+    // Draft chapters don't have a page
+    let pages: Vec<_> = book
+        .iter()
+        .filter_map(|item| match *item {
+            BookItem::Chapter(ref ch) => ch.path.as_ref().map(|path| {
+                let url = utils::fs::normalize_path(&path.with_extension("html").to_string_lossy());
+                (ch, url)
+            }),
+            _ => None,
+        })
+        .filter(|(_, url)| !redirects.contains(url.as_str()))
+        .collect();
+
+    let sources: Vec<_> = pages
+        .iter()
+        .filter_map(|(ch, _)| ch.source_path.as_ref())
+        .map(|source| src_dir.join(source))
+        .collect();
+    let commit_dates = match html_config.sitemap.lastmod {
+        LastModified::Git => last_commits(src_dir, &sources),
+        _ => HashMap::new(),
+    };
+
+    let mut sitemap = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+
+    for (ch, url) in pages {
+        sitemap.push_str("  <url>\n");
+        sitemap.push_str(&format!(
+            "    <loc>{}</loc>\n",
+            utils::escape_xml(&format!("{}{}", base, url))
+        ));
+        let source = ch.source_path.as_ref().map(|source| src_dir.join(source));
+        if let Some(date) =
+            source.and_then(|source| last_modified(html_config, &commit_dates, &source))
+        {
+            sitemap.push_str(&format!("    <lastmod>{}</lastmod>\n", date));
+        }
+        sitemap.push_str("  </url>\n");
+    }
+
+    sitemap.push_str("</urlset>\n");
+    utils::fs::write_file(destination, "sitemap.xml", sitemap.as_bytes())?;
+    debug!("Creating sitemap.xml ✓");
+
+    if html_config.sitemap.robots_txt {
+        if src_dir.join("robots.txt").exists() {
+            debug!("Not creating robots.txt because the book has its own");
+        } else {
+            let robots = format!("User-agent: *\nAllow: /\n\nSitemap: {}sitemap.xml\n", base);
+            utils::fs::write_file(destination, "robots.txt", robots.as_bytes())?;
+            debug!("Creating robots.txt ✓");
+        }
+    }
+
+    Ok(())
+}
+
+/// The date the chapter at `source` was last changed, as `YYYY-MM-DD`.
+fn last_modified(
+    html_config: &HtmlConfig,
+    commit_dates: &HashMap<PathBuf, String>,
+    source: &Path,
+) -> Option<String> {
+    match html_config.sitemap.lastmod {
+        LastModified::Omit => None,
+        LastModified::Git => fs::canonicalize(source)
+            .ok()
+            .and_then(|source| commit_dates.get(&source).cloned())
+            .or_else(|| modified_date(source)),
+        LastModified::Mtime => modified_date(source),
+    }
+}
+
+fn modified_date(source: &Path) -> Option<String> {
+    modification_time(source).map(|time| time.format("%Y-%m-%d").to_string())
+}
+
+/// The modification time of a chapter's source file.
+pub(super) fn modification_time(source: &Path) -> Option<DateTime<Utc>> {
+    let modified = fs::metadata(source).and_then(|m| m.modified()).ok()?;
+    Some(DateTime::from(modified))
+}
+
+/// The date of the last commit changing each of the `sources`, keyed by their
+/// canonical path. A single `git log` lists the files changed by every commit,
+/// newest first, so the first date seen for a file is the one we want.
+fn last_commits(src_dir: &Path, sources: &[PathBuf]) -> HashMap<PathBuf, String> {
+    let mut dates = HashMap::new();
+    let root = match git(
+        src_dir,
+        ["rev-parse", "--show-toplevel"].iter().map(OsStr::new),
+    ) {
+        Some(root) => PathBuf::from(root.trim_end()),
+        None => return dates,
+    };
+    let args = [
+        "-c",
+        "core.quotepath=off",
+        "log",
+        "--format=%x00%cI",
+        "--name-only",
+        "--",
+    ];
+    let sources = sources.iter().map(|source| source.as_os_str());
+    let log = match git(src_dir, args.iter().map(OsStr::new).chain(sources)) {
+        Some(log) => log,
+        None => return dates,
+    };
+
+    let mut date = None;
+    for line in log.lines() {
+        if let Some(commit_date) = line.strip_prefix('\0') {
+            date = commit_date.get(..10);
+        } else if !line.is_empty() {
+            if let Some(date) = date {
+                dates
+                    .entry(root.join(line))
+                    .or_insert_with(|| date.to_string());
+            }
+        }
+    }
+    dates
+}
+
+/// Run git in `dir`, returning its output if it succeeded.
+fn git<'a>(dir: &Path, args: impl IntoIterator<Item = &'a OsStr>) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book::Chapter;
+    use crate::config::Sitemap;
+    use tempfile::Builder as TempFileBuilder;
+
+    fn book() -> Book {
+        let mut book = Book::new();
+        book.push_item(Chapter::new("Intro", String::new(), "intro.md", vec![]));
+        book.push_item(Chapter::new("Q & A", String::new(), "q&a/index.md", vec![]));
+        book.push_item(Chapter::new("Moved", String::new(), "old.md", vec![]));
+        book.push_item(Chapter::new_draft("Draft", vec![]));
+        book
+    }
+
+    #[test]
+    fn chapters_are_listed_by_url() {
+        let temp = TempFileBuilder::new().prefix("sitemap").tempdir().unwrap();
+        let cfg = HtmlConfig {
+            site_url: Some("https://example.com/book".to_string()),
+            sitemap: Sitemap {
+                lastmod: LastModified::Omit,
+                ..Default::default()
+            },
+            redirect: vec![("/old.html".to_string(), "intro.html".to_string())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+
+        create_files(
+            &BookConfig::default(),
+            &cfg,
+            temp.path(),
+            temp.path(),
+            &book(),
+        )
+        .unwrap();
+
+        let got = fs::read_to_string(temp.path().join("sitemap.xml")).unwrap();
+        assert_eq!(
+            got,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n  \
+             <url>\n    <loc>https://example.com/book/intro.html</loc>\n  </url>\n  \
+             <url>\n    <loc>https://example.com/book/q&amp;a/index.html</loc>\n  </url>\n\
+             </urlset>\n"
+        );
+        assert!(!temp.path().join("robots.txt").exists());
+    }
+
+    #[test]
+    fn translations_are_listed_under_their_language() {
+        let temp = TempFileBuilder::new().prefix("sitemap").tempdir().unwrap();
+        let book_config = BookConfig {
+            multilingual: true,
+            language: Some("fr".to_string()),
+            ..Default::default()
+        };
+        let cfg = HtmlConfig {
+            site_url: Some("https://example.com/book/".to_string()),
+            sitemap: Sitemap {
+                lastmod: LastModified::Omit,
+                robots_txt: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        create_files(&book_config, &cfg, temp.path(), temp.path(), &book()).unwrap();
+
+        let got = fs::read_to_string(temp.path().join("sitemap.xml")).unwrap();
+        assert!(got.contains("<loc>https://example.com/book/fr/intro.html</loc>"));
+        let robots = fs::read_to_string(temp.path().join("robots.txt")).unwrap();
+        assert!(robots.contains("Sitemap: https://example.com/book/fr/sitemap.xml"));
+    }
+
+    #[test]
+    fn relative_site_urls_are_skipped() {
+        let temp = TempFileBuilder::new().prefix("sitemap").tempdir().unwrap();
+        let cfg = HtmlConfig {
+            site_url: Some("/book/".to_string()),
+            ..Default::default()
+        };
+
+        create_files(
+            &BookConfig::default(),
+            &cfg,
+            temp.path(),
+            temp.path(),
+            &book(),
+        )
+        .unwrap();
+
+        assert!(!temp.path().join("sitemap.xml").exists());
+    }
+
+    #[test]
+    fn git_dates_are_read_from_the_history() {
+        let temp = TempFileBuilder::new().prefix("sitemap").tempdir().unwrap();
+        let git = |args: &[&str], date: &str| {
+            let status = Command::new("git")
+                .args(args)
+                .current_dir(temp.path())
+                .env("GIT_AUTHOR_DATE", date)
+                .env("GIT_COMMITTER_DATE", date)
+                .env("GIT_AUTHOR_NAME", "mdbook")
+                .env("GIT_AUTHOR_EMAIL", "mdbook@example.com")
+                .env("GIT_COMMITTER_NAME", "mdbook")
+                .env("GIT_COMMITTER_EMAIL", "mdbook@example.com")
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "-q"], "");
+        fs::write(temp.path().join("intro.md"), "# Intro").unwrap();
+        fs::write(temp.path().join("old.md"), "# Old").unwrap();
+        git(&["add", "."], "");
+        git(&["commit", "-qm", "first"], "2020-01-02T03:04:05Z");
+        fs::write(temp.path().join("intro.md"), "# Introduction").unwrap();
+        git(&["commit", "-qam", "second"], "2021-06-07T08:09:10Z");
+        fs::write(temp.path().join("new.md"), "# New").unwrap();
+
+        let sources: Vec<_> = ["intro.md", "old.md", "new.md"]
+            .iter()
+            .map(|name| temp.path().join(name))
+            .collect();
+        let dates = last_commits(temp.path(), &sources);
+
+        let date = |name: &str| {
+            let path = fs::canonicalize(temp.path().join(name)).unwrap();
+            dates.get(&path).map(String::as_str)
+        };
+        assert_eq!(date("intro.md"), Some("2021-06-07"));
+        assert_eq!(date("old.md"), Some("2020-01-02"));
+        assert_eq!(date("new.md"), None);
+    }
+}
@@ -11,14 +11,16 @@
 //! [For Developers]: https://rust-lang.github.io/mdBook/for_developers/index.html
 //! [RenderContext]: struct.RenderContext.html
 
+pub use self::epub_renderer::EpubRenderer;
 pub use self::html_handlebars::HtmlHandlebars;
 pub use self::markdown_renderer::MarkdownRenderer;
 
+mod epub_renderer;
 mod html_handlebars;
 mod markdown_renderer;
 
 use shlex::Shlex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
 use std::io::{self, ErrorKind, Read};
 use std::path::{Path, PathBuf};
@@ -66,6 +68,13 @@ pub struct RenderContext {
     pub destination: PathBuf,
     #[serde(skip)]
     pub(crate) chapter_titles: HashMap<PathBuf, String>,
+    /// The language a multilingual book falls back to.
+    #[serde(skip)]
+    pub(crate) default_language: Option<String>,
+    /// The paths of the chapters in each translation of a multilingual book,
+    /// keyed by language code.
+    #[serde(skip)]
+    pub(crate) translations: BTreeMap<String, BTreeSet<PathBuf>>,
     #[serde(skip)]
     __non_exhaustive: (),
 }
@@ -84,6 +93,8 @@ impl RenderContext {
             root: root.into(),
             destination: destination.into(),
             chapter_titles: HashMap::new(),
+            default_language: None,
+            translations: BTreeMap::new(),
             __non_exhaustive: (),
         }
     }
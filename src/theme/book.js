@@ -148,12 +148,6 @@ function playground_text(playground) {
         .catch(error => result_block.innerText = "Playground Communication: " + error.message);
     }
 
-    // Syntax highlighting Configuration
-    hljs.configure({
-        tabReplace: '    ', // 4 spaces
-        languages: [],      // Languages used for auto-detection
-    });
-
     let code_nodes = Array
         .from(document.querySelectorAll('code'))
         // Don't highlight `inline code` blocks in headers.
@@ -165,12 +159,23 @@ function playground_text(playground) {
         Array
             .from(document.querySelectorAll('code.editable'))
             .forEach(function (block) { block.classList.remove('language-rust'); });
+    }
 
-        Array
-            .from(document.querySelectorAll('code:not(.editable)'))
-            .forEach(function (block) { hljs.highlightBlock(block); });
-    } else {
-        code_nodes.forEach(function (block) { hljs.highlightBlock(block); });
+    // highlight.js isn't loaded when the book was highlighted as it was built
+    if (window.hljs) {
+        // Syntax highlighting Configuration
+        hljs.configure({
+            tabReplace: '    ', // 4 spaces
+            languages: [],      // Languages used for auto-detection
+        });
+
+        if (window.ace) {
+            Array
+                .from(document.querySelectorAll('code:not(.editable)'))
+                .forEach(function (block) { hljs.highlightBlock(block); });
+        } else {
+            code_nodes.forEach(function (block) { hljs.highlightBlock(block); });
+        }
     }
 
     // Adding the hljs class gives code blocks the color css
@@ -423,6 +428,47 @@ function playground_text(playground) {
     });
 })();
 
+(function languages() {
+    var languageToggleButton = document.getElementById('language-toggle');
+    var languagePopup = document.getElementById('language-list');
+
+    // Only multilingual books have a language switcher
+    if (!languageToggleButton) { return; }
+
+    function showLanguages() {
+        languagePopup.style.display = 'block';
+        languageToggleButton.setAttribute('aria-expanded', true);
+        languagePopup.querySelector('a').focus();
+    }
+
+    function hideLanguages() {
+        languagePopup.style.display = 'none';
+        languageToggleButton.setAttribute('aria-expanded', false);
+    }
+
+    languageToggleButton.addEventListener('click', function () {
+        if (languagePopup.style.display === 'block') {
+            hideLanguages();
+        } else {
+            showLanguages();
+        }
+    });
+
+    document.addEventListener('click', function(e) {
+        if (languagePopup.style.display === 'block' && !languageToggleButton.contains(e.target) && !languagePopup.contains(e.target)) {
+            hideLanguages();
+        }
+    });
+
+    document.addEventListener('keydown', function (e) {
+        if (e.key === 'Escape' && languagePopup.contains(e.target)) {
+            e.preventDefault();
+            hideLanguages();
+            languageToggleButton.focus();
+        }
+    });
+})();
+
 (function sidebar() {
     var html = document.querySelector("html");
     var sidebar = document.getElementById("sidebar");
@@ -669,3 +715,29 @@ function playground_text(playground) {
         }, { passive: true });
     })();
 })();
+
+(function pageToc() {
+    var links = Array.from(document.querySelectorAll('.page-toc a'));
+    if (links.length === 0) {
+        return;
+    }
+    var headings = links.map(function (link) {
+        return document.getElementById(decodeURIComponent(link.hash.substring(1)));
+    });
+
+    // Highlight the last heading which has been scrolled past
+    function updateActive() {
+        var active = 0;
+        headings.forEach(function (heading, i) {
+            if (heading && heading.getBoundingClientRect().top <= 100) {
+                active = i;
+            }
+        });
+        links.forEach(function (link, i) {
+            link.classList.toggle('active', i === active);
+        });
+    }
+
+    document.addEventListener('scroll', updateActive, { passive: true });
+    updateActive();
+})();
@@ -7,6 +7,7 @@ pub mod fonts;
 #[cfg(feature = "search")]
 pub mod searcher;
 
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
@@ -62,6 +63,10 @@ pub struct Theme {
     pub ayu_highlight_css: Vec<u8>,
     pub highlight_js: Vec<u8>,
     pub clipboard_js: Vec<u8>,
+    /// The other templates chapters are rendered with instead of `index.hbs`,
+    /// keyed by their name without the extension. Only the ones loaded with
+    /// [`Theme::load_templates`] are included.
+    pub templates: BTreeMap<String, Vec<u8>>,
 }
 
 impl Theme {
@@ -140,6 +145,60 @@ impl Theme {
 
         theme
     }
+
+    /// Load the templates with the given names from `<name>.hbs` files in the
+    /// `theme_dir`, so chapters can be rendered with them. Names which can't
+    /// be used for a page template, and templates which don't exist, are
+    /// skipped with a warning.
+    pub fn load_templates<'a, P, I>(&mut self, theme_dir: P, names: I)
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = &'a str>,
+    {
+        let theme_dir = theme_dir.as_ref();
+
+        for name in names {
+            if name == "index" || self.templates.contains_key(name) {
+                continue;
+            }
+            if !is_page_template(name) {
+                warn!(
+                    "Ignoring the template \"{}\", which isn't a page template in the theme \
+                     directory",
+                    name
+                );
+                continue;
+            }
+            let filename = theme_dir.join(format!("{}.hbs", name));
+            if !filename.is_file() {
+                warn!(
+                    "The template \"{}\" doesn't exist, there is no {}",
+                    name,
+                    filename.display()
+                );
+                continue;
+            }
+            let mut template = Vec::new();
+            match load_file_contents(&filename, &mut template) {
+                Ok(()) => {
+                    self.templates.insert(name.to_string(), template);
+                }
+                Err(e) => {
+                    warn!("Couldn't load custom file, {}: {}", filename.display(), e);
+                }
+            }
+        }
+    }
+}
+
+/// Whether chapters can be rendered with the template called `name`. Page
+/// templates are `.hbs` files directly in the theme directory, other than the
+/// `head`, `header` and `redirect` templates every theme has.
+pub(crate) fn is_page_template(name: &str) -> bool {
+    !name.is_empty()
+        && !name.contains(&['/', '\\'][..])
+        && !name.contains("..")
+        && !matches!(name, "head" | "header" | "redirect")
 }
 
 impl Default for Theme {
@@ -161,6 +220,7 @@ impl Default for Theme {
             ayu_highlight_css: AYU_HIGHLIGHT_CSS.to_owned(),
             highlight_js: HIGHLIGHT_JS.to_owned(),
             clipboard_js: CLIPBOARD_JS.to_owned(),
+            templates: BTreeMap::new(),
         }
     }
 }
@@ -248,11 +308,48 @@ mod tests {
             ayu_highlight_css: Vec::new(),
             highlight_js: Vec::new(),
             clipboard_js: Vec::new(),
+            templates: BTreeMap::new(),
         };
 
         assert_eq!(got, empty);
     }
 
+    #[test]
+    fn only_named_templates_are_loaded() {
+        let temp = TempFileBuilder::new().prefix("mdbook-").tempdir().unwrap();
+        fs::write(temp.path().join("landing.hbs"), "landing page").unwrap();
+        fs::write(temp.path().join("footer.hbs"), "a partial").unwrap();
+        fs::write(temp.path().join("head.hbs"), "head").unwrap();
+
+        let mut got = Theme::new(temp.path());
+        assert!(got.templates.is_empty());
+
+        got.load_templates(temp.path(), vec!["landing", "head", "missing"]);
+        assert_eq!(got.templates.len(), 1);
+        assert_eq!(got.templates["landing"], b"landing page");
+        assert_eq!(got.head, b"head");
+    }
+
+    #[test]
+    fn templates_outside_the_theme_directory_are_rejected() {
+        let temp = TempFileBuilder::new().prefix("mdbook-").tempdir().unwrap();
+        let theme_dir = temp.path().join("theme");
+        fs::create_dir(&theme_dir).unwrap();
+        fs::create_dir(theme_dir.join("nested")).unwrap();
+        fs::write(temp.path().join("secret.hbs"), "secret").unwrap();
+        fs::write(theme_dir.join("nested/page.hbs"), "nested").unwrap();
+
+        let mut got = Theme::new(&theme_dir);
+        got.load_templates(&theme_dir, vec!["../secret", "nested/page", "..\\secret"]);
+        assert!(got.templates.is_empty());
+
+        assert!(is_page_template("landing"));
+        assert!(is_page_template("index"));
+        assert!(!is_page_template("redirect"));
+        assert!(!is_page_template("../secret"));
+        assert!(!is_page_template(""));
+    }
+
     #[test]
     fn favicon_override() {
         let temp = TempFileBuilder::new().prefix("mdbook-").tempdir().unwrap();
@@ -0,0 +1,298 @@
+//! Admonitions (or callouts), which are blockquotes starting with a marker
+//! like `[!NOTE]`:
+//!
+//! ```markdown
+//! > [!WARNING]
+//! > Don't do this.
+//! ```
+//!
+//! They can also be written as a fenced block:
+//!
+//! ```markdown
+//! :::warning
+//! Don't do this.
+//! :::
+//! ```
+//!
+//! Either way, they're rendered as `<div class="admonition warning">`.
+
+use pulldown_cmark::{CowStr, Event, Tag};
+use regex::Regex;
+use std::borrow::Cow;
+use std::ops::Range;
+
+use super::new_cmark_parser;
+
+/// Rewrite admonitions fenced by `:::` lines as blockquotes, so the
+/// markdown parser sees them the same way as admonitions written with a
+/// `[!TYPE]` marker.
+pub(crate) fn expand_fenced_admonitions(text: &str) -> Cow<'_, str> {
+    lazy_static! {
+        static ref OPEN: Regex =
+            Regex::new(r"^ {0,3}:{3,}[ \t]*([A-Za-z]+)[ \t]*(.*?)\s*$").unwrap();
+        static ref CLOSE: Regex = Regex::new(r"^ {0,3}:{3,}\s*$").unwrap();
+    }
+
+    if !text.contains(":::") {
+        return Cow::Borrowed(text);
+    }
+
+    let code_blocks = code_blocks(text);
+    let mut expanded = String::with_capacity(text.len() + 64);
+    let mut depth = 0;
+    let mut offset = 0;
+
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            expanded.push('\n');
+        }
+        let in_code = code_blocks.iter().any(|range| range.contains(&offset));
+        offset += line.len() + 1;
+        let prefix = "> ".repeat(depth);
+
+        if in_code {
+            expanded.push_str(&prefix);
+            expanded.push_str(line);
+        } else if let Some(caps) = OPEN.captures(line) {
+            expanded.push_str(&prefix);
+            expanded.push_str("> [!");
+            expanded.push_str(&caps[1]);
+            expanded.push(']');
+            if !caps[2].is_empty() {
+                expanded.push(' ');
+                expanded.push_str(&caps[2]);
+            }
+            depth += 1;
+        } else if depth > 0 && CLOSE.is_match(line) {
+            // An empty line ends the blockquote
+            depth -= 1;
+            expanded.push_str("> ".repeat(depth).trim_end());
+        } else if line.trim().is_empty() {
+            expanded.push_str(prefix.trim_end());
+        } else {
+            expanded.push_str(&prefix);
+            expanded.push_str(line);
+        }
+    }
+
+    Cow::Owned(expanded)
+}
+
+fn code_blocks(text: &str) -> Vec<Range<usize>> {
+    new_cmark_parser(text)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_)) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Replace the blockquotes starting with a `[!TYPE]` marker with admonition
+/// `<div>`s. The rest of the marker's line, including any inline markup, is
+/// used as the admonition's title, which otherwise defaults to its type.
+pub(crate) fn convert_admonitions<'a, I>(events: I) -> Vec<Event<'a>>
+where
+    I: Iterator<Item = Event<'a>>,
+{
+    lazy_static! {
+        static ref MARKER: Regex = Regex::new(r"^\[!([A-Za-z]+)\][ \t]*").unwrap();
+    }
+
+    let mut events = events.peekable();
+    let mut converted = Vec::new();
+    // Whether each of the currently open blockquotes is an admonition
+    let mut quotes = Vec::new();
+
+    while let Some(event) = events.next() {
+        match event {
+            Event::Start(Tag::BlockQuote) => {
+                if events.peek() != Some(&Event::Start(Tag::Paragraph)) {
+                    quotes.push(false);
+                    converted.push(Event::Start(Tag::BlockQuote));
+                    continue;
+                }
+                events.next();
+
+                let mut first_line = Vec::new();
+                let mut text = String::new();
+                while let Some(Event::Text(_)) = events.peek() {
+                    if let Some(Event::Text(t)) = events.next() {
+                        text.push_str(&t);
+                        first_line.push(Event::Text(t));
+                    }
+                }
+
+                let caps = match MARKER.captures(&text) {
+                    Some(caps) => caps,
+                    None => {
+                        quotes.push(false);
+                        converted.push(Event::Start(Tag::BlockQuote));
+                        converted.push(Event::Start(Tag::Paragraph));
+                        converted.extend(first_line);
+                        continue;
+                    }
+                };
+
+                let kind = caps[1].to_ascii_lowercase();
+                let mut title = Vec::new();
+                let rest = &text[caps[0].len()..];
+                if !rest.is_empty() {
+                    title.push(Event::Text(CowStr::from(rest.to_string())));
+                }
+                // The text may be followed by inline markup on the same line
+                let mut depth = 0;
+                while let Some(event) = events.peek() {
+                    match event {
+                        Event::SoftBreak | Event::HardBreak | Event::End(Tag::Paragraph)
+                            if depth == 0 =>
+                        {
+                            break
+                        }
+                        Event::Start(_) => depth += 1,
+                        Event::End(_) => depth -= 1,
+                        _ => {}
+                    }
+                    title.extend(events.next());
+                }
+                if let Some(Event::Text(last)) = title.last_mut() {
+                    *last = CowStr::from(last.trim_end().to_string());
+                }
+                if title.is_empty() {
+                    let mut default = kind.clone();
+                    default[..1].make_ascii_uppercase();
+                    title.push(Event::Text(CowStr::from(default)));
+                }
+
+                quotes.push(true);
+                converted.push(Event::Html(CowStr::from(format!(
+                    "<div class=\"admonition {}\">\n<p class=\"admonition-title\">",
+                    kind
+                ))));
+                converted.extend(title);
+                converted.push(Event::Html(CowStr::Borrowed("</p>\n")));
+
+                match events.peek() {
+                    // The rest of the first paragraph is the admonition's body
+                    Some(Event::SoftBreak) | Some(Event::HardBreak) => {
+                        events.next();
+                        converted.push(Event::Start(Tag::Paragraph));
+                    }
+                    Some(Event::End(Tag::Paragraph)) => {
+                        events.next();
+                    }
+                    _ => converted.push(Event::Start(Tag::Paragraph)),
+                }
+            }
+            Event::End(Tag::BlockQuote) => {
+                if quotes.pop() == Some(true) {
+                    converted.push(Event::Html(CowStr::Borrowed("</div>\n")));
+                } else {
+                    converted.push(Event::End(Tag::BlockQuote));
+                }
+            }
+            event => converted.push(event),
+        }
+    }
+
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{render_markdown, render_markdown_with_options};
+
+    fn render(text: &str) -> String {
+        render_markdown_with_options(text, false, true, None)
+    }
+
+    #[test]
+    fn marked_blockquotes_become_admonitions() {
+        let got = render("> [!NOTE]\n> Some *text*\n> more\n");
+
+        assert_eq!(
+            got,
+            "<div class=\"admonition note\">\n<p class=\"admonition-title\">Note</p>\n\
+             <p>Some <em>text</em>\nmore</p>\n</div>\n"
+        );
+    }
+
+    #[test]
+    fn text_after_the_marker_is_the_title() {
+        let got = render("> [!WARNING] Mind & the gap\n>\n> Body\n");
+
+        assert_eq!(
+            got,
+            "<div class=\"admonition warning\">\n\
+             <p class=\"admonition-title\">Mind &amp; the gap</p>\n\
+             <p>Body</p>\n</div>\n"
+        );
+    }
+
+    #[test]
+    fn titles_keep_their_inline_markup() {
+        let got = render("> [!TIP] Use *this* `one` \n> Body\n");
+
+        assert_eq!(
+            got,
+            "<div class=\"admonition tip\">\n\
+             <p class=\"admonition-title\">Use <em>this</em> <code>one</code></p>\n\
+             <p>Body</p>\n</div>\n"
+        );
+    }
+
+    #[test]
+    fn ordinary_blockquotes_are_untouched() {
+        let src = "> Just [a quote]\n\n> > [!TIP] nested\n";
+        let got = render(src);
+
+        assert_eq!(
+            got,
+            "<blockquote>\n<p>Just [a quote]</p>\n</blockquote>\n\
+             <blockquote>\n<div class=\"admonition tip\">\n\
+             <p class=\"admonition-title\">nested</p>\n</div>\n</blockquote>\n"
+        );
+    }
+
+    #[test]
+    fn fenced_admonitions_become_blockquotes() {
+        let src = "Before\n:::tip Read this\nSome text\n\n:::caution\nNested\n:::\n:::\nAfter\n";
+
+        assert_eq!(
+            expand_fenced_admonitions(src),
+            "Before\n> [!tip] Read this\n> Some text\n>\n> > [!caution]\n> > Nested\n>\n\nAfter\n"
+        );
+    }
+
+    #[test]
+    fn fences_in_code_blocks_are_ignored() {
+        let src = "```\n:::note\n```\n\n:::note\n```\n:::\n```\n:::\n";
+
+        assert_eq!(
+            expand_fenced_admonitions(src),
+            "```\n:::note\n```\n\n> [!note]\n> ```\n> :::\n> ```\n\n"
+        );
+    }
+
+    #[test]
+    fn admonitions_are_only_rendered_when_enabled() {
+        let src = "> [!NOTE]\n> Text\n\n:::tip\nMore\n:::\n";
+
+        assert_eq!(
+            render_markdown(src, false),
+            "<blockquote>\n<p>[!NOTE]\nText</p>\n</blockquote>\n<p>:::tip\nMore\n:::</p>\n"
+        );
+    }
+
+    #[test]
+    fn fenced_admonitions_are_rendered() {
+        let got = render(":::warning\nCareful!\n:::\n");
+
+        assert_eq!(
+            got,
+            "<div class=\"admonition warning\">\n<p class=\"admonition-title\">Warning</p>\n\
+             <p>Careful!</p>\n</div>\n"
+        );
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(missing_docs)] // FIXME: Document this
 
+mod admonition;
 pub mod fs;
 mod string;
 pub(crate) mod toml_ext;
@@ -12,6 +13,8 @@ use std::borrow::Cow;
 use std::fmt::Write;
 use std::path::Path;
 
+#[cfg(feature = "search")]
+pub(crate) use self::admonition::{convert_admonitions, expand_fenced_admonitions};
 pub use self::string::{
     take_anchored_lines, take_lines, take_rustdoc_include_anchored_lines,
     take_rustdoc_include_lines,
@@ -178,10 +181,31 @@ pub fn new_cmark_parser(text: &str) -> Parser<'_> {
 }
 
 pub fn render_markdown_with_path(text: &str, curly_quotes: bool, path: Option<&Path>) -> String {
+    render_markdown_with_options(text, curly_quotes, false, path)
+}
+
+/// Like [`render_markdown_with_path`], but also rendering
+/// [admonitions](admonition) when `admonitions` is set.
+pub(crate) fn render_markdown_with_options(
+    text: &str,
+    curly_quotes: bool,
+    admonitions: bool,
+    path: Option<&Path>,
+) -> String {
     let mut s = String::with_capacity(text.len() * 3 / 2);
-    let p = new_cmark_parser(text);
+    let text = if admonitions {
+        admonition::expand_fenced_admonitions(text)
+    } else {
+        Cow::Borrowed(text)
+    };
+    let p = new_cmark_parser(&text);
+    let events: Box<dyn Iterator<Item = Event<'_>>> = if admonitions {
+        Box::new(admonition::convert_admonitions(p).into_iter())
+    } else {
+        Box::new(p)
+    };
     let mut converter = EventQuoteConverter::new(curly_quotes);
-    let events = p
+    let events = events
         .map(clean_codeblock_headers)
         .map(|event| adjust_links(event, path))
         .map(|event| converter.convert(event));
@@ -275,6 +299,22 @@ fn convert_quotes_to_curly(original_text: &str) -> String {
         .collect()
 }
 
+/// Escape the characters which are special in XML text and attributes.
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 /// Prints a "backtrace" of some `Error`.
 pub fn log_backtrace(e: &Error) {
     error!("Error: {}", e);
@@ -286,6 +326,14 @@ pub fn log_backtrace(e: &Error) {
 
 #[cfg(test)]
 mod tests {
+    #[test]
+    fn special_xml_characters_are_escaped() {
+        assert_eq!(
+            super::escape_xml(r#"Tom & "Jerry" <3 'em"#),
+            "Tom &amp; &quot;Jerry&quot; &lt;3 &apos;em"
+        );
+    }
+
     mod render_markdown {
         use super::super::render_markdown;
 
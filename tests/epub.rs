@@ -0,0 +1,98 @@
+//! Integration tests for the built-in EPUB renderer.
+
+mod dummy_book;
+
+use crate::dummy_book::DummyBook;
+use mdbook::utils::fs::write_file;
+use mdbook::MDBook;
+use std::fs::File;
+use std::io::Read;
+use zip::ZipArchive;
+
+fn build_epub() -> (ZipArchive<File>, tempfile::TempDir) {
+    let temp = DummyBook::new().build().unwrap();
+    let book_toml = r#"
+        [book]
+        title = "Dummy Book"
+
+        [output.epub]
+        cover-image = "first/cover.png"
+        "#;
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    write_file(&temp.path().join("src"), "first/cover.png", b"not a png").unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let file = File::open(md.build_dir_for("epub").join("book.epub")).unwrap();
+    (ZipArchive::new(file).unwrap(), temp)
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> String {
+    let mut contents = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    contents
+}
+
+#[test]
+fn mimetype_is_the_first_uncompressed_entry() {
+    let (mut archive, _temp) = build_epub();
+
+    let mimetype = archive.by_index(0).unwrap();
+    assert_eq!(mimetype.name(), "mimetype");
+    assert_eq!(mimetype.compression(), zip::CompressionMethod::Stored);
+    drop(mimetype);
+    assert_eq!(read_entry(&mut archive, "mimetype"), "application/epub+zip");
+}
+
+#[test]
+fn package_document_lists_every_chapter_in_order() {
+    let (mut archive, _temp) = build_epub();
+
+    let opf = read_entry(&mut archive, "OEBPS/content.opf");
+    assert!(opf.contains("<dc:title>Dummy Book</dc:title>"));
+    assert!(
+        opf.contains(r#"href="first/cover.png" media-type="image/png" properties="cover-image""#)
+    );
+
+    let intro = opf.find(r#"href="intro.html""#).unwrap();
+    let nested = opf.find(r#"href="first/nested.html""#).unwrap();
+    let conclusion = opf.find(r#"href="conclusion.html""#).unwrap();
+    assert!(intro < nested && nested < conclusion);
+    assert!(opf.contains(r#"<itemref idref="chapter-0"/>"#));
+}
+
+#[test]
+fn chapters_share_links_and_heading_ids_with_the_html_output() {
+    let (mut archive, _temp) = build_epub();
+
+    let nested = read_entry(&mut archive, "OEBPS/first/nested.html");
+    assert!(
+        nested.contains(r##"<h1 id="nested-chapter"><a class="header" href="#nested-chapter">"##)
+    );
+    assert!(nested.contains(r#"href="../stylesheet.css""#));
+
+    let nav = read_entry(&mut archive, "OEBPS/nav.xhtml");
+    assert!(nav.contains(r#"<a href="first/nested.html">1.1. Nested Chapter</a>"#));
+}
+
+#[test]
+fn hidden_chapters_are_not_in_the_reading_order() {
+    let temp = DummyBook::new().build().unwrap();
+    write_file(temp.path(), "book.toml", b"[output.epub]\n").unwrap();
+    let summary = "- [First](first.md)\n- [Legal notice](legal.md \"hidden\")\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    write_file(temp.path(), "src/first.md", b"# First").unwrap();
+    write_file(temp.path(), "src/legal.md", b"# Legal notice").unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let file = File::open(md.build_dir_for("epub").join("book.epub")).unwrap();
+    let mut archive = ZipArchive::new(file).unwrap();
+    let opf = read_entry(&mut archive, "OEBPS/content.opf");
+    assert!(opf.contains(r#"<itemref idref="chapter-0"/>"#));
+    assert!(opf.contains(r#"<itemref idref="chapter-1" linear="no"/>"#));
+}
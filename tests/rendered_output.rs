@@ -592,6 +592,749 @@ fn edit_url_has_configured_src_dir_edit_url() {
     );
 }
 
+#[test]
+fn multilingual_books_render_each_translation() {
+    let temp = TempFileBuilder::new().prefix("mdBook").tempdir().unwrap();
+    let book_toml = r#"
+        [book]
+        title = "Multilingual"
+        multilingual = true
+        language = "en"
+
+        [language.en]
+        name = "English"
+
+        [language.ja]
+        name = "日本語"
+        title = "多言語"
+        "#;
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+
+    let summary = "- [Intro](intro.md)\n- [Untranslated](untranslated.md)\n";
+    let en_summary = format!("{}- [Extra](extra.md)\n", summary);
+    write_file(temp.path(), "src/en/SUMMARY.md", en_summary.as_bytes()).unwrap();
+    write_file(temp.path(), "src/en/intro.md", b"# Introduction").unwrap();
+    write_file(temp.path(), "src/en/untranslated.md", b"# English only").unwrap();
+    write_file(temp.path(), "src/en/extra.md", b"# Not in the translation").unwrap();
+    write_file(temp.path(), "src/ja/SUMMARY.md", summary.as_bytes()).unwrap();
+    write_file(temp.path(), "src/ja/intro.md", "# はじめに".as_bytes()).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    assert_eq!(md.translations.keys().collect::<Vec<_>>(), vec!["ja"]);
+    md.build().unwrap();
+
+    let en = temp.path().join("book/en");
+    assert_contains_strings(
+        en.join("intro.html"),
+        &[
+            r#"<html lang="en""#,
+            "Introduction",
+            "<title>Intro - Multilingual</title>",
+            r#"href="../ja/intro.html" hreflang="ja""#,
+        ],
+    );
+    // Chapters missing from a translation link to its index instead
+    assert_contains_strings(
+        en.join("extra.html"),
+        &[r#"href="../ja/index.html" hreflang="ja""#],
+    );
+    assert_contains_strings(
+        temp.path().join("book/index.html"),
+        &[r#"content="0;URL='en/index.html'""#],
+    );
+
+    let ja = temp.path().join("book/ja");
+    assert_contains_strings(
+        ja.join("intro.html"),
+        &[
+            r#"<html lang="ja""#,
+            "はじめに",
+            "<title>Intro - 多言語</title>",
+            r#"href="../en/intro.html" hreflang="en""#,
+            "日本語",
+        ],
+    );
+    assert_contains_strings(ja.join("untranslated.html"), &["English only"]);
+    assert!(!temp.path().join("src/ja/untranslated.md").exists());
+}
+
+#[test]
+fn untranslated_chapters_keep_their_own_source() {
+    let temp = TempFileBuilder::new().prefix("mdBook").tempdir().unwrap();
+    let book_toml = r#"
+        [book]
+        multilingual = true
+        language = "en"
+
+        [language.en]
+        [language.ja]
+
+        [output.html]
+        edit-url-template = "https://example.com/edit/{path}"
+        "#;
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+
+    let summary = "- [Guide](guide/index.md)\n    - [Install](guide/install.md)\n";
+    for language in &["en", "ja"] {
+        let path = format!("src/{}/SUMMARY.md", language);
+        write_file(temp.path(), &path, summary.as_bytes()).unwrap();
+    }
+    let guide = "# Guide\n\n{{#include snippet.md}}\n";
+    write_file(temp.path(), "src/en/guide/index.md", guide.as_bytes()).unwrap();
+    write_file(temp.path(), "src/en/guide/snippet.md", b"Included text").unwrap();
+    write_file(temp.path(), "src/en/guide/install.md", b"# Install").unwrap();
+    write_file(
+        temp.path(),
+        "src/ja/guide/install.md",
+        "# インストール".as_bytes(),
+    )
+    .unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let ja = temp.path().join("book/ja/guide");
+    assert_contains_strings(
+        ja.join("index.html"),
+        &[
+            "Included text",
+            r#"href="https://example.com/edit/src/en/guide/index.md""#,
+        ],
+    );
+    assert_contains_strings(
+        ja.join("install.html"),
+        &[
+            "インストール",
+            r#"href="https://example.com/edit/src/ja/guide/install.md""#,
+        ],
+    );
+}
+
+#[test]
+fn check_links_accepts_valid_links() {
+    let temp = TempFileBuilder::new().prefix("mdBook").tempdir().unwrap();
+    write_file(
+        temp.path(),
+        "book.toml",
+        b"[output.html]\ncheck-links = true\n",
+    )
+    .unwrap();
+    let summary = "- [Intro](intro.md)\n- [Nested](nested/chapter.md)\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    let intro = "# Intro\n\n[nested](nested/chapter.md#some-heading)\n";
+    write_file(temp.path(), "src/intro.md", intro.as_bytes()).unwrap();
+    let nested = "# Some heading\n\n[intro](../intro.md) [self](#some-heading)\n";
+    write_file(temp.path(), "src/nested/chapter.md", nested.as_bytes()).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+}
+
+#[test]
+fn check_links_rejects_broken_links() {
+    let temp = TempFileBuilder::new().prefix("mdBook").tempdir().unwrap();
+    write_file(
+        temp.path(),
+        "book.toml",
+        b"[output.html]\ncheck-links = true\n",
+    )
+    .unwrap();
+    let summary = "- [Intro](intro.md)\n- [Second](second.md)\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    let intro = "# Intro\n\n[gone](missing.md)\n[no anchor](second.md#nope)\n";
+    write_file(temp.path(), "src/intro.md", intro.as_bytes()).unwrap();
+    write_file(temp.path(), "src/second.md", b"# Second").unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    let got = md.build();
+    assert!(got.is_err());
+    assert!(!temp.path().join("book/intro.html").exists());
+}
+
+#[test]
+fn incremental_builds_only_rerender_changed_chapters() {
+    let temp = TempFileBuilder::new().prefix("mdBook").tempdir().unwrap();
+    let summary = "- [First](first.md)\n- [Second](second.md)\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    write_file(temp.path(), "src/first.md", b"# First\n\nUnchanged").unwrap();
+    write_file(temp.path(), "src/second.md", b"# Second").unwrap();
+
+    let build = || {
+        let mut md = MDBook::load(temp.path()).unwrap();
+        md.config.set("output.html.incremental", true).unwrap();
+        md.build().unwrap();
+    };
+    build();
+
+    // Mark the rendered pages so we can tell whether they get rewritten.
+    let book = temp.path().join("book");
+    write_file(&book, "first.html", b"stale first").unwrap();
+    write_file(&book, "second.html", b"stale second").unwrap();
+
+    write_file(temp.path(), "src/second.md", b"# Edited second").unwrap();
+    build();
+    assert_eq!(
+        fs::read_to_string(book.join("first.html")).unwrap(),
+        "stale first"
+    );
+    assert_contains_strings(book.join("second.html"), &["Edited second"]);
+    assert_contains_strings(book.join("print.html"), &["Unchanged", "Edited second"]);
+    #[cfg(feature = "search")]
+    assert_contains_strings(
+        book.join("searchindex.json"),
+        &[r#""body":"Unchanged""#, "Edited second"],
+    );
+
+    // Changing the table of contents affects every page.
+    let summary = "- [First](first.md)\n- [Renamed](second.md)\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    build();
+    assert_contains_strings(book.join("first.html"), &["Renamed"]);
+
+    // So does the Rust edition used by the playground.
+    write_file(
+        temp.path(),
+        "src/first.md",
+        b"# First\n\n```rust\nfn main() {}\n```",
+    )
+    .unwrap();
+    build();
+    write_file(temp.path(), "book.toml", b"[rust]\nedition = \"2018\"\n").unwrap();
+    build();
+    assert_contains_strings(book.join("first.html"), &["edition2018"]);
+
+    // The build state isn't published with the book, and goes away when
+    // incremental builds are turned off
+    assert!(!book.join(".mdbook-incremental").exists());
+    assert!(temp.path().join(".mdbook-incremental").is_dir());
+    MDBook::load(temp.path()).unwrap().build().unwrap();
+    assert!(!temp.path().join(".mdbook-incremental").exists());
+}
+
+#[test]
+fn front_matter_is_stripped_and_exposed_to_templates() {
+    let temp = TempFileBuilder::new().prefix("mdBook").tempdir().unwrap();
+    write_file(
+        temp.path(),
+        "book.toml",
+        b"[book]\ndescription = \"A book\"\n",
+    )
+    .unwrap();
+    let summary = "- [Intro](intro.md)\n- [Other](other.md)\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    let intro = "+++\ndescription = \"All about the intro\"\n+++\n# Intro\n";
+    write_file(temp.path(), "src/intro.md", intro.as_bytes()).unwrap();
+    write_file(temp.path(), "src/other.md", b"# Other").unwrap();
+    let index = "{{ chapter.meta.description }}|{{ description }}|{{{ content }}}";
+    write_file(temp.path(), "theme/index.hbs", index.as_bytes()).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let intro = fs::read_to_string(temp.path().join("book/intro.html")).unwrap();
+    assert!(intro.starts_with("All about the intro|All about the intro|"));
+    assert!(!intro.contains("+++"));
+    let other = fs::read_to_string(temp.path().join("book/other.html")).unwrap();
+    assert!(other.starts_with("|A book|"));
+}
+
+#[test]
+fn print_page_keeps_chapters_in_summary_order() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    let src = temp.path().join("src");
+    let mut summary = String::from("# Summary\n\n");
+    for i in 0..50 {
+        summary.push_str(&format!("- [Chapter {0}](chapter_{0}.md)\n", i));
+        let content = format!("# Chapter {0}\n\nThe text of chapter {0}.\n", i);
+        write_file(&src, format!("chapter_{}.md", i), content.as_bytes()).unwrap();
+    }
+    write_file(&src, "SUMMARY.md", summary.as_bytes()).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let print = fs::read_to_string(temp.path().join("book/print.html")).unwrap();
+    let positions: Vec<usize> = (0..50)
+        .map(|i| print.find(&format!("The text of chapter {}.", i)).unwrap())
+        .collect();
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    for i in 0..50 {
+        assert!(temp
+            .path()
+            .join(format!("book/chapter_{}.html", i))
+            .exists());
+    }
+}
+
+#[test]
+fn server_side_highlighting_replaces_highlight_js() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut cfg = Config::default();
+    cfg.set("output.html.highlight", "server").unwrap();
+    let md = MDBook::load_with_config(temp.path(), cfg).unwrap();
+    md.build().unwrap();
+
+    let nested = temp.path().join("book/first/nested.html");
+    assert_contains_strings(
+        &nested,
+        &[
+            r#"<code class="language-rust hljs">"#,
+            r#"<span class="hljs-keyword">fn</span> <span class="hljs-title">main</span>"#,
+        ],
+    );
+    assert_doesnt_contain_strings(&nested, &["highlight.js"]);
+}
+
+#[test]
+fn math_preprocessor_renders_mathml() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    write_file(temp.path(), "book.toml", b"[preprocessor.math]\n").unwrap();
+    write_file(temp.path(), "src/SUMMARY.md", b"- [Math](math.md)\n").unwrap();
+    let math = "# Math\n\nInline $x^2$, and display:\n\n$$\n\\frac{1}{2}\n$$\n\n`$not_math$`\n";
+    write_file(temp.path(), "src/math.md", math.as_bytes()).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let chapter = temp.path().join("book/math.html");
+    assert_contains_strings(
+        &chapter,
+        &[
+            "Inline <math xmlns=\"http://www.w3.org/1998/Math/MathML\" alttext=\"x&#94;2\"><msup><mi>x</mi><mn>2</mn></msup></math>, and display:",
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"block\" alttext=\"&#92;frac&#123;1&#125;&#123;2&#125;\"><mfrac><mn>1</mn><mn>2</mn></mfrac></math>",
+            "<code>$not_math$</code>",
+        ],
+    );
+    assert_doesnt_contain_strings(&chapter, &["$x^2$", "$$"]);
+}
+
+#[test]
+fn chapters_can_use_other_templates() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    let book_toml = "[output.html.templates]\n\"reference/**\" = \"reference\"\n";
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    let summary = "- [Landing](landing.md)\n- [Guide](guide.md)\n- [Api](reference/api.md)\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    write_file(
+        temp.path(),
+        "src/landing.md",
+        b"---\ntemplate: landing\n---\n# Welcome",
+    )
+    .unwrap();
+    write_file(temp.path(), "src/guide.md", b"# Guide").unwrap();
+    write_file(temp.path(), "src/reference/api.md", b"# Api").unwrap();
+    write_file(temp.path(), "theme/index.hbs", b"index: {{{ content }}}").unwrap();
+    write_file(
+        temp.path(),
+        "theme/landing.hbs",
+        b"landing: {{{ content }}}",
+    )
+    .unwrap();
+    write_file(
+        temp.path(),
+        "theme/reference.hbs",
+        b"reference: {{{ content }}}",
+    )
+    .unwrap();
+    // Other `.hbs` files, like partials, aren't registered as templates
+    write_file(temp.path(), "theme/footer.hbs", b"{{#if}}").unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let book = temp.path().join("book");
+    let read = |path: &str| fs::read_to_string(book.join(path)).unwrap();
+    assert!(read("landing.html").starts_with("landing: "));
+    assert!(read("index.html").starts_with("landing: "));
+    assert!(read("guide.html").starts_with("index: "));
+    assert!(read("reference/api.html").starts_with("reference: "));
+    assert!(read("print.html").starts_with("index: "));
+
+    fs::remove_file(temp.path().join("theme/landing.hbs")).unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    let err = md.build().unwrap_err();
+    assert!(format!("{:?}", err).contains("no landing.hbs in the theme directory"));
+}
+
+#[test]
+#[cfg(not(feature = "script-helpers"))]
+fn script_helpers_need_their_feature() {
+    let temp = DummyBook::new().build().unwrap();
+    let book_toml = "[output.html.helpers]\nshout = \"helpers/shout.rhai\"\n";
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    write_file(temp.path(), "theme/helpers/shout.rhai", b"params[0]").unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    let err = md.build().unwrap_err();
+    assert!(format!("{:?}", err).contains("`script-helpers` feature"));
+}
+
+#[test]
+#[cfg(feature = "script-helpers")]
+fn theme_partials_and_script_helpers_are_registered() {
+    let temp = DummyBook::new().build().unwrap();
+    let book_toml = r#"
+        [output.html.partials]
+        footer = "partials/footer.hbs"
+
+        [output.html.helpers]
+        shout = "helpers/shout.rhai"
+        "#;
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    write_file(
+        temp.path(),
+        "theme/partials/footer.hbs",
+        b"<footer>{{ shout \"the end\" }}</footer>",
+    )
+    .unwrap();
+    write_file(
+        temp.path(),
+        "theme/helpers/shout.rhai",
+        b"params[0].to_upper() + \"!\"",
+    )
+    .unwrap();
+    write_file(
+        temp.path(),
+        "theme/index.hbs",
+        b"{{{ content }}}{{> footer }}",
+    )
+    .unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(
+        temp.path().join("book/intro.html"),
+        &["<footer>THE END!</footer>"],
+    );
+
+    write_file(temp.path(), "theme/helpers/shout.rhai", b"params[0].(").unwrap();
+    let md = MDBook::load(temp.path()).unwrap();
+    let err = md.build().unwrap_err();
+    assert!(format!("{:?}", err).contains("Invalid script for the shout helper"));
+}
+
+#[test]
+fn breadcrumbs_link_to_parent_chapters() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut cfg = Config::default();
+    cfg.set("output.html.breadcrumbs", true).unwrap();
+    let md = MDBook::load_with_config(temp.path(), cfg).unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(
+        temp.path().join("book/first/nested.html"),
+        &[
+            r#"<nav class="breadcrumbs" aria-label="Breadcrumbs">"#,
+            r#"<a href="../first/index.html">1. First Chapter</a>"#,
+            r#"<span aria-current="page">Nested Chapter</span>"#,
+        ],
+    );
+    assert_doesnt_contain_strings(
+        temp.path().join("book/first/index.html"),
+        &[r#"class="breadcrumbs""#],
+    );
+}
+
+#[test]
+fn page_toc_lists_the_chapters_headings() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    let book_toml = "[output.html.page-toc]\nenable = true\ndepth = 3\n";
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    write_file(temp.path(), "src/SUMMARY.md", b"- [Chapter](chapter.md)\n").unwrap();
+    let chapter = "# Chapter\n\n## Setup\n\n### The `config` file\n\n#### Too deep\n\n## Setup\n";
+    write_file(temp.path(), "src/chapter.md", chapter.as_bytes()).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let chapter = temp.path().join("book/chapter.html");
+    assert_contains_strings(
+        &chapter,
+        &[
+            r##"<li class="page-toc-level-2"><a href="#setup">Setup</a></li>"##,
+            r##"<li class="page-toc-level-3"><a href="#the-config-file">The config file</a></li>"##,
+            r##"<li class="page-toc-level-2"><a href="#setup-1">Setup</a></li>"##,
+            r##"<h2 id="setup-1"><a class="header" href="#setup-1">Setup</a></h2>"##,
+        ],
+    );
+    assert_doesnt_contain_strings(&chapter, &["<a href=\"#too-deep\"", "<a href=\"#chapter\""]);
+}
+
+#[test]
+fn sitemap_lists_the_chapters() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    let book_toml = "[output.html]\nsite-url = \"https://example.com/book/\"\n\n\
+                     [output.html.sitemap]\nrobots-txt = true\n";
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    let summary = "- [Chapter](chapter.md)\n  - [Nested](dir/nested.md)\n- [Draft]()\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    write_file(temp.path(), "src/chapter.md", b"# Chapter\n").unwrap();
+    write_file(temp.path(), "src/dir/nested.md", b"# Nested\n").unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let sitemap = temp.path().join("book/sitemap.xml");
+    assert_contains_strings(
+        &sitemap,
+        &[
+            "<loc>https://example.com/book/chapter.html</loc>\n    <lastmod>",
+            "<loc>https://example.com/book/dir/nested.html</loc>",
+        ],
+    );
+    assert_doesnt_contain_strings(&sitemap, &["Draft", "404"]);
+    assert_contains_strings(
+        temp.path().join("book/robots.txt"),
+        &["Sitemap: https://example.com/book/sitemap.xml"],
+    );
+}
+
+#[test]
+fn feed_has_an_entry_for_each_chapter_in_its_section() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    let book_toml = "[book]\ntitle = \"Product\"\n\n\
+                     [output.html]\nsite-url = \"https://example.com/docs\"\n\n\
+                     [output.html.feed]\nenable = true\nsection = \"Release notes\"\n";
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    let summary = "- [Guide](guide.md)\n\n# Release notes\n\n\
+                   - [1.0](releases/1.0.md)\n- [1.1](releases/1.1.md)\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    write_file(temp.path(), "src/guide.md", b"# Guide\n").unwrap();
+    let release = "---\ndate: 2021-05-01\n---\n# 1.0\n\nSee the [guide](../guide.md).\n";
+    write_file(temp.path(), "src/releases/1.0.md", release.as_bytes()).unwrap();
+    let release = "+++\ndate = 2021-06-01T12:00:00Z\n+++\n# 1.1\n";
+    write_file(temp.path(), "src/releases/1.1.md", release.as_bytes()).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let feed = temp.path().join("book/feed.xml");
+    let got = fs::read_to_string(&feed).unwrap();
+    let newest = got.find("<title>1.1</title>").unwrap();
+    let oldest = got.find("<title>1.0</title>").unwrap();
+    assert!(newest < oldest);
+    assert_contains_strings(
+        &feed,
+        &[
+            "<title>Product: Release notes</title>",
+            "<id>https://example.com/docs/feed.xml</id>",
+            "<updated>2021-06-01T12:00:00Z</updated>",
+            "<id>https://example.com/docs/releases/1.0.html</id>",
+            "<updated>2021-05-01T00:00:00Z</updated>",
+            "<content type=\"html\" xml:base=\"https://example.com/docs/\">&lt;h1&gt;1.0&lt;/h1&gt;",
+            "See the &lt;a href=&quot;releases/../guide.html&quot;&gt;guide&lt;/a&gt;",
+        ],
+    );
+    assert_doesnt_contain_strings(&feed, &["<title>Guide</title>"]);
+    assert_contains_strings(
+        temp.path().join("book/guide.html"),
+        &[
+            r#"<link rel="alternate" type="application/atom+xml" title="Product: Release notes" href="feed.xml">"#,
+        ],
+    );
+}
+
+#[test]
+fn feed_is_skipped_without_an_absolute_site_url() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    // Like `mdbook serve` sets it
+    let book_toml = "[output.html]\nsite-url = \"/\"\n\n\
+                     [output.html.feed]\nenable = true\nsection = \"Intro\"\n";
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    write_file(temp.path(), "src/SUMMARY.md", b"- [Intro](intro.md)\n").unwrap();
+    write_file(temp.path(), "src/intro.md", b"# Intro\n").unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    assert!(!temp.path().join("book/feed.xml").exists());
+    assert_doesnt_contain_strings(temp.path().join("book/intro.html"), &["feed.xml"]);
+}
+
+#[test]
+fn pages_have_social_metadata() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    let book_toml = "[book]\ntitle = \"Book\"\n\n[output.html]\n\
+                     site-url = \"https://example.com/book/\"\n\
+                     social-image = \"cover.png\"\n";
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    let summary = "- [Intro](intro.md)\n- [Shared](dir/shared.md)\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    let intro = "# Intro\n\nThis book is about \"things\".\n\nMore.\n";
+    write_file(temp.path(), "src/intro.md", intro.as_bytes()).unwrap();
+    let shared = "---\ndescription: Custom\nimage: https://cdn.example.com/a.png\n---\n# Shared\n";
+    write_file(temp.path(), "src/dir/shared.md", shared.as_bytes()).unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(
+        temp.path().join("book/intro.html"),
+        &[
+            r#"<meta name="description" content="This book is about &quot;things&quot;.">"#,
+            r#"<link rel="canonical" href="https://example.com/book/intro.html">"#,
+            r#"<meta property="og:title" content="Intro - Book">"#,
+            r#"<meta property="og:image" content="https://example.com/book/cover.png">"#,
+            r#"<meta name="twitter:card" content="summary_large_image">"#,
+        ],
+    );
+    assert_contains_strings(
+        temp.path().join("book/dir/shared.html"),
+        &[
+            r#"<meta property="og:description" content="Custom">"#,
+            r#"<meta property="og:url" content="https://example.com/book/dir/shared.html">"#,
+            r#"<meta property="og:image" content="https://cdn.example.com/a.png">"#,
+        ],
+    );
+}
+
+#[test]
+fn translations_have_their_own_canonical_urls() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    let book_toml = "[book]\nmultilingual = true\nlanguage = \"en\"\n\n\
+                     [language.en]\n[language.ja]\n\n[output.html]\n\
+                     site-url = \"https://example.com/book/\"\n\
+                     social-image = \"cover.png\"\n";
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    for language in &["en", "ja"] {
+        let path = format!("src/{}/SUMMARY.md", language);
+        write_file(temp.path(), path, b"- [Intro](intro.md)\n").unwrap();
+        let path = format!("src/{}/intro.md", language);
+        write_file(temp.path(), path, b"# Intro").unwrap();
+    }
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(
+        temp.path().join("book/ja/intro.html"),
+        &[
+            r#"<link rel="canonical" href="https://example.com/book/ja/intro.html">"#,
+            r#"<meta property="og:url" content="https://example.com/book/ja/intro.html">"#,
+            r#"<meta property="og:image" content="https://example.com/book/ja/cover.png">"#,
+        ],
+    );
+}
+
+#[test]
+fn nested_summaries_are_included() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    let summary = "# Summary\n\n- [Intro](intro.md)\n- [API](api/SUMMARY.md)\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    write_file(temp.path(), "src/intro.md", b"# Intro\n").unwrap();
+    let api_summary = "# API\n\n- [Calls](calls.md)\n    - [Errors](./errors.md)\n";
+    write_file(temp.path(), "src/api/SUMMARY.md", api_summary.as_bytes()).unwrap();
+    write_file(temp.path(), "src/api/calls.md", b"# Calls\n").unwrap();
+    write_file(temp.path(), "src/api/errors.md", b"# Errors\n").unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(
+        temp.path().join("book/api/errors.html"),
+        &[
+            r#"<a href="../api/calls.html"><strong aria-hidden="true">2.</strong> Calls</a>"#,
+            r#"<a href="../api/errors.html" class="active"><strong aria-hidden="true">2.1.</strong> Errors</a>"#,
+        ],
+    );
+}
+
+#[test]
+fn external_links_are_in_the_sidebar_but_not_the_navigation() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    let summary =
+        "- [First](first.md)\n- [API docs](https://docs.rs/mdbook)\n- [Second](second.md)\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    write_file(temp.path(), "src/first.md", b"# First\n").unwrap();
+    write_file(temp.path(), "src/second.md", b"# Second\n").unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let first = temp.path().join("book/first.html");
+    assert_contains_strings(
+        &first,
+        &[
+            r#"<a href="https://docs.rs/mdbook" class="external-link">API docs <i class="fa fa-external-link" aria-hidden="true"></i></a>"#,
+            r#"<a href="second.html"><strong aria-hidden="true">2.</strong> Second</a>"#,
+            r#"<a rel="next" href="second.html""#,
+        ],
+    );
+    assert!(!temp.path().join("book/docs.rs").exists());
+}
+
+#[test]
+fn parts_can_be_numbered_in_other_styles() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    let book_toml = r#"
+        [book.numbering.parts."Appendices"]
+        appendix = true
+
+        [output.html]
+        breadcrumbs = true
+    "#;
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    let summary = "- [Intro](intro.md)\n- [Usage](usage.md)\n\n\
+                   # Appendices\n\n- [Glossary](glossary.md)\n    - [Terms](terms.md)\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    for name in &["intro", "usage", "glossary", "terms"] {
+        write_file(temp.path(), format!("src/{}.md", name), b"# Chapter\n").unwrap();
+    }
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    let toc = [
+        r#"<strong aria-hidden="true">2.</strong> Usage</a>"#,
+        r#"<strong aria-hidden="true">A.</strong> Glossary</a>"#,
+        r#"<strong aria-hidden="true">A.1.</strong> Terms</a>"#,
+    ];
+    assert_contains_strings(temp.path().join("book/terms.html"), &toc);
+    assert_contains_strings(temp.path().join("book/print.html"), &toc);
+    assert_contains_strings(
+        temp.path().join("book/terms.html"),
+        &[r#"<a href="glossary.html">A. Glossary</a>"#],
+    );
+}
+
+#[test]
+fn hidden_chapters_are_rendered_but_not_listed() {
+    let temp = TempFileBuilder::new().prefix("mdbook").tempdir().unwrap();
+    let summary =
+        "- [First](first.md)\n- [Legal notice](legal.md \"hidden\")\n- [Second](second.md)\n";
+    write_file(temp.path(), "src/SUMMARY.md", summary.as_bytes()).unwrap();
+    write_file(temp.path(), "src/first.md", b"# First\n").unwrap();
+    write_file(
+        temp.path(),
+        "src/legal.md",
+        b"# Legal\n\nAll rights reserved.\n",
+    )
+    .unwrap();
+    write_file(temp.path(), "src/second.md", b"# Second\n").unwrap();
+
+    let md = MDBook::load(temp.path()).unwrap();
+    md.build().unwrap();
+
+    assert_contains_strings(
+        temp.path().join("book/legal.html"),
+        &["All rights reserved."],
+    );
+    let first = temp.path().join("book/first.html");
+    assert_contains_strings(
+        &first,
+        &[
+            r#"<a href="second.html"><strong aria-hidden="true">2.</strong> Second</a>"#,
+            r#"<a rel="next" href="second.html""#,
+        ],
+    );
+    assert_doesnt_contain_strings(&first, &["legal.html"]);
+    assert_doesnt_contain_strings(
+        temp.path().join("book/print.html"),
+        &["legal.html", "All rights reserved."],
+    );
+}
+
 fn remove_absolute_components(path: &Path) -> impl Iterator<Item = Component> + '_ {
     path.components().skip_while(|c| match c {
         Component::Prefix(_) | Component::RootDir => true,
@@ -2,7 +2,10 @@ mod dummy_book;
 
 use crate::dummy_book::DummyBook;
 
+use mdbook::book::{BookItem, Outcome, TestOptions};
+use mdbook::utils::fs::write_file;
 use mdbook::MDBook;
+use std::path::Path;
 
 #[test]
 fn mdbook_can_correctly_test_a_passing_book() {
@@ -24,3 +27,107 @@ fn mdbook_detects_book_with_failing_tests() {
 
     assert!(md.test(vec![]).is_err());
 }
+
+#[test]
+fn failing_code_blocks_are_reported_with_their_chapter() {
+    let temp = DummyBook::new().with_passing_test(false).build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+
+    let report = md.test_with_options(&TestOptions::default()).unwrap();
+    assert!(report.failed());
+
+    let nested = report
+        .chapters
+        .iter()
+        .find(|ch| ch.path == Path::new("first/nested.md"))
+        .unwrap();
+    assert!(nested.failed());
+    assert!(nested
+        .blocks
+        .iter()
+        .any(|block| matches!(block.outcome, Outcome::Failed(_))));
+    assert!(report
+        .chapters
+        .iter()
+        .filter(|ch| ch.path != Path::new("first/nested.md"))
+        .all(|ch| !ch.failed()));
+}
+
+#[test]
+fn tests_can_be_limited_to_a_single_chapter() {
+    let temp = DummyBook::new().with_passing_test(false).build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+
+    let options = TestOptions {
+        chapter: Some("Introduction"),
+        ..Default::default()
+    };
+    let report = md.test_with_options(&options).unwrap();
+    assert_eq!(report.chapters.len(), 1);
+    assert!(!report.failed());
+
+    let options = TestOptions {
+        chapter: Some("missing.md"),
+        ..Default::default()
+    };
+    assert!(md.test_with_options(&options).is_err());
+}
+
+#[test]
+fn chapters_are_reported_in_book_order_when_tested_in_parallel() {
+    let temp = DummyBook::new().build().unwrap();
+    let mut md = MDBook::load(temp.path()).unwrap();
+
+    let serial = TestOptions {
+        jobs: Some(1),
+        ..Default::default()
+    };
+    let parallel = TestOptions {
+        jobs: Some(4),
+        ..Default::default()
+    };
+
+    let serial = md.test_with_options(&serial).unwrap();
+    let parallel = md.test_with_options(&parallel).unwrap();
+    assert_eq!(serial, parallel);
+
+    let book_order: Vec<_> = md
+        .iter()
+        .filter_map(|item| match item {
+            BookItem::Chapter(ch) => ch.path.clone(),
+            _ => None,
+        })
+        .collect();
+    let tested: Vec<_> = parallel.chapters.iter().map(|ch| ch.path.clone()).collect();
+    assert_eq!(tested, book_order);
+}
+
+#[test]
+#[cfg(unix)]
+fn code_blocks_in_other_languages_use_the_configured_runner() {
+    let temp = DummyBook::new().build().unwrap();
+    let book_toml = r#"
+        [book]
+        title = "Dummy Book"
+
+        [test.runners.sh]
+        command = "sh -e"
+        "#;
+    write_file(temp.path(), "book.toml", book_toml.as_bytes()).unwrap();
+    let chapter = "# Shell\n\n```sh\ntrue\n```\n\n```sh\nfalse\n```\n";
+    write_file(&temp.path().join("src"), "intro.md", chapter.as_bytes()).unwrap();
+
+    let mut md = MDBook::load(temp.path()).unwrap();
+    let options = TestOptions {
+        chapter: Some("intro.md"),
+        ..Default::default()
+    };
+    let report = md.test_with_options(&options).unwrap();
+
+    let outcomes: Vec<_> = report.chapters[0]
+        .blocks
+        .iter()
+        .map(|block| (block.line, block.outcome == Outcome::Passed))
+        .collect();
+    assert_eq!(outcomes, vec![(3, true), (7, false)]);
+}